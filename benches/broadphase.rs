@@ -0,0 +1,169 @@
+//! Compares the broadphases on large circle scenes where every body moves a little
+//! each tick, as in a settled simulation. A scene mixing circles with long thin
+//! rectangles compares the number of candidate pairs to the actual overlaps.
+//!
+//! Run with `cargo bench --bench broadphase`.
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use game_engine::engine::init_utils::create_grid_positions;
+use game_engine::engine::physics_engine::broadphase::{
+    AabbTree, BlockMap, BroadPhase, CandidateStatistics, IncrementalBlockMap,
+    SparseSpatialSubdivision, SpatialSubdivision, SweepAndPrune,
+};
+use game_engine::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+use game_engine::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+
+const NUM_TICKS: u32 = 100;
+const RADIUS: f32 = 2.0;
+const SPACING: f32 = 5.0;
+
+/// Grid of circles placed in the positive quadrant, which is where `BlockMap` expects them
+fn circle_scene(num_cols: u32, num_rows: u32) -> (Vec<RigidBody>, f32) {
+    let width = num_cols.max(num_rows) as f32 * SPACING;
+    let bodies = create_grid_positions(num_cols, num_rows, SPACING, None)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            RigidBodyBuilder::default()
+                .id(i)
+                .position([p.x + width / 2.0, p.y + width / 2.0, 0.0])
+                .body_type(RigidBodyType::Circle { radius: RADIUS })
+                .build()
+        })
+        .collect();
+    (bodies, width + SPACING)
+}
+
+/// Small circles mixed with long thin rectangles, as in a level built from planks
+fn plank_scene(num_circles: usize, num_planks: usize) -> Vec<RigidBody> {
+    let mut rng = StdRng::seed_from_u64(1);
+    let width = 1000.0;
+    (0..num_circles + num_planks)
+        .map(|i| {
+            let builder = RigidBodyBuilder::default().id(i).position([
+                rng.gen_range(0.0..width),
+                rng.gen_range(0.0..width),
+                0.0,
+            ]);
+            match i < num_circles {
+                true => builder.body_type(RigidBodyType::Circle { radius: RADIUS }),
+                false => builder
+                    .body_type(RigidBodyType::Rectangle {
+                        width: 200.0,
+                        height: 4.0,
+                    })
+                    .rotation(rng.gen_range(-0.3..0.3)),
+            }
+            .build()
+        })
+        .collect()
+}
+
+/// Moves every body a fraction of its radius such that few bodies change cell
+fn jitter(bodies: &mut [RigidBody], rng: &mut StdRng) {
+    bodies.iter_mut().for_each(|b| {
+        b.position.x += rng.gen_range(-0.1..0.1) * RADIUS;
+        b.position.y += rng.gen_range(-0.1..0.1) * RADIUS;
+    });
+}
+
+fn run<T, F>(name: &str, bodies: &[RigidBody], mut step: F) -> Duration
+where
+    F: FnMut(&[RigidBody]) -> T,
+{
+    let mut bodies = bodies.to_vec();
+    let mut rng = StdRng::seed_from_u64(0);
+    // Warm up, lets incremental structures reach their steady state
+    let _ = step(&bodies);
+
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..NUM_TICKS {
+        jitter(&mut bodies, &mut rng);
+        let start = Instant::now();
+        let candidates = step(&bodies);
+        elapsed += start.elapsed();
+        std::hint::black_box(candidates);
+    }
+    let per_tick = elapsed / NUM_TICKS;
+    println!("  {name:<24} {per_tick:>12.3?} per tick");
+    per_tick
+}
+
+fn main() {
+    for (num_cols, num_rows) in [(100, 100), (200, 200), (300, 300)] {
+        let (bodies, width) = circle_scene(num_cols, num_rows);
+        println!("{} circles", bodies.len());
+
+        let mut blockmap = BlockMap::new(width);
+        let blockmap_time = run("BlockMap", &bodies, |b| {
+            blockmap.collision_detection(b.iter())
+        });
+
+        let mut spatial_subdivision = SpatialSubdivision::new();
+        run("SpatialSubdivision", &bodies, |b| {
+            spatial_subdivision.collision_detection(b.iter())
+        });
+
+        let mut sparse_spatial_subdivision = SparseSpatialSubdivision::new();
+        run("SparseSpatialSubdivision", &bodies, |b| {
+            sparse_spatial_subdivision.collision_detection(b.iter())
+        });
+
+        let mut incremental = IncrementalBlockMap::new();
+        let mut candidates: Vec<CollisionCandidates> = vec![];
+        let incremental_time = run("IncrementalBlockMap", &bodies, |b| {
+            incremental.collision_detection_into(b.iter(), &mut candidates);
+            candidates.len()
+        });
+
+        let mut tree = AabbTree::new();
+        let mut tree_candidates: Vec<CollisionCandidates> = vec![];
+        run("AabbTree", &bodies, |b| {
+            tree.collision_detection_into(b.iter(), &mut tree_candidates);
+            tree_candidates.len()
+        });
+
+        let mut sweep_and_prune = SweepAndPrune::default();
+        let mut sweep_candidates: Vec<CollisionCandidates> = vec![];
+        run("SweepAndPrune", &bodies, |b| {
+            sweep_and_prune.collision_detection_into(b.iter(), &mut sweep_candidates);
+            sweep_candidates.len()
+        });
+
+        println!(
+            "  speedup over BlockMap: {:.1}x",
+            blockmap_time.as_secs_f64() / incremental_time.as_secs_f64()
+        );
+    }
+
+    let bodies = plank_scene(10000, 100);
+    println!("10000 circles and 100 planks");
+    let mut exact = AabbTree::with_margin(0.0);
+    let overlapping =
+        CandidateStatistics::from_candidates(exact.collision_detection(bodies.iter()).iter());
+    println!(
+        "  overlapping bounding boxes: {} pairs",
+        overlapping.num_pairs
+    );
+
+    for percentile in [1.0, 0.9] {
+        let mut spatial_subdivision = SpatialSubdivision::with_cell_size_percentile(percentile);
+        run(
+            &format!("SpatialSubdivision (percentile {percentile})"),
+            &bodies,
+            |b| spatial_subdivision.collision_detection(b.iter()),
+        );
+        let passes = spatial_subdivision.collision_detection(bodies.iter());
+        let statistics = spatial_subdivision.statistics();
+        println!(
+            "  cells: {:.1}x{:.1}, oversized bodies: {}, {}",
+            statistics.cell_width,
+            statistics.cell_height,
+            statistics.num_oversized,
+            CandidateStatistics::from_candidates(passes.iter().flatten())
+        );
+    }
+}
@@ -12,9 +12,10 @@ use game_engine::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder
 use game_engine::engine::physics_engine::constraint::box_constraint::BoxConstraint;
 use game_engine::engine::physics_engine::constraint::resolver::inelastic::InelasticConstraintResolver;
 use game_engine::engine::physics_engine::constraint::Constraint;
+use game_engine::engine::physics_engine::force::{ForceRegistry, Gravity};
 use game_engine::engine::physics_engine::integrator::verlet::VerletIntegrator;
 use game_engine::engine::physics_engine::narrowphase::naive::Naive;
-use game_engine::engine::physics_engine::narrowphase::NarrowPhase;
+use game_engine::engine::physics_engine::narrowphase::{NarrowPhase, PairCache};
 use game_engine::engine::renderer_engine::{
     RenderBodyBuilder, RenderBodyShape, RenderEngineControl,
 };
@@ -31,6 +32,7 @@ where
 {
     dt: f32,
     integrator: VerletIntegrator,
+    forces: ForceRegistry,
     constraint: C,
     broadphase: B,
     narrowphase: N,
@@ -45,8 +47,8 @@ where
 {
     pub fn new(constraint: C, broadphase: B, narrowphase: N) -> Self {
         let dt = 0.001;
-        //let acceleration = Vector3::new(0., (-9.82 / dt)*60., 0.);
-        //let bodies = spawn_bodies(RADIUS, acceleration, NUM_COLS, NUM_ROWS);
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0., (-9.82 / dt) * 60., 0.]));
         // TODO:
         // - RectRect collision
         // - Refactor CircleCircle collision using techniques in RectCircle and RectRect
@@ -107,6 +109,7 @@ where
         return Self {
             dt,
             integrator,
+            forces,
             constraint,
             broadphase,
             narrowphase,
@@ -122,6 +125,7 @@ where
     N: NarrowPhase + Sync,
 {
     fn update(&mut self) {
+        self.forces.apply(self.ecs.rigid_body_iter_mut(), self.dt);
         self.integrator
             .update(self.ecs.rigid_body_iter_mut(), self.dt);
 
@@ -133,27 +137,14 @@ where
             .broadphase
             .collision_detection(self.ecs.rigid_body_iter());
 
-        let pass1 = &candidates[0];
-        let pass2 = &candidates[1];
-        let pass3 = &candidates[2];
-        let pass4 = &candidates[3];
-
         let mut bodies: Vec<&mut RigidBody> = self.ecs.rigid_body_iter_mut().collect();
-        let _graphs_1: Vec<CollisionGraph> = pass1
-            .iter()
-            .filter_map(|c| self.narrowphase.collision_detection(&mut bodies, c))
-            .collect();
-        let _graphs_2: Vec<CollisionGraph> = pass2
-            .iter()
-            .filter_map(|c| self.narrowphase.collision_detection(&mut bodies, c))
-            .collect();
-        let _graphs_3: Vec<CollisionGraph> = pass3
-            .iter()
-            .filter_map(|c| self.narrowphase.collision_detection(&mut bodies, c))
-            .collect();
-        let _graphs_4: Vec<CollisionGraph> = pass4
+        let mut pairs = PairCache::new();
+        let _graphs: Vec<CollisionGraph> = candidates
             .iter()
-            .filter_map(|c| self.narrowphase.collision_detection(&mut bodies, c))
+            .flat_map(|pass| {
+                self.narrowphase
+                    .parallel_collision_detection(&mut bodies, pass, &mut pairs)
+            })
             .collect();
 
         //panic!();
@@ -7,14 +7,15 @@ use game_engine::engine::event::ElementState;
 use game_engine::engine::game_engine::GameEngineBuilder;
 use game_engine::engine::physics_engine::broadphase::{BroadPhase, SpatialSubdivision};
 use game_engine::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
-use game_engine::engine::physics_engine::collision::SimpleCollisionSolver;
+use game_engine::engine::physics_engine::collision::{CollisionFilter, SimpleCollisionSolver};
 use game_engine::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
 use game_engine::engine::physics_engine::constraint::box_constraint::BoxConstraint;
 use game_engine::engine::physics_engine::constraint::resolver::inelastic::InelasticConstraintResolver;
 use game_engine::engine::physics_engine::constraint::Constraint;
 use game_engine::engine::physics_engine::integrator::verlet::VerletIntegrator;
 use game_engine::engine::physics_engine::narrowphase::naive::Naive;
-use game_engine::engine::physics_engine::narrowphase::NarrowPhase;
+use game_engine::engine::physics_engine::narrowphase::{NarrowPhase, PairCache};
+use game_engine::engine::physics_engine::query::SpatialQuery;
 use game_engine::engine::renderer_engine::{
     RenderBodyBuilder, RenderBodyShape, RenderEngineControl,
 };
@@ -25,7 +26,7 @@ use game_engine::engine::RenderEngine;
 struct CollisionResolution<C, B, N>
 where
     C: Constraint,
-    B: BroadPhase<[Vec<CollisionCandidates>; 4]>,
+    B: BroadPhase<[Vec<CollisionCandidates>; 4]> + SpatialQuery,
     N: NarrowPhase + Sync,
 {
     dt: f32,
@@ -43,7 +44,7 @@ where
 impl<C, B, N> CollisionResolution<C, B, N>
 where
     C: Constraint,
-    B: BroadPhase<[Vec<CollisionCandidates>; 4]>,
+    B: BroadPhase<[Vec<CollisionCandidates>; 4]> + SpatialQuery,
     N: NarrowPhase + Sync,
 {
     pub fn new(constraint: C, broadphase: B, narrowphase: N) -> Self {
@@ -121,7 +122,7 @@ where
 impl<C, B, N> PhysicsEngine for CollisionResolution<C, B, N>
 where
     C: Constraint,
-    B: BroadPhase<[Vec<CollisionCandidates>; 4]>,
+    B: BroadPhase<[Vec<CollisionCandidates>; 4]> + SpatialQuery,
     N: NarrowPhase + Sync,
 {
     fn update(&mut self) {
@@ -173,10 +174,11 @@ where
         // TODO: Now the collision wont have any resolution as for rect rect only
         // detection is implemented
         // TODO: Make the object collide but they do not shoot away (crf = 0.0)
-        let collision_graph = match self
-            .narrowphase
-            .collision_detection(&mut bodies, &candidates)
-        {
+        let collision_graph = match self.narrowphase.collision_detection(
+            &mut bodies,
+            &candidates,
+            &mut PairCache::new(),
+        ) {
             Some(graph) => graph,
             None => return,
         };
@@ -212,18 +214,22 @@ where
                     state: ElementState::Pressed,
                 } => {
                     self.cursor_state = ElementState::Pressed;
-                    let bodies: Vec<&mut RigidBody> = self
+                    let bodies: Vec<&RigidBody> = self
                         .ecs
-                        .rigid_body_iter_mut()
+                        .rigid_body_iter()
                         .filter(|rb| rb.body_type != RigidBodyType::Unknown)
                         .collect();
-                    self.selected_body = if bodies[0].click_inside(self.cursor_pos) {
-                        0
-                    } else if bodies[1].click_inside(self.cursor_pos) {
-                        1
-                    } else {
-                        usize::MAX
-                    };
+                    self.broadphase.refresh_query_grid(bodies.iter().copied());
+                    self.selected_body = self
+                        .broadphase
+                        .query_point(
+                            bodies.iter().copied(),
+                            self.cursor_pos,
+                            &CollisionFilter::default(),
+                        )
+                        .first()
+                        .copied()
+                        .unwrap_or(usize::MAX);
 
                     if self.selected_body != usize::MAX {
                         self.click_position_body_center_offset = (
@@ -282,7 +288,7 @@ where
 impl<C, B, N> RenderEngine for CollisionResolution<C, B, N>
 where
     C: Constraint,
-    B: BroadPhase<[Vec<CollisionCandidates>; 4]>,
+    B: BroadPhase<[Vec<CollisionCandidates>; 4]> + SpatialQuery,
     N: NarrowPhase + Sync,
 {
     fn render(&mut self, engine_ctl: &mut RenderEngineControl) {
@@ -1,35 +1,41 @@
 extern crate game_engine;
 
-use cgmath::Vector3;
+use cgmath::{Vector3, Vector4};
 
 use game_engine::engine::entity::{EntityBuilder, EntityComponentStorage, EntityHandle};
+use game_engine::engine::event::key_event::{Key, KeyEvent};
+use game_engine::engine::event::user_event::UserEvent;
+use game_engine::engine::event::ElementState;
 use game_engine::engine::game_engine::GameEngineBuilder;
 use game_engine::engine::physics_engine::broadphase::BlockMap;
 use game_engine::engine::physics_engine::broadphase::BroadPhase;
 use game_engine::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
-use game_engine::engine::physics_engine::collision::CollisionGraph;
 use game_engine::engine::physics_engine::collision::SimpleCollisionSolver;
+use game_engine::engine::physics_engine::collision::{CollisionFilter, CollisionGraph};
 use game_engine::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
 use game_engine::engine::physics_engine::constraint::box_constraint::BoxConstraint;
 use game_engine::engine::physics_engine::constraint::resolver::elastic::ElasticConstraintResolver;
 use game_engine::engine::physics_engine::constraint::Constraint;
 use game_engine::engine::physics_engine::integrator::verlet::VerletIntegrator;
 use game_engine::engine::physics_engine::narrowphase::naive::Naive;
-use game_engine::engine::physics_engine::narrowphase::NarrowPhase;
+use game_engine::engine::physics_engine::narrowphase::{NarrowPhase, PairCache};
+use game_engine::engine::physics_engine::query::SpatialQuery;
 use game_engine::engine::renderer_engine::asset::asset::Asset;
 use game_engine::engine::renderer_engine::asset::font::{Font, Writer};
-use game_engine::engine::renderer_engine::asset::sprite_sheet::SpriteCoordinate;
+use game_engine::engine::renderer_engine::asset::sprite_sheet::{SpriteCoordinate, SpriteSheet};
 use game_engine::engine::renderer_engine::post_process::PostProcessFilterId;
 use game_engine::engine::renderer_engine::{
-    RenderBody, RenderBodyBuilder, RenderBodyShape, RenderEngineControl,
+    AnimationClip, AnimationMode, Animator, Frame, RenderBody, RenderBodyBuilder, RenderBodyShape,
+    RenderEngineControl, TextureHandle,
 };
 use game_engine::engine::util::color::{blue, green};
+use game_engine::engine::util::physics_debug_draw::PhysicsDebugDraw;
 use game_engine::engine::util::zero;
 use game_engine::engine::{PhysicsEngine, RenderEngine};
 
 pub struct DebugPhysicsEngine<B>
 where
-    B: BroadPhase<Vec<CollisionCandidates>>,
+    B: BroadPhase<Vec<CollisionCandidates>> + SpatialQuery,
 {
     dt: f32,
     integrator: VerletIntegrator,
@@ -37,11 +43,16 @@ where
     broadphase: B,
     narrowphase: Box<dyn NarrowPhase>,
     ecs: EntityComponentStorage,
+    body_colors: Vec<Vector4<f32>>,
+    cursor_pos: (f32, f32),
+    collision_graphs: Vec<CollisionGraph>,
+    debug_draw: PhysicsDebugDraw,
+    chest_texture: Option<TextureHandle>,
 }
 
 impl<B> DebugPhysicsEngine<B>
 where
-    B: BroadPhase<Vec<CollisionCandidates>>,
+    B: BroadPhase<Vec<CollisionCandidates>> + SpatialQuery,
 {
     pub fn new(window_size: &(u32, u32), broadphase: B) -> Self {
         let dt = 0.001;
@@ -62,6 +73,11 @@ where
                         .sprite_coord(SpriteCoordinate::new([2., 0.], [3., 1.]))
                         .build(),
                 )
+                .animator(Animator::new(
+                    AnimationClip::new("idle")
+                        .row(0, 0, 3, 200)
+                        .mode(AnimationMode::PingPong),
+                ))
                 .build(),
         );
 
@@ -142,6 +158,8 @@ where
             0.0,
         ));
         let narrowphase = Box::new(Naive::new(SimpleCollisionSolver::new()));
+        let body_colors = ecs.render_body_iter().map(|b| b.color).collect();
+        let cursor_pos = (f32::MAX, f32::MAX);
 
         Self {
             dt,
@@ -150,55 +168,67 @@ where
             broadphase,
             narrowphase,
             ecs,
+            body_colors,
+            cursor_pos,
+            collision_graphs: vec![],
+            // Toggled with F3
+            debug_draw: PhysicsDebugDraw::new().enabled(true),
+            chest_texture: None,
         }
     }
 }
 
 impl<B> RenderEngine for DebugPhysicsEngine<B>
 where
-    B: BroadPhase<Vec<CollisionCandidates>>,
+    B: BroadPhase<Vec<CollisionCandidates>> + SpatialQuery,
 {
     fn render(&mut self, engine_ctl: &mut RenderEngineControl) {
-        let target_texture_handle = engine_ctl.request_texture_handle();
+        // A second sheet loaded at runtime, drawn in the same call as the sprite sheet
+        if self.chest_texture.is_none() {
+            let chest = SpriteSheet::new(include_bytes!("../assets/chest.png"), 16, 16);
+            let handle = engine_ctl
+                .load_texture(&chest)
+                .expect("Failed to load the chest texture");
+            let rect = self.ecs.render_body_iter_mut().nth(3).unwrap();
+            rect.texture = handle;
+            rect.sprite_coord = SpriteCoordinate::new([0., 0.], [1., 1.]);
+            self.chest_texture = Some(handle);
+        }
 
         let entities: Vec<EntityHandle> = self.ecs.entities_iter().collect();
         let rect_instances = game_engine::engine::util::get_rectangle_instances(&entities[..]);
         let circle_instances = game_engine::engine::util::get_circle_instances(&entities[..]);
-        engine_ctl
-            .render_background(&target_texture_handle)
-            .unwrap();
-
-        engine_ctl
-            .render_rectangles(&target_texture_handle, &rect_instances, false)
-            .unwrap();
-        engine_ctl
-            .render_circles(&target_texture_handle, &circle_instances, false)
-            .unwrap();
-
-        //let target_texture_handle = engine_ctl.run_post_process_filter(
-        //    &PostProcessFilterId::Tint, &target_texture_handle).unwrap();
 
         let text_size = 110.;
         let text1 = Writer::write("HELLO WORLD", &[-400.0, -100.0, 0.0], text_size);
         let text2 = Writer::write("012 345 678 9", &[-700.0, -400.0, 0.0], text_size);
-        engine_ctl
-            .render_text(&target_texture_handle, text1, false)
-            .unwrap();
-        engine_ctl
-            .render_text(&target_texture_handle, text2, false)
-            .unwrap();
 
-        //let target_texture_handle = engine_ctl
-        //    .run_post_process_filter(&PostProcessFilterId::Gray, &target_texture_handle)
-        //    .unwrap();
+        self.debug_draw.clear();
+        let bodies: Vec<&RigidBody> = self.ecs.rigid_body_iter().collect();
+        self.debug_draw.query_grid(self.broadphase.query_grid());
+        self.debug_draw.constraint(self.constraint.as_ref());
+        self.debug_draw.bodies(bodies.into_iter());
+        for graph in self.collision_graphs.iter() {
+            self.debug_draw.collision_graph(graph);
+        }
+
+        // Everything is drawn in order and submitted at once
+        let frame = Frame::new()
+            .background()
+            .rectangles(&rect_instances)
+            .circles(&circle_instances)
+            //.post_process_filter(PostProcessFilterId::Tint)
+            .primitives(self.debug_draw.primitives())
+            .text(&text1)
+            .text(&text2);
         engine_ctl
-            .present(&target_texture_handle)
+            .render_frame(&frame)
             .expect("Failed to present texture");
     }
 }
 impl<B> PhysicsEngine for DebugPhysicsEngine<B>
 where
-    B: BroadPhase<Vec<CollisionCandidates>>,
+    B: BroadPhase<Vec<CollisionCandidates>> + SpatialQuery,
 {
     fn update(&mut self) {
         self.integrator
@@ -208,25 +238,52 @@ where
             .rigid_body_iter_mut()
             .for_each(|b| self.constraint.apply_constraint(b));
 
+        self.ecs.animate();
+
         let candidates = self
             .broadphase
             .collision_detection(self.ecs.rigid_body_iter());
 
         let mut rigid_bodies: Vec<&mut RigidBody> = self.ecs.rigid_body_iter_mut().collect();
-        let graphs: Vec<CollisionGraph> = candidates
+        let mut pairs = PairCache::new();
+        self.collision_graphs = candidates
             .iter()
-            .filter_map(|c| self.narrowphase.collision_detection(&mut rigid_bodies, c))
+            .filter_map(|c| {
+                self.narrowphase
+                    .collision_detection(&mut rigid_bodies, c, &mut pairs)
+            })
             .collect();
 
-        let rect_id = 3;
+        // The narrowphase moved the bodies since the collision detection
+        self.broadphase
+            .refresh_query_grid(self.ecs.rigid_body_iter());
+        let hovered_bodies = self.broadphase.query_point(
+            self.ecs.rigid_body_iter(),
+            self.cursor_pos,
+            &CollisionFilter::default(),
+        );
+
         let mut render_bodies: Vec<&mut RenderBody> = self.ecs.render_body_iter_mut().collect();
-        render_bodies[rect_id].color = Vector3::new(0.0, 255.0, 255.0);
-        for g in graphs {
-            for node in g.collisions {
-                if node.body_i_idx == rect_id || node.body_j_idx == rect_id {
-                    render_bodies[rect_id].color = Vector3::new(255.0, 255.0, 0.0);
-                }
+        for (body, color) in render_bodies.iter_mut().zip(self.body_colors.iter()) {
+            body.color = *color;
+        }
+        for i in hovered_bodies {
+            render_bodies[i].color = Vector4::new(1.0, 0.0, 1.0, 1.0);
+        }
+    }
+
+    fn user_event(&mut self, event: UserEvent) {
+        match event {
+            UserEvent::CursorMoved(position) => {
+                self.cursor_pos = (position.x as f32, position.y as f32);
             }
+            UserEvent::CursorLeft => self.cursor_pos = (f32::MAX, f32::MAX),
+            UserEvent::Keyboard(KeyEvent {
+                key: Key::F3,
+                state: ElementState::Pressed,
+                repeat: false,
+            }) => self.debug_draw.toggle(),
+            _ => (),
         }
     }
 }
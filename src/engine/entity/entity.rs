@@ -1,9 +1,10 @@
-use crate::engine::renderer_engine::RenderBody;
+use crate::engine::renderer_engine::{Animator, RenderBody};
 use crate::engine::RigidBody;
 
 pub struct Entity {
     pub rigid_body: Option<RigidBody>,
     pub render_body: Option<RenderBody>,
+    pub animator: Option<Animator>,
 }
 
 pub struct EntityHandle<'a> {
@@ -16,6 +17,7 @@ impl Entity {
         Self {
             rigid_body: None,
             render_body: None,
+            animator: None,
         }
     }
 }
@@ -23,6 +25,7 @@ impl Entity {
 pub struct EntityBuilder {
     pub rigid_body: Option<RigidBody>,
     pub render_body: Option<RenderBody>,
+    pub animator: Option<Animator>,
 }
 
 impl EntityBuilder {
@@ -30,6 +33,7 @@ impl EntityBuilder {
         Self {
             rigid_body: None,
             render_body: None,
+            animator: None,
         }
     }
 
@@ -43,10 +47,17 @@ impl EntityBuilder {
         self
     }
 
+    /// Animates the sprite coordinate of the render body
+    pub fn animator(mut self, animator: Animator) -> Self {
+        self.animator = Some(animator);
+        self
+    }
+
     pub fn build(self) -> Entity {
         Entity {
             rigid_body: self.rigid_body,
             render_body: self.render_body,
+            animator: self.animator,
         }
     }
 }
@@ -1,11 +1,12 @@
 use super::{Entity, EntityHandle};
 
-use crate::engine::renderer_engine::RenderBody;
+use crate::engine::renderer_engine::{AnimationEvent, Animator, RenderBody};
 use crate::engine::RigidBody;
 
 pub struct EntityComponentStorage {
     pub rigid_bodies: Vec<Option<RigidBody>>,
     pub render_bodies: Vec<Option<RenderBody>>,
+    pub animators: Vec<Option<Animator>>,
 }
 
 impl EntityComponentStorage {
@@ -13,6 +14,7 @@ impl EntityComponentStorage {
         Self {
             rigid_bodies: vec![],
             render_bodies: vec![],
+            animators: vec![],
         }
     }
 
@@ -32,6 +34,29 @@ impl EntityComponentStorage {
         self.render_bodies.iter().filter_map(|rb| rb.as_ref())
     }
 
+    pub fn animator_iter_mut(&mut self) -> impl Iterator<Item = &mut Animator> {
+        self.animators.iter_mut().filter_map(|a| a.as_mut())
+    }
+
+    /// Advances every animator by one game tick and shows its current frame on the
+    /// render body of the entity. Returns the index of the entity with every event.
+    pub fn animate(&mut self) -> Vec<(usize, AnimationEvent)> {
+        let mut events = vec![];
+        let entities = std::iter::zip(self.animators.iter_mut(), self.render_bodies.iter_mut());
+        for (i, (animator, render_body)) in entities.enumerate() {
+            let Some(animator) = animator else {
+                continue;
+            };
+            if let Some(event) = animator.tick() {
+                events.push((i, event));
+            }
+            if let Some(render_body) = render_body {
+                render_body.sprite_coord = animator.sprite_coord().clone();
+            }
+        }
+        events
+    }
+
     pub fn entities_iter(&self) -> impl Iterator<Item = EntityHandle> {
         std::iter::zip(self.rigid_bodies.iter(), self.render_bodies.iter()).map(
             |(rigid, render)| EntityHandle {
@@ -45,12 +70,14 @@ impl EntityComponentStorage {
         // Note: Align all entities with None if the do not contain the component
         self.rigid_bodies.push(entity.rigid_body);
         self.render_bodies.push(entity.render_body);
+        self.animators.push(entity.animator);
     }
 
     pub fn remove_by_index(&mut self, idx: usize) -> Entity {
         let mut entity = Entity::new();
         entity.rigid_body = self.rigid_bodies.remove(idx);
         entity.render_body = self.render_bodies.remove(idx);
+        entity.animator = self.animators.remove(idx);
         entity
     }
 
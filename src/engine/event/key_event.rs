@@ -13,6 +13,8 @@ impl From<winit::keyboard::PhysicalKey> for Key {
             winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyA) => Key::A,
             winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyS) => Key::S,
             winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyD) => Key::D,
+            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F3) => Key::F3,
+            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F11) => Key::F11,
             _ => Key::Unknown,
         }
     }
@@ -33,5 +35,7 @@ pub enum Key {
     A,
     S,
     D,
+    F3,
+    F11,
     Unknown,
 }
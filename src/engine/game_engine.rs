@@ -4,22 +4,25 @@ use crate::engine::renderer_engine::asset::font::Font;
 use crate::engine::renderer_engine::asset::sprite_sheet::SpriteSheet;
 use crate::engine::renderer_engine::graphics_context::GraphicsContext;
 use crate::engine::renderer_engine::post_process::PostProcessFilterId;
-use crate::engine::renderer_engine::{RenderEngineControl, RenderEngineControlBuilder};
+use crate::engine::renderer_engine::{Camera2D, RenderEngineControl, RenderEngineControlBuilder};
 use crate::engine::{PhysicsEngine, RenderEngine};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::WindowEvent,
+    event::{ElementState, WindowEvent},
     event_loop::EventLoop,
-    window::{Window, WindowId},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Fullscreen, Window, WindowId},
 };
 
 pub struct GameEngine<'a, T: PhysicsEngine + RenderEngine> {
     window_size: PhysicalSize<u32>,
     window_title: String,
     window: Option<Arc<Window>>,
+    fullscreen: bool,
+    scale_factor: f64,
     last_tick: Instant,
     next_tick: Duration,
     tick_delta: Duration,
@@ -31,8 +34,10 @@ pub struct GameEngine<'a, T: PhysicsEngine + RenderEngine> {
     background: Option<Background>,
     font: Option<Font>,
     pp_filter: Vec<PostProcessFilterId>,
-    max_num_circle_instances: u32,
-    max_num_rectangle_instances: u32,
+    max_num_circle_instances: Option<u32>,
+    max_num_rectangle_instances: Option<u32>,
+    shrink_instance_buffers: bool,
+    atlas_size: Option<u32>,
 }
 
 impl<'a, T: PhysicsEngine + RenderEngine> GameEngine<'a, T> {
@@ -65,7 +70,8 @@ impl<'a, T: PhysicsEngine + RenderEngine> ApplicationHandler for GameEngine<'a,
         // but the init of graphics context because kind of messy
         let window_attributes = Window::default_attributes()
             .with_title(&self.window_title)
-            .with_inner_size(self.window_size);
+            .with_inner_size(self.window_size)
+            .with_fullscreen(self.fullscreen.then_some(Fullscreen::Borderless(None)));
 
         let window = Arc::new(
             event_loop
@@ -76,6 +82,12 @@ impl<'a, T: PhysicsEngine + RenderEngine> ApplicationHandler for GameEngine<'a,
         // Note: https://github.com/rust-windowing/winit/discussions/3667
         let window_handle = window.clone();
         let g_ctx = GraphicsContext::new(window_handle);
+        // The window may differ from the requested size, e.g. when fullscreen or scaled
+        let inner_size = window.inner_size();
+        if inner_size.width > 0 && inner_size.height > 0 {
+            self.window_size = inner_size;
+        }
+        self.scale_factor = window.scale_factor();
         self.window = Some(window);
 
         // Build the render engine with data from the physics engine
@@ -98,12 +110,35 @@ impl<'a, T: PhysicsEngine + RenderEngine> ApplicationHandler for GameEngine<'a,
             render_engine_ctl_builder
         };
 
-        let render_engine_ctl = render_engine_ctl_builder
-            .max_num_rectangle_instances(self.max_num_rectangle_instances)
-            .max_num_circle_instances(self.max_num_circle_instances)
+        render_engine_ctl_builder = if let Some(len) = self.max_num_circle_instances {
+            render_engine_ctl_builder.max_num_circle_instances(len)
+        } else {
+            render_engine_ctl_builder
+        };
+
+        render_engine_ctl_builder = if let Some(len) = self.max_num_rectangle_instances {
+            render_engine_ctl_builder.max_num_rectangle_instances(len)
+        } else {
+            render_engine_ctl_builder
+        };
+
+        render_engine_ctl_builder = if let Some(size) = self.atlas_size {
+            render_engine_ctl_builder.atlas_size(size)
+        } else {
+            render_engine_ctl_builder
+        };
+
+        let mut render_engine_ctl = render_engine_ctl_builder
+            .shrink_instance_buffers(self.shrink_instance_buffers)
             .add_post_process_filters(&mut self.pp_filter)
             .build(g_ctx, self.window_size);
 
+        // Start at the zoom a later change of the scale factor would lead to, such that
+        // the world has the same size on screen whichever display the window starts on
+        render_engine_ctl
+            .camera_mut()
+            .set_zoom(self.scale_factor as f32);
+
         self.render_engine_ctl = Some(render_engine_ctl);
     }
 
@@ -132,13 +167,48 @@ impl<'a, T: PhysicsEngine + RenderEngine> ApplicationHandler for GameEngine<'a,
                 }
             }
             WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => {
+                // A minimized window has no size, keep the last one until it is restored
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                self.window_size = size;
+                if let Some(ctl) = &mut self.render_engine_ctl {
+                    ctl.resize(size);
+                }
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // Keep the world the same size on screen, the new physical size of the
+                // window follows with a resize event
+                if let Some(ctl) = &mut self.render_engine_ctl {
+                    let camera = ctl.camera_mut();
+                    camera.set_zoom(camera.zoom() * (scale_factor / self.scale_factor) as f32);
+                }
+                self.scale_factor = scale_factor;
+            }
+            WindowEvent::KeyboardInput { event: ref key, .. }
+                if key.physical_key == PhysicalKey::Code(KeyCode::F11)
+                    && key.state == ElementState::Pressed
+                    && !key.repeat =>
+            {
+                self.fullscreen = !self.fullscreen;
+                if let Some(window) = &self.window {
+                    window.set_fullscreen(self.fullscreen.then_some(Fullscreen::Borderless(None)));
+                }
+                self.engine.user_event(UserEvent::from(event))
+            }
             WindowEvent::CursorMoved { position, .. } => {
-                let cursor_position_x = position.x - (self.window_size.width as f64 / 2.0);
-                let cursor_position_y = (self.window_size.height as f64 / 2.0) - position.y;
+                // The cursor is reported in world coordinates, as seen through the camera
+                let screen = [position.x as f32, position.y as f32];
+                let [cursor_position_x, cursor_position_y] = match &self.render_engine_ctl {
+                    Some(ctl) => ctl.camera().screen_to_world(screen),
+                    None => Camera2D::new((self.window_size.width, self.window_size.height))
+                        .screen_to_world(screen),
+                };
                 self.engine.user_event(UserEvent::CursorMoved(
                     super::event::cursor_moved_event::CursorMovedEvent {
-                        x: cursor_position_x,
-                        y: cursor_position_y,
+                        x: cursor_position_x as f64,
+                        y: cursor_position_y as f64,
                     },
                 ))
             }
@@ -160,10 +230,13 @@ pub struct GameEngineBuilder<T: PhysicsEngine + RenderEngine> {
     target_fps: u32,
     target_tpf: u32,
     window_title: String,
+    fullscreen: bool,
     font: Option<Font>,
     pp_filter: Vec<PostProcessFilterId>,
-    max_num_circle_instances: u32,
-    max_num_rectangle_instances: u32,
+    max_num_circle_instances: Option<u32>,
+    max_num_rectangle_instances: Option<u32>,
+    shrink_instance_buffers: bool,
+    atlas_size: Option<u32>,
 }
 
 impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
@@ -171,8 +244,6 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
         let window_size = (800, 600);
         let target_fps = 60;
         let target_tpf = 1;
-        let max_num_circle_instances = 0;
-        let max_num_rectangle_instances = 0;
         Self {
             window_size,
             engine: None,
@@ -181,10 +252,13 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
             target_fps,
             background: None,
             window_title: "".to_string(),
+            fullscreen: false,
             font: None,
             pp_filter: vec![],
-            max_num_circle_instances,
-            max_num_rectangle_instances,
+            max_num_circle_instances: None,
+            max_num_rectangle_instances: None,
+            shrink_instance_buffers: false,
+            atlas_size: None,
         }
     }
 
@@ -223,6 +297,12 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
         self
     }
 
+    /// Starts the window in borderless fullscreen, F11 toggles fullscreen at runtime
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
     pub fn font(mut self, font: Font) -> Self {
         self.font = Some(font);
         self
@@ -233,27 +313,33 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
         self
     }
 
+    /// Limits the number of circles drawn in one frame, the instance buffer grows as
+    /// needed up to the limit. Without a limit there is no bound.
     pub fn max_num_circle_instances(mut self, len: usize) -> Self {
-        self.max_num_circle_instances = len as u32;
+        self.max_num_circle_instances = Some(len as u32);
         self
     }
 
+    /// Limits the number of rectangles drawn in one frame, see `max_num_circle_instances`
     pub fn max_num_rectangle_instances(mut self, len: usize) -> Self {
-        self.max_num_rectangle_instances = len as u32;
+        self.max_num_rectangle_instances = Some(len as u32);
         self
     }
 
-    pub fn build(self) -> GameEngine<'a, T> {
-        debug_assert_ne!(
-            0, self.max_num_circle_instances,
-            "Expected maximum number of circle instances to be more than 0"
-        );
+    /// Releases instance buffer memory again after frames with many instances
+    pub fn shrink_instance_buffers(mut self, shrink: bool) -> Self {
+        self.shrink_instance_buffers = shrink;
+        self
+    }
 
-        debug_assert_ne!(
-            0, self.max_num_rectangle_instances,
-            "Expected maximum number of rectangle instances to be more than 0"
-        );
+    /// Width and height in pixels of the atlas textures loaded at runtime are packed
+    /// into, together with the sprite sheet
+    pub fn atlas_size(mut self, size: u32) -> Self {
+        self.atlas_size = Some(size);
+        self
+    }
 
+    pub fn build(self) -> GameEngine<'a, T> {
         let (window_width, window_height) = self.window_size;
         let window_size = PhysicalSize::new(window_width, window_height);
         let last_tick = Instant::now();
@@ -263,6 +349,8 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
             window_size,
             window_title: self.window_title,
             window: None, // Initiated by event loop resume fn, by doc recommendation
+            fullscreen: self.fullscreen,
+            scale_factor: 1.0,
             last_tick,
             tick_delta,
             next_tick,
@@ -274,6 +362,8 @@ impl<'a, T: PhysicsEngine + RenderEngine> GameEngineBuilder<T> {
             pp_filter: self.pp_filter,
             max_num_circle_instances: self.max_num_circle_instances,
             max_num_rectangle_instances: self.max_num_rectangle_instances,
+            shrink_instance_buffers: self.shrink_instance_buffers,
+            atlas_size: self.atlas_size,
         }
     }
 }
@@ -1,10 +1,22 @@
 use cgmath::Vector3;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random number generator for reproducible scenes, the same seed results in the same
+/// colors, positions and radii. Unlike `StdRng` its algorithm is fixed, such that the
+/// scenes are the same between platforms and versions of `rand`.
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
 
 #[allow(dead_code)]
 pub(crate) fn generate_random_colors(n: u32) -> Vec<Vector3<f32>> {
+    generate_random_colors_with_rng(n, &mut rand::thread_rng())
+}
+
+#[allow(dead_code)]
+pub(crate) fn generate_random_colors_with_rng<R: Rng>(n: u32, rng: &mut R) -> Vec<Vector3<f32>> {
     let mut colors = vec![];
-    let mut rng = rand::thread_rng();
     let min = 0.2;
     let max = 1.0;
     for _ in 0..n {
@@ -16,8 +28,12 @@ pub(crate) fn generate_random_colors(n: u32) -> Vec<Vector3<f32>> {
 
 #[allow(dead_code)]
 pub fn create_grid_positions(num_cols: u32, num_rows: u32, spacing: f32, variance: Option<f32>) -> Vec<Vector3<f32>> {
+    create_grid_positions_with_rng(num_cols, num_rows, spacing, variance, &mut rand::thread_rng())
+}
+
+/// Same as `create_grid_positions` with the horizontal jitter drawn from `rng`
+pub fn create_grid_positions_with_rng<R: Rng>(num_cols: u32, num_rows: u32, spacing: f32, variance: Option<f32>, rng: &mut R) -> Vec<Vector3<f32>> {
     let mut positions = Vec::new();
-    let mut rng = rand::thread_rng();
     let var = variance.unwrap_or(0.0);
 
     // Calculate the total width and height of the grid
@@ -44,10 +60,14 @@ pub fn create_grid_positions(num_cols: u32, num_rows: u32, spacing: f32, varianc
 
 #[allow(dead_code)]
 pub(crate) fn generate_random_radii(num_instances: u32, base_radius: f32, variance: f32) -> Vec<f32> {
+    generate_random_radii_with_rng(num_instances, base_radius, variance, &mut rand::thread_rng())
+}
+
+#[allow(dead_code)]
+pub(crate) fn generate_random_radii_with_rng<R: Rng>(num_instances: u32, base_radius: f32, variance: f32, rng: &mut R) -> Vec<f32> {
     if variance == 0.0 {
         return vec![base_radius; num_instances as usize];
     }
-    let mut rng = rand::thread_rng();
     let mut radii = Vec::new();
     for _ in 0..num_instances {
         let radius = base_radius + rng.gen_range(-variance.abs()..variance.abs());
@@ -55,3 +75,22 @@ pub(crate) fn generate_random_radii(num_instances: u32, base_radius: f32, varian
     }
     return radii;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{create_grid_positions_with_rng, generate_random_colors_with_rng, generate_random_radii_with_rng, seeded_rng};
+
+    #[test]
+    fn given_same_seed_expect_same_scene() {
+        let scene = |seed: u64| {
+            let mut rng = seeded_rng(seed);
+            (
+                create_grid_positions_with_rng(10, 10, 2.0, Some(0.5), &mut rng),
+                generate_random_radii_with_rng(100, 1.0, 0.2, &mut rng),
+                generate_random_colors_with_rng(100, &mut rng),
+            )
+        };
+        assert_eq!(scene(7), scene(7));
+        assert_ne!(scene(7), scene(8));
+    }
+}
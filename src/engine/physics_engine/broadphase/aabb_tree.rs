@@ -0,0 +1,604 @@
+use crate::engine::physics_engine::collision::{
+    collision_candidates::CollisionCandidates,
+    {Aabb, RigidBody, RigidBodyType},
+};
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+use super::{update_average_size_query_grid, BroadPhase};
+
+const NULL_NODE: usize = usize::MAX;
+
+/// Default distance the boxes stored in the tree extend past the bodies
+const DEFAULT_MARGIN: f32 = 5.0;
+
+struct Node {
+    aabb: Aabb,
+    parent: usize,
+    left: usize,
+    right: usize,
+    /// Zero for leaves, one more than the highest child for internal nodes
+    height: usize,
+    /// Index of the body for leaves, `NULL_NODE` for internal nodes
+    body_idx: usize,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.left == NULL_NODE
+    }
+}
+
+/// Broadphase based on a dynamic bounding volume tree. Every body is a leaf holding
+/// its bounding box grown by a margin, such that a body only has to be reinserted
+/// once it leaves its fattened box. The tree is kept balanced with AVL style
+/// rotations on every insertion and removal.
+///
+/// Unlike the grid based broadphases the cost does not depend on the largest body
+/// or the size of the world, which makes it a good fit for scenes mixing small and
+/// huge bodies. Each pair of bodies with overlapping fattened boxes is emitted as
+/// its own `CollisionCandidates` holding the two indices, lowest index first.
+///
+/// Bodies are identified by their position in the iterator, bodies of unknown type
+/// are ignored.
+pub struct AabbTree {
+    margin: f32,
+    nodes: Vec<Node>,
+    free_nodes: Vec<usize>,
+    root: usize,
+    leaves: Vec<usize>,
+    stack: Vec<usize>,
+    query_grid: QueryGrid,
+}
+
+impl AabbTree {
+    pub fn new() -> Self {
+        Self::with_margin(DEFAULT_MARGIN)
+    }
+
+    /// Creates a tree where the boxes extend `margin` past the bodies. Larger margins
+    /// mean fewer reinsertions of moving bodies but more candidates.
+    pub fn with_margin(margin: f32) -> Self {
+        debug_assert!(
+            margin >= 0.0,
+            "Expected margin to be positive, found {margin}"
+        );
+        Self {
+            margin,
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: NULL_NODE,
+            leaves: Vec::new(),
+            stack: Vec::new(),
+            query_grid: QueryGrid::new([0.0, 0.0, 0.0], 1.0),
+        }
+    }
+
+    pub fn margin(&self) -> f32 {
+        self.margin
+    }
+
+    /// Number of edges on the longest path from the root to a leaf
+    pub fn height(&self) -> usize {
+        match self.root {
+            NULL_NODE => 0,
+            root => self.nodes[root].height,
+        }
+    }
+
+    /// Same as `BroadPhase::collision_detection` but writes the candidates into
+    /// `candidates`, reusing the lists already allocated there.
+    pub fn collision_detection_into<'a, I>(
+        &mut self,
+        bodies: I,
+        candidates: &mut Vec<CollisionCandidates>,
+    ) where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.update_leaves(bodies);
+        // The fattened boxes of the leaves contain the bodies
+        let nodes = &self.nodes;
+        let leaves = self.leaves.iter().enumerate();
+        update_average_size_query_grid(
+            &mut self.query_grid,
+            leaves
+                .filter(|(_, leaf)| **leaf != NULL_NODE)
+                .map(|(i, leaf)| (i, nodes[*leaf].aabb)),
+        );
+
+        let mut num_candidates = 0;
+        for body_idx in 0..self.leaves.len() {
+            let leaf = self.leaves[body_idx];
+            if leaf == NULL_NODE {
+                continue;
+            }
+            let aabb = self.nodes[leaf].aabb;
+            self.stack.clear();
+            self.stack.push(self.root);
+            while let Some(node_idx) = self.stack.pop() {
+                let node = &self.nodes[node_idx];
+                if !node.aabb.overlaps(&aabb) {
+                    continue;
+                }
+                if !node.is_leaf() {
+                    self.stack.push(node.left);
+                    self.stack.push(node.right);
+                    continue;
+                }
+                // Every pair is found from both bodies, only keep it once
+                if node.body_idx <= body_idx {
+                    continue;
+                }
+                if num_candidates == candidates.len() {
+                    candidates.push(CollisionCandidates::new(Vec::with_capacity(2)));
+                }
+                let indices = &mut candidates[num_candidates].indices;
+                indices.clear();
+                indices.push(body_idx);
+                indices.push(node.body_idx);
+                num_candidates += 1;
+            }
+        }
+        candidates.truncate(num_candidates);
+    }
+
+    /// Inserts new bodies, reinserts bodies that left their box and removes bodies
+    /// that no longer exist
+    fn update_leaves<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut num_bodies = 0;
+        for (i, body) in bodies.enumerate() {
+            num_bodies += 1;
+            if i == self.leaves.len() {
+                self.leaves.push(NULL_NODE);
+            }
+            let leaf = self.leaves[i];
+            if body.body_type == RigidBodyType::Unknown {
+                if leaf != NULL_NODE {
+                    self.remove_leaf(leaf);
+                    self.free_node(leaf);
+                    self.leaves[i] = NULL_NODE;
+                }
+                continue;
+            }
+
+            let aabb = body.aabb();
+            if leaf == NULL_NODE {
+                let leaf = self.allocate_node(aabb.expand(self.margin), i);
+                self.insert_leaf(leaf);
+                self.leaves[i] = leaf;
+                continue;
+            }
+            // A body that shrank is reinserted as well to keep the boxes tight
+            let fat_aabb = self.nodes[leaf].aabb;
+            if !fat_aabb.contains(&aabb) || !aabb.expand(4.0 * self.margin).contains(&fat_aabb) {
+                self.remove_leaf(leaf);
+                self.nodes[leaf].aabb = aabb.expand(self.margin);
+                self.insert_leaf(leaf);
+            }
+        }
+
+        for i in num_bodies..self.leaves.len() {
+            let leaf = self.leaves[i];
+            if leaf != NULL_NODE {
+                self.remove_leaf(leaf);
+                self.free_node(leaf);
+            }
+        }
+        self.leaves.truncate(num_bodies);
+    }
+
+    fn allocate_node(&mut self, aabb: Aabb, body_idx: usize) -> usize {
+        let node = Node {
+            aabb,
+            parent: NULL_NODE,
+            left: NULL_NODE,
+            right: NULL_NODE,
+            height: 0,
+            body_idx,
+        };
+        match self.free_nodes.pop() {
+            Some(node_idx) => {
+                self.nodes[node_idx] = node;
+                node_idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn free_node(&mut self, node_idx: usize) {
+        self.free_nodes.push(node_idx);
+    }
+
+    /// Inserts the leaf next to the sibling that increases the total perimeter of
+    /// the tree the least
+    fn insert_leaf(&mut self, leaf: usize) {
+        if self.root == NULL_NODE {
+            self.root = leaf;
+            self.nodes[leaf].parent = NULL_NODE;
+            return;
+        }
+
+        let leaf_aabb = self.nodes[leaf].aabb;
+        let mut sibling = self.root;
+        while !self.nodes[sibling].is_leaf() {
+            let node = &self.nodes[sibling];
+            let perimeter = node.aabb.perimeter();
+            let combined_perimeter = node.aabb.union(&leaf_aabb).perimeter();
+            // Cost of creating a new parent for this node and the leaf
+            let cost = 2.0 * combined_perimeter;
+            // Minimum cost of pushing the leaf further down the tree
+            let inheritance_cost = 2.0 * (combined_perimeter - perimeter);
+            let child_cost = |child: &Node| {
+                let child_perimeter = child.aabb.union(&leaf_aabb).perimeter();
+                match child.is_leaf() {
+                    true => child_perimeter + inheritance_cost,
+                    false => child_perimeter - child.aabb.perimeter() + inheritance_cost,
+                }
+            };
+            let left_cost = child_cost(&self.nodes[node.left]);
+            let right_cost = child_cost(&self.nodes[node.right]);
+
+            if cost < left_cost && cost < right_cost {
+                break;
+            }
+            sibling = match left_cost < right_cost {
+                true => node.left,
+                false => node.right,
+            };
+        }
+
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node(leaf_aabb.union(&self.nodes[sibling].aabb), NULL_NODE);
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].left = sibling;
+        self.nodes[new_parent].right = leaf;
+        self.nodes[new_parent].height = self.nodes[sibling].height + 1;
+        self.replace_child(old_parent, sibling, new_parent);
+        self.nodes[sibling].parent = new_parent;
+        self.nodes[leaf].parent = new_parent;
+
+        self.refit(old_parent);
+    }
+
+    /// Detaches the leaf from the tree, the leaf node itself is kept
+    fn remove_leaf(&mut self, leaf: usize) {
+        if leaf == self.root {
+            self.root = NULL_NODE;
+            return;
+        }
+
+        let parent = self.nodes[leaf].parent;
+        let grand_parent = self.nodes[parent].parent;
+        let sibling = match self.nodes[parent].left == leaf {
+            true => self.nodes[parent].right,
+            false => self.nodes[parent].left,
+        };
+        self.replace_child(grand_parent, parent, sibling);
+        self.nodes[sibling].parent = grand_parent;
+        self.free_node(parent);
+
+        self.refit(grand_parent);
+    }
+
+    /// Replaces `old_child` of `parent` by `new_child`, a missing parent means
+    /// `old_child` is the root
+    fn replace_child(&mut self, parent: usize, old_child: usize, new_child: usize) {
+        if parent == NULL_NODE {
+            self.root = new_child;
+        } else if self.nodes[parent].left == old_child {
+            self.nodes[parent].left = new_child;
+        } else {
+            self.nodes[parent].right = new_child;
+        }
+    }
+
+    /// Rebalances and updates the boxes and heights from `node_idx` up to the root
+    fn refit(&mut self, mut node_idx: usize) {
+        while node_idx != NULL_NODE {
+            node_idx = self.balance(node_idx);
+            self.update_node(node_idx);
+            node_idx = self.nodes[node_idx].parent;
+        }
+    }
+
+    fn update_node(&mut self, node_idx: usize) {
+        let left = &self.nodes[self.nodes[node_idx].left];
+        let right = &self.nodes[self.nodes[node_idx].right];
+        let aabb = left.aabb.union(&right.aabb);
+        let height = left.height.max(right.height) + 1;
+        self.nodes[node_idx].aabb = aabb;
+        self.nodes[node_idx].height = height;
+    }
+
+    /// Rotates the higher child up if the heights of the children differ by more
+    /// than one. Returns the node now at the position of `node_idx`.
+    fn balance(&mut self, node_idx: usize) -> usize {
+        let node = &self.nodes[node_idx];
+        if node.is_leaf() || node.height < 2 {
+            return node_idx;
+        }
+        let (left, right) = (node.left, node.right);
+        let left_height = self.nodes[left].height;
+        let right_height = self.nodes[right].height;
+        if right_height > left_height + 1 {
+            self.rotate_up(node_idx, right)
+        } else if left_height > right_height + 1 {
+            self.rotate_up(node_idx, left)
+        } else {
+            node_idx
+        }
+    }
+
+    /// Moves `child` into the position of its parent `node_idx`. The parent becomes
+    /// a child of `child` and takes over its lower grandchild.
+    fn rotate_up(&mut self, node_idx: usize, child: usize) -> usize {
+        let grand_parent = self.nodes[node_idx].parent;
+        self.replace_child(grand_parent, node_idx, child);
+        self.nodes[child].parent = grand_parent;
+
+        let (first, second) = (self.nodes[child].left, self.nodes[child].right);
+        let (keep, give) = match self.nodes[first].height > self.nodes[second].height {
+            true => (first, second),
+            false => (second, first),
+        };
+        self.nodes[child].left = node_idx;
+        self.nodes[child].right = keep;
+        self.nodes[node_idx].parent = child;
+        self.replace_child(node_idx, child, give);
+        self.nodes[give].parent = node_idx;
+
+        self.update_node(node_idx);
+        self.update_node(child);
+        child
+    }
+
+    /// Asserts that links, heights and boxes of the tree are consistent
+    #[cfg(test)]
+    fn validate(&self) {
+        if self.root == NULL_NODE {
+            return;
+        }
+        assert_eq!(NULL_NODE, self.nodes[self.root].parent);
+        let mut stack = vec![self.root];
+        let mut num_leaves = 0;
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if node.is_leaf() {
+                assert_eq!(0, node.height);
+                assert_eq!(node_idx, self.leaves[node.body_idx]);
+                num_leaves += 1;
+                continue;
+            }
+            let (left, right) = (&self.nodes[node.left], &self.nodes[node.right]);
+            assert_eq!(node_idx, left.parent);
+            assert_eq!(node_idx, right.parent);
+            assert_eq!(left.height.max(right.height) + 1, node.height);
+            assert!(
+                left.height.abs_diff(right.height) <= 1,
+                "Unbalanced node {node_idx}"
+            );
+            assert!(node.aabb.contains(&left.aabb) && node.aabb.contains(&right.aabb));
+            stack.push(node.left);
+            stack.push(node.right);
+        }
+        let num_bodies = self.leaves.iter().filter(|l| **l != NULL_NODE).count();
+        assert_eq!(num_bodies, num_leaves);
+    }
+}
+
+impl std::default::Default for AabbTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BroadPhase<Vec<CollisionCandidates>> for AabbTree {
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> Vec<CollisionCandidates>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut candidates = vec![];
+        self.collision_detection_into(bodies, &mut candidates);
+        candidates
+    }
+}
+
+impl SpatialQuery for AabbTree {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.query_grid.refill(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::engine::physics_engine::broadphase::BroadPhase;
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::util::zero;
+
+    use super::AabbTree;
+
+    fn circle(id: usize, position: [f32; 3], radius: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius })
+            .build()
+    }
+
+    fn rectangle(id: usize, position: [f32; 3], width: f32, height: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Rectangle { width, height })
+            .build()
+    }
+
+    fn contains_pair(candidates: &[CollisionCandidates], i: usize, j: usize) -> bool {
+        candidates.iter().any(|c| c.indices == [i, j])
+    }
+
+    #[test]
+    fn rect_circle_are_possible_collision_candidates() {
+        let mut tree = AabbTree::new();
+        let bodies = [circle(0, zero(), 50.0), rectangle(1, zero(), 50.0, 50.0)];
+
+        let candidates = tree.collision_detection(bodies.iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(vec![0, 1], candidates[0].indices);
+    }
+
+    #[test]
+    fn given_body_moved_away_expect_no_longer_candidates() {
+        let mut tree = AabbTree::new();
+        let mut bodies = [
+            circle(0, [0., 0., 0.], 10.0),
+            circle(1, [15., 0., 0.], 10.0),
+        ];
+        let candidates = tree.collision_detection(bodies.iter());
+        assert!(contains_pair(&candidates, 0, 1));
+
+        bodies[1].position.x = 500.0;
+        let candidates = tree.collision_detection(bodies.iter());
+        assert!(candidates.is_empty());
+
+        bodies[1].position.x = -15.0;
+        let candidates = tree.collision_detection(bodies.iter());
+        assert!(contains_pair(&candidates, 0, 1));
+        tree.validate();
+    }
+
+    #[test]
+    fn given_body_removed_expect_no_stale_indices() {
+        let mut tree = AabbTree::new();
+        let bodies = [
+            circle(0, [0., 0., 0.], 10.0),
+            circle(1, [15., 0., 0.], 10.0),
+            circle(2, [5., 5., 0.], 10.0),
+        ];
+        let _ = tree.collision_detection(bodies.iter());
+        let candidates = tree.collision_detection(bodies[..2].iter());
+        assert_eq!(1, candidates.len());
+        assert!(contains_pair(&candidates, 0, 1));
+        tree.validate();
+    }
+
+    #[test]
+    fn given_unknown_body_expect_it_to_be_ignored() {
+        let mut tree = AabbTree::new();
+        let mut bodies = [circle(0, zero(), 10.0), circle(1, zero(), 10.0)];
+        let _ = tree.collision_detection(bodies.iter());
+
+        bodies[1].body_type = RigidBodyType::Unknown;
+        let candidates = tree.collision_detection(bodies.iter());
+        assert!(candidates.is_empty());
+        tree.validate();
+    }
+
+    #[test]
+    fn given_huge_body_among_small_bodies_expect_only_overlapping_pairs() {
+        let mut tree = AabbTree::with_margin(0.0);
+        let mut bodies = vec![rectangle(0, [0., -10., 0.], 10000.0, 10.0)];
+        for i in 1..50 {
+            bodies.push(circle(i, [i as f32 * 100.0 - 2500.0, 50.0, 0.0], 2.0));
+        }
+        bodies.push(circle(50, [4000.0, -2.0, 0.0], 4.0));
+
+        let candidates = tree.collision_detection(bodies.iter());
+        assert_eq!(1, candidates.len());
+        assert!(contains_pair(&candidates, 0, 50));
+    }
+
+    #[test]
+    fn given_bodies_far_from_origin_expect_candidates() {
+        let mut tree = AabbTree::new();
+        let bodies = [
+            circle(0, [-1.0e6, 1.0e6, 0.], 10.0),
+            circle(1, [-1.0e6 + 15.0, 1.0e6, 0.], 10.0),
+            circle(2, [1.0e6, -1.0e6, 0.], 10.0),
+        ];
+        let candidates = tree.collision_detection(bodies.iter());
+        assert_eq!(1, candidates.len());
+        assert!(contains_pair(&candidates, 0, 1));
+    }
+
+    #[test]
+    fn given_bodies_inserted_in_sorted_order_expect_balanced_tree() {
+        let mut tree = AabbTree::new();
+        let bodies: Vec<RigidBody> = (0..1024)
+            .map(|i| circle(i, [i as f32 * 100.0, 0.0, 0.0], 1.0))
+            .collect();
+        let _ = tree.collision_detection(bodies.iter());
+        tree.validate();
+        // An AVL tree with n leaves is at most 1.44 * log2(n) high
+        assert!(
+            tree.height() <= 15,
+            "Tree height {} is too large",
+            tree.height()
+        );
+    }
+
+    #[test]
+    fn given_random_moving_scene_with_mixed_sizes_expect_all_overlapping_pairs_once() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut bodies: Vec<RigidBody> = (0..300)
+            .map(|i| {
+                let position = [
+                    rng.gen_range(-500.0..500.0),
+                    rng.gen_range(-500.0..500.0),
+                    0.0,
+                ];
+                match i % 20 {
+                    0 => rectangle(
+                        i,
+                        position,
+                        rng.gen_range(50.0..400.0),
+                        rng.gen_range(5.0..20.0),
+                    ),
+                    _ => circle(i, position, rng.gen_range(1.0..10.0)),
+                }
+            })
+            .collect();
+
+        let mut tree = AabbTree::new();
+        for _ in 0..10 {
+            let candidates = tree.collision_detection(bodies.iter());
+            tree.validate();
+            for i in 0..bodies.len() {
+                for j in (i + 1)..bodies.len() {
+                    if bodies[i].aabb().overlaps(&bodies[j].aabb()) {
+                        assert!(contains_pair(&candidates, i, j), "Missing pair ({i}, {j})");
+                    }
+                }
+            }
+            let mut pairs: Vec<&Vec<usize>> = candidates.iter().map(|c| &c.indices).collect();
+            pairs.sort();
+            pairs.dedup();
+            assert_eq!(
+                candidates.len(),
+                pairs.len(),
+                "Expected every pair only once"
+            );
+
+            bodies.iter_mut().for_each(|b| {
+                b.position.x += rng.gen_range(-15.0..15.0);
+                b.position.y += rng.gen_range(-15.0..15.0);
+            });
+        }
+    }
+}
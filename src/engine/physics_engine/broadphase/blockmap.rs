@@ -2,20 +2,35 @@ use crate::engine::physics_engine::collision::{
     collision_candidates::CollisionCandidates,
     {RigidBody, RigidBodyType},
 };
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
 
 use super::BroadPhase;
 
 pub struct BlockMap {
     width: f32,
+    query_grid: QueryGrid,
 }
 
 impl BlockMap {
     pub fn new(window_width: f32) -> Self {
         Self {
             width: window_width,
+            query_grid: QueryGrid::new([-1.0, -1.0, 0.0], 1.0),
         }
     }
 
+    fn cell_size(bodies: &[&RigidBody]) -> f32 {
+        bodies.iter().fold(0.0, |acc, b| match b.body_type {
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                f32::max(acc, radius)
+            }
+            RigidBodyType::Rectangle { width, height } => {
+                f32::max(acc, f32::max(width, height)) / 2.0
+            }
+            _ => panic!("Unknown body type {}", b.body_type),
+        }) * 2.0
+    }
+
     fn assign_object_to_cell(
         &self,
         bodies: &Vec<&RigidBody>,
@@ -59,20 +74,14 @@ impl BlockMap {
 }
 
 impl BroadPhase<Vec<CollisionCandidates>> for BlockMap {
-    fn collision_detection<'a, I>(&self, bodies: I) -> Vec<CollisionCandidates>
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> Vec<CollisionCandidates>
     where
         I: Iterator<Item = &'a RigidBody>,
     {
         let bodies: Vec<&RigidBody> = bodies.collect();
         // Create grid with largest side equal to the largest diameter of the circles
         // FIXME: Allow for width and height of cell to unequal
-        let cell_size = bodies.iter().fold(0.0, |acc, b| match b.body_type {
-            RigidBodyType::Circle { radius } => f32::max(acc, radius),
-            RigidBodyType::Rectangle { width, height } => {
-                f32::max(acc, f32::max(width, height)) / 2.0
-            }
-            _ => panic!("Unknown body type {}", b.body_type),
-        }) * 2.0;
+        let cell_size = Self::cell_size(&bodies);
 
         let grid_width = (self.width / cell_size).ceil() as u32;
 
@@ -80,6 +89,14 @@ impl BroadPhase<Vec<CollisionCandidates>> for BlockMap {
             println!("warning: grid width smaller than 3 is not supported.");
         }
         let cells = self.assign_object_to_cell(&bodies, cell_size, grid_width);
+
+        // Same cell layout for queries, which offsets all coordinates by 1.0. Bodies are
+        // inserted into every cell they overlap instead of only their center cell.
+        self.query_grid.reset([-1.0, -1.0, 0.0], cell_size);
+        for (i, b) in bodies.iter().enumerate() {
+            self.query_grid.insert_aabb(i, &b.aabb());
+        }
+
         // For each cell, compute collision between all circles in the current cell and
         // all surrounding cells. Skip over the outer most cells.
         let mut all_candidates = vec![];
@@ -105,6 +122,19 @@ impl BroadPhase<Vec<CollisionCandidates>> for BlockMap {
     }
 }
 
+impl SpatialQuery for BlockMap {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.query_grid.refill(bodies);
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -117,7 +147,7 @@ mod tests {
     #[test]
     fn rect_circle_are_possible_collision_candidates() {
         let (window_width, _window_height) = (1000.0, 1000);
-        let blockmap = BlockMap::new(window_width);
+        let mut blockmap = BlockMap::new(window_width);
         let circ = RigidBodyBuilder::default()
             .id(0)
             .position(zero())
@@ -136,4 +166,25 @@ mod tests {
         assert_eq!(1, candidates.len());
         assert_eq!(candidates[0].indices, vec![0, 1]);
     }
+
+    #[test]
+    fn custom_circle_are_possible_collision_candidates() {
+        let mut blockmap = BlockMap::new(1000.0);
+        let custom = RigidBodyBuilder::default()
+            .id(0)
+            .position([100.0, 100.0, 0.0])
+            .body_type(RigidBodyType::Custom {
+                kind: 1,
+                radius: 50.0,
+            })
+            .build();
+        let circ = RigidBodyBuilder::default()
+            .id(1)
+            .position([150.0, 100.0, 0.0])
+            .body_type(RigidBodyType::Circle { radius: 10.0 })
+            .build();
+
+        let candidates = blockmap.collision_detection([custom, circ].iter());
+        assert!(candidates.iter().any(|c| c.indices == vec![0, 1]));
+    }
 }
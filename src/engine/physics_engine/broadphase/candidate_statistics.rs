@@ -0,0 +1,65 @@
+use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+
+/// Summary of the candidates produced by a broadphase. The narrowphase tests every
+/// pair within a candidate list, so the number of pairs is what it costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CandidateStatistics {
+    pub num_lists: usize,
+    pub num_pairs: usize,
+    pub largest_list: usize,
+}
+
+impl CandidateStatistics {
+    pub fn from_candidates<'a, I>(candidates: I) -> Self
+    where
+        I: Iterator<Item = &'a CollisionCandidates>,
+    {
+        candidates.fold(Self::default(), |acc, c| {
+            let len = c.len();
+            Self {
+                num_lists: acc.num_lists + 1,
+                num_pairs: acc.num_pairs + len * len.saturating_sub(1) / 2,
+                largest_list: acc.largest_list.max(len),
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for CandidateStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CandidateStatistics{{ lists: {}, pairs: {}, largest list: {} }}",
+            self.num_lists, self.num_pairs, self.largest_list
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_candidates {
+        use super::super::CandidateStatistics;
+        use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+        macro_rules! from_candidates_tests {
+            ($($name:ident: $candidates: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let candidates: Vec<CollisionCandidates> = $candidates;
+                        let expected: CandidateStatistics = $expected;
+                        let output = CandidateStatistics::from_candidates(candidates.iter());
+                        assert_eq!(expected, output, "Expected {expected} but found {output}");
+                    }
+                )*
+            }
+        }
+
+        from_candidates_tests! {
+            given_no_candidates_expect_empty_statistics:
+                vec![], CandidateStatistics::default()
+            given_pair_and_triple_expect_four_pairs:
+                vec![CollisionCandidates::new(vec![0, 1]), CollisionCandidates::new(vec![2, 3, 4])],
+                CandidateStatistics { num_lists: 2, num_pairs: 4, largest_list: 3 }
+        }
+    }
+}
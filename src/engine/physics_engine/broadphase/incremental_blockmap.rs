@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use crate::engine::physics_engine::collision::{
+    collision_candidates::CollisionCandidates,
+    {Aabb, RigidBody, RigidBodyType},
+};
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+use super::BroadPhase;
+
+/// Cells that are tested against the center cell. Together with the center cell they
+/// form half of the 3x3 neighbourhood, which is enough to cover every pair of
+/// neighbouring cells exactly once.
+const FORWARD_NEIGHBOURS: [(i32, i32); 4] = [(1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Multiplicative hasher for cell coordinates. The default SipHash dominates the
+/// run time when looking up neighbouring cells.
+#[derive(Default)]
+struct CellHasher {
+    hash: u64,
+}
+
+impl Hasher for CellHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|b| self.write_u64(*b as u64));
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u64(i as u32 as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ i).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+type CellMap = HashMap<(i32, i32), Vec<usize>, BuildHasherDefault<CellHasher>>;
+
+/// Uniform grid broadphase that keeps its cells between calls. Only bodies that
+/// changed cell since the previous call are moved, and all buffers, including the
+/// candidate lists when using `collision_detection_into`, are reused such that a
+/// scene in steady state does not allocate.
+///
+/// Bodies are identified by their position in the iterator. Adding or removing
+/// bodies is supported, a body that ends up at a different index is treated as a
+/// body that moved. Unlike `BlockMap` the grid is unbounded and accepts negative
+/// coordinates.
+pub struct IncrementalBlockMap {
+    cell_size: f32,
+    cells: CellMap,
+    body_cells: Vec<(i32, i32)>,
+    positions: Vec<(f32, f32)>,
+    aabbs: Vec<Aabb>,
+    query_grid: QueryGrid,
+    query_cells: Vec<[(i64, i64); 2]>,
+}
+
+impl IncrementalBlockMap {
+    /// The cell size is set to the largest body diameter on the first call
+    pub fn new() -> Self {
+        Self::with_cell_size(0.0)
+    }
+
+    /// Creates a grid with a fixed cell size. The cells still grow if a body larger
+    /// than the cell is found.
+    pub fn with_cell_size(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: CellMap::default(),
+            body_cells: Vec::new(),
+            positions: Vec::new(),
+            aabbs: Vec::new(),
+            query_grid: QueryGrid::new([0.0, 0.0, 0.0], 1.0),
+            query_cells: Vec::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Same as `BroadPhase::collision_detection` but writes the candidates into
+    /// `candidates`, reusing the lists already allocated there.
+    pub fn collision_detection_into<'a, I>(
+        &mut self,
+        bodies: I,
+        candidates: &mut Vec<CollisionCandidates>,
+    ) where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.update_bodies(bodies);
+
+        let mut num_candidates = 0;
+        for (cell, cell_bodies) in self.cells.iter() {
+            if cell_bodies.is_empty() {
+                continue;
+            }
+            if num_candidates == candidates.len() {
+                candidates.push(CollisionCandidates::new(Vec::new()));
+            }
+            let indices = &mut candidates[num_candidates].indices;
+            indices.clear();
+            indices.extend_from_slice(cell_bodies);
+            for (dx, dy) in FORWARD_NEIGHBOURS {
+                if let Some(neighbour) = self.cells.get(&(cell.0 + dx, cell.1 + dy)) {
+                    indices.extend_from_slice(neighbour);
+                }
+            }
+            if indices.len() > 1 {
+                num_candidates += 1;
+            }
+        }
+        candidates.truncate(num_candidates);
+    }
+
+    /// Stores where the bodies are and moves them into their cells
+    fn update_bodies<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut largest_diameter: f32 = 0.0;
+        let mut num_bodies = 0;
+        for (i, body) in bodies.enumerate() {
+            largest_diameter = largest_diameter.max(Self::bounding_radius(body) * 2.0);
+            let position = (body.position.x, body.position.y);
+            match self.positions.get_mut(i) {
+                Some(p) => *p = position,
+                None => self.positions.push(position),
+            }
+            match self.aabbs.get_mut(i) {
+                Some(aabb) => *aabb = body.aabb(),
+                None => self.aabbs.push(body.aabb()),
+            }
+            num_bodies += 1;
+        }
+        self.positions.truncate(num_bodies);
+        self.aabbs.truncate(num_bodies);
+
+        if largest_diameter > self.cell_size {
+            // Bodies may only overlap neighbouring cells, start over with larger cells
+            self.cell_size = largest_diameter;
+            self.cells.clear();
+            self.body_cells.clear();
+        }
+
+        self.update_cells();
+        self.update_query_grid();
+    }
+
+    /// Moves the bodies whose cell changed since the last call and removes bodies
+    /// that no longer exist
+    fn update_cells(&mut self) {
+        let num_bodies = self.positions.len();
+        for i in 0..num_bodies {
+            let cell = self.cell_of(self.positions[i]);
+            match self.body_cells.get(i).copied() {
+                Some(prev_cell) if prev_cell == cell => (),
+                Some(prev_cell) => {
+                    self.remove_from_cell(i, prev_cell);
+                    self.cells.entry(cell).or_default().push(i);
+                    self.body_cells[i] = cell;
+                }
+                None => {
+                    self.cells.entry(cell).or_default().push(i);
+                    self.body_cells.push(cell);
+                }
+            }
+        }
+
+        for i in num_bodies..self.body_cells.len() {
+            self.remove_from_cell(i, self.body_cells[i]);
+        }
+        self.body_cells.truncate(num_bodies);
+
+        // Empty cells are kept to avoid reallocating when a body returns, but not forever
+        if self.cells.len() > 4 * num_bodies + 64 {
+            self.cells.retain(|_, cell_bodies| !cell_bodies.is_empty());
+        }
+    }
+
+    /// Moves the bodies whose bounding box changed cells since the last call in the
+    /// query grid, such that queries find bodies in every cell they overlap instead of
+    /// only their center cell. The grid is only refilled when the cells grew.
+    fn update_query_grid(&mut self) {
+        let cell_size = match self.cell_size {
+            cell_size if cell_size > 0.0 => cell_size,
+            _ => 1.0,
+        };
+        if self.query_grid.cell_size() != (cell_size, cell_size) {
+            self.query_grid.reset([0.0, 0.0, 0.0], cell_size);
+            self.query_cells.clear();
+        }
+
+        let num_bodies = self.aabbs.len();
+        let mut bounds: Option<Aabb> = None;
+        for (i, aabb) in self.aabbs.iter().enumerate() {
+            let range = self.query_grid.cell_range(aabb);
+            match self.query_cells.get(i).copied() {
+                Some(prev_range) if prev_range == range => (),
+                Some(prev_range) => {
+                    self.query_grid.remove(i, QueryGrid::cells_in(prev_range));
+                    self.query_grid.insert(i, aabb, QueryGrid::cells_in(range));
+                    self.query_cells[i] = range;
+                }
+                None => {
+                    self.query_grid.insert(i, aabb, QueryGrid::cells_in(range));
+                    self.query_cells.push(range);
+                }
+            }
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(aabb),
+                None => *aabb,
+            });
+        }
+
+        for i in num_bodies..self.query_cells.len() {
+            self.query_grid
+                .remove(i, QueryGrid::cells_in(self.query_cells[i]));
+        }
+        self.query_cells.truncate(num_bodies);
+        self.query_grid.set_bounds(bounds);
+
+        // A body overlaps at most four cells, empty cells are dropped the same way as
+        // in `update_cells`
+        if self.query_grid.num_cells() > 8 * num_bodies + 64 {
+            self.query_grid.drop_empty_cells();
+        }
+    }
+
+    fn remove_from_cell(&mut self, body_idx: usize, cell: (i32, i32)) {
+        if let Some(cell_bodies) = self.cells.get_mut(&cell) {
+            if let Some(pos) = cell_bodies.iter().position(|b| *b == body_idx) {
+                cell_bodies.swap_remove(pos);
+            }
+        }
+    }
+
+    fn cell_of(&self, position: (f32, f32)) -> (i32, i32) {
+        (
+            (position.0 / self.cell_size).floor() as i32,
+            (position.1 / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Radius of a circle containing the body regardless of its rotation
+    fn bounding_radius(body: &RigidBody) -> f32 {
+        match body.body_type {
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => radius,
+            RigidBodyType::Rectangle { width, height } => {
+                (width.powi(2) + height.powi(2)).sqrt() / 2.0
+            }
+            _ => panic!("Unknown body type {}", body.body_type),
+        }
+    }
+}
+
+impl std::default::Default for IncrementalBlockMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BroadPhase<Vec<CollisionCandidates>> for IncrementalBlockMap {
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> Vec<CollisionCandidates>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut candidates = vec![];
+        self.collision_detection_into(bodies, &mut candidates);
+        candidates
+    }
+}
+
+impl SpatialQuery for IncrementalBlockMap {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    /// Moves the bodies into their cells the same way as `collision_detection`,
+    /// without collecting the candidates
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.update_bodies(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::engine::physics_engine::broadphase::BroadPhase;
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{
+        Aabb, RigidBody, RigidBodyBuilder, RigidBodyType,
+    };
+    use crate::engine::physics_engine::query::SpatialQuery;
+    use crate::engine::util::zero;
+
+    use super::IncrementalBlockMap;
+
+    fn circle(id: usize, position: [f32; 3], radius: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius })
+            .build()
+    }
+
+    fn contains_pair(candidates: &[CollisionCandidates], i: usize, j: usize) -> bool {
+        candidates
+            .iter()
+            .any(|c| c.indices.contains(&i) && c.indices.contains(&j))
+    }
+
+    #[test]
+    fn rect_circle_are_possible_collision_candidates() {
+        let mut blockmap = IncrementalBlockMap::new();
+        let circ = circle(0, zero(), 50.0);
+        let rect = RigidBodyBuilder::default()
+            .id(1)
+            .position(zero())
+            .body_type(RigidBodyType::Rectangle {
+                width: 50.0,
+                height: 50.0,
+            })
+            .build();
+
+        let candidates = blockmap.collision_detection([circ, rect].iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(candidates[0].indices.len(), 2);
+    }
+
+    #[test]
+    fn given_body_moved_away_expect_no_longer_candidates() {
+        let mut blockmap = IncrementalBlockMap::new();
+        let mut bodies = [circle(0, [0., 0., 0.], 10.0), circle(1, [15., 0., 0.], 10.0)];
+        let candidates = blockmap.collision_detection(bodies.iter());
+        assert!(contains_pair(&candidates, 0, 1));
+
+        bodies[1].position.x = 500.0;
+        let candidates = blockmap.collision_detection(bodies.iter());
+        assert!(!contains_pair(&candidates, 0, 1));
+
+        bodies[1].position.x = -15.0;
+        let candidates = blockmap.collision_detection(bodies.iter());
+        assert!(contains_pair(&candidates, 0, 1));
+    }
+
+    #[test]
+    fn given_body_removed_expect_no_stale_indices() {
+        let mut blockmap = IncrementalBlockMap::new();
+        let bodies = [
+            circle(0, [0., 0., 0.], 10.0),
+            circle(1, [15., 0., 0.], 10.0),
+            circle(2, [5., 5., 0.], 10.0),
+        ];
+        let _ = blockmap.collision_detection(bodies.iter());
+        let candidates = blockmap.collision_detection(bodies[..2].iter());
+        assert!(candidates.iter().all(|c| c.indices.iter().all(|i| *i < 2)));
+        assert!(contains_pair(&candidates, 0, 1));
+    }
+
+    #[test]
+    fn given_larger_body_added_expect_cells_to_grow() {
+        let mut blockmap = IncrementalBlockMap::new();
+        let mut bodies = vec![circle(0, [0., 0., 0.], 1.0), circle(1, [100., 0., 0.], 1.0)];
+        let _ = blockmap.collision_detection(bodies.iter());
+        assert_eq!(2.0, blockmap.cell_size());
+
+        bodies.push(circle(2, [50., 0., 0.], 60.0));
+        let candidates = blockmap.collision_detection(bodies.iter());
+        assert_eq!(120.0, blockmap.cell_size());
+        assert!(contains_pair(&candidates, 0, 2));
+        assert!(contains_pair(&candidates, 1, 2));
+    }
+
+    #[test]
+    fn given_bodies_moved_and_removed_expect_query_grid_equal_to_a_fresh_grid() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut bodies: Vec<RigidBody> = (0..100)
+            .map(|i| {
+                let position = [
+                    rng.gen_range(-200.0..200.0),
+                    rng.gen_range(-200.0..200.0),
+                    0.0,
+                ];
+                circle(i, position, rng.gen_range(2.0..10.0))
+            })
+            .collect();
+        let mut blockmap = IncrementalBlockMap::new();
+        for step in 0..10 {
+            blockmap.collision_detection(bodies.iter());
+            for body in bodies.iter_mut().step_by(3) {
+                body.position.x += rng.gen_range(-30.0..30.0);
+                body.position.y += rng.gen_range(-30.0..30.0);
+            }
+            if step % 3 == 0 {
+                bodies.truncate(bodies.len() - 5);
+            }
+        }
+        blockmap.collision_detection(bodies.iter());
+        let mut fresh = IncrementalBlockMap::with_cell_size(blockmap.cell_size());
+        fresh.collision_detection(bodies.iter());
+
+        let cells = |blockmap: &IncrementalBlockMap| {
+            let grid = blockmap.query_grid();
+            let mut cells: Vec<(Aabb, Vec<usize>)> = grid
+                .occupied_cells()
+                .map(|cell| {
+                    let inside = Aabb::from_center(
+                        cell.center().into(),
+                        cell.width() / 4.0,
+                        cell.height() / 4.0,
+                    );
+                    (cell, grid.candidates_in_aabb(&inside))
+                })
+                .collect();
+            cells.sort_by(|a, b| {
+                a.0.min
+                    .x
+                    .total_cmp(&b.0.min.x)
+                    .then(a.0.min.y.total_cmp(&b.0.min.y))
+            });
+            cells
+        };
+        assert_eq!(cells(&fresh), cells(&blockmap));
+        assert_eq!(
+            fresh
+                .query_grid()
+                .candidates_along_segment([-1000., 0., 0.], [1000., 10., 0.]),
+            blockmap
+                .query_grid()
+                .candidates_along_segment([-1000., 0., 0.], [1000., 10., 0.])
+        );
+    }
+
+    #[test]
+    fn given_static_scene_expect_candidate_buffers_to_be_reused() {
+        let mut blockmap = IncrementalBlockMap::new();
+        let bodies = [circle(0, [0., 0., 0.], 10.0), circle(1, [15., 0., 0.], 10.0)];
+        let mut candidates = vec![];
+        blockmap.collision_detection_into(bodies.iter(), &mut candidates);
+        let buffer = candidates[0].indices.as_ptr();
+        blockmap.collision_detection_into(bodies.iter(), &mut candidates);
+        assert_eq!(buffer, candidates[0].indices.as_ptr());
+    }
+
+    #[test]
+    fn given_random_moving_scene_expect_all_overlapping_pairs_to_be_candidates() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut bodies: Vec<RigidBody> = (0..300)
+            .map(|i| {
+                let position = [rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0), 0.0];
+                circle(i, position, rng.gen_range(2.0..10.0))
+            })
+            .collect();
+
+        let mut blockmap = IncrementalBlockMap::new();
+        for _ in 0..10 {
+            let candidates = blockmap.collision_detection(bodies.iter());
+            for i in 0..bodies.len() {
+                for j in (i + 1)..bodies.len() {
+                    if bodies[i].aabb().overlaps(&bodies[j].aabb()) {
+                        assert!(contains_pair(&candidates, i, j), "Missing pair ({i}, {j})");
+                    }
+                }
+            }
+            bodies.iter_mut().for_each(|b| {
+                b.position.x += rng.gen_range(-15.0..15.0);
+                b.position.y += rng.gen_range(-15.0..15.0);
+            });
+        }
+    }
+}
@@ -1,13 +1,39 @@
-use super::collision::RigidBody;
+use super::collision::{Aabb, RigidBody};
+use super::query::QueryGrid;
 
+mod aabb_tree;
 mod blockmap;
+mod candidate_statistics;
+mod incremental_blockmap;
 mod spatial_subdivision;
+mod sweep_and_prune;
 
+pub use aabb_tree::AabbTree;
 pub use blockmap::BlockMap;
+pub use candidate_statistics::CandidateStatistics;
+pub use incremental_blockmap::IncrementalBlockMap;
+pub use spatial_subdivision::sparse_spatial_subdivision::SparseSpatialSubdivision;
 pub use spatial_subdivision::spatial_subdivision::SpatialSubdivision;
+pub use sweep_and_prune::{SweepAndPrune, SweepAxis};
 
+/// Finds groups of bodies that may be colliding. Implementations are allowed to keep
+/// state between calls, bodies are identified by their position in the iterator.
 pub trait BroadPhase<T> {
-    fn collision_detection<'a, I>(&self, bodies: I) -> T
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> T
     where
         I: Iterator<Item = &'a RigidBody>;
 }
+
+/// Refills a query grid with cells the size of the average body. Used by the
+/// broadphases that have no cells of their own.
+fn update_average_size_query_grid<I>(grid: &mut QueryGrid, aabbs: I)
+where
+    I: Iterator<Item = (usize, Aabb)> + Clone,
+{
+    let cell_size = QueryGrid::average_cell_size(aabbs.clone().map(|(_, aabb)| aabb));
+
+    grid.reset([0.0, 0.0, 0.0], cell_size);
+    for (i, aabb) in aabbs {
+        grid.insert_aabb(i, &aabb);
+    }
+}
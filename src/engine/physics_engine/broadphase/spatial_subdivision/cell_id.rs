@@ -1,13 +1,13 @@
 
 #[derive(PartialEq, Debug)]
 pub struct CellId {
-    pub cell_id: (u32,u32,u32),
+    pub cell_id: (i32,i32,i32),
     pub cell_object_type: CellIdType,
     pub object_id: usize,
 }
 
 impl CellId {
-    pub fn new(cell_id: (u32,u32,u32), cell_type:CellIdType, object_id: usize) -> Self {
+    pub fn new(cell_id: (i32,i32,i32), cell_type:CellIdType, object_id: usize) -> Self {
         Self {cell_id, cell_object_type: cell_type, object_id }
     }
 }
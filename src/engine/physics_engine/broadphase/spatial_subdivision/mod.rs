@@ -1,3 +1,4 @@
+pub mod sparse_spatial_subdivision;
 pub mod spatial_subdivision;
 
 mod cell_id;
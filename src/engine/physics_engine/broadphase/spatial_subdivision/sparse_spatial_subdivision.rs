@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+use crate::engine::physics_engine::collision::{Aabb, RigidBody};
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+use super::super::BroadPhase;
+use super::cell_id::CellId;
+use super::object_id::ObjectId;
+use super::spatial_subdivision::{
+    assert_object_id_in_candidate_list_exists_in_no_other_candidate_list, SpatialSubdivision,
+};
+
+/// Variant of `SpatialSubdivision` that works directly on signed cell coordinates.
+/// The bodies are not shifted into the positive quadrant, instead the cell objects
+/// are grouped in a hash map keyed by cell. Memory is proportional to the number of
+/// occupied cells, so the world may be arbitrarily large and bodies may wander far
+/// into negative space.
+///
+/// The output is the same four pass layout as `SpatialSubdivision`, where the
+/// candidate lists within a pass share no bodies and can be resolved in parallel.
+/// The lists of a pass are ordered by cell, the output of two calls with the same
+/// bodies is identical.
+pub struct SparseSpatialSubdivision {
+    query_grid: QueryGrid,
+}
+
+impl SparseSpatialSubdivision {
+    pub fn new() -> Self {
+        Self {
+            query_grid: QueryGrid::new([0.0, 0.0, 0.0], 1.0),
+        }
+    }
+}
+
+impl std::default::Default for SparseSpatialSubdivision {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BroadPhase<[Vec<CollisionCandidates>; 4]> for SparseSpatialSubdivision {
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> [Vec<CollisionCandidates>; 4]
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let bodies: Vec<&RigidBody> = bodies.collect();
+        if bodies.is_empty() {
+            self.query_grid.reset([0.0, 0.0, 0.0], 1.0);
+            return [vec![], vec![], vec![], vec![]];
+        }
+        let (bvolumes, _) = SpatialSubdivision::world_bounding_volumes(&bodies);
+
+        let cell_size = SpatialSubdivision::cell_size(&bvolumes, 1.0);
+        let (object_id_array, cell_id_array_nested): (Vec<ObjectId>, Vec<Vec<CellId>>) = bvolumes
+            .par_iter()
+            .enumerate()
+            .map(|(i, b)| SpatialSubdivision::create_cell_object(b, cell_size, i))
+            .unzip();
+
+        self.query_grid
+            .reset_with_cell_size([0.0, 0.0, 0.0], cell_size);
+        for (i, (b, cell_ids)) in bvolumes.iter().zip(cell_id_array_nested.iter()).enumerate() {
+            self.query_grid.insert(
+                i,
+                &Aabb::from_center(b.center.into(), b.half_width, b.half_height),
+                cell_ids
+                    .iter()
+                    .map(|c| (c.cell_id.0 as i64, c.cell_id.1 as i64)),
+            );
+        }
+
+        let mut cells: HashMap<(i32, i32, i32), Vec<&CellId>> = HashMap::new();
+        for cell_id in cell_id_array_nested.iter().flatten() {
+            cells.entry(cell_id.cell_id).or_default().push(cell_id);
+        }
+
+        // The cells are visited in order of their coordinates such that the output does
+        // not depend on the iteration order of the map
+        let mut occupied_cells: Vec<_> = cells.iter().filter(|(_, cell)| cell.len() > 1).collect();
+        occupied_cells.sort_unstable_by_key(|(cell_id, _)| **cell_id);
+        let cell_collisions: Vec<(u8, Vec<usize>)> = occupied_cells
+            .par_iter()
+            .map(|(_, cell)| SpatialSubdivision::cell_collisions(cell, &object_id_array))
+            .collect();
+        let passes = SpatialSubdivision::split_into_passes(cell_collisions.into_iter());
+
+        debug_assert!(
+            passes
+                .iter()
+                .all(assert_object_id_in_candidate_list_exists_in_no_other_candidate_list),
+            "Expected each object id to appear at most once within the same pass:\n{passes:?}"
+        );
+
+        passes
+    }
+}
+
+impl SpatialQuery for SparseSpatialSubdivision {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.query_grid.refill(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::MetricSpace;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::engine::physics_engine::broadphase::BroadPhase;
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::util::zero;
+
+    use super::super::spatial_subdivision::assert_object_id_in_candidate_list_exists_in_no_other_candidate_list;
+    use super::SparseSpatialSubdivision;
+
+    fn circle(id: usize, position: [f32; 3], radius: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius })
+            .build()
+    }
+
+    fn contains_pair(passes: &[Vec<CollisionCandidates>; 4], i: usize, j: usize) -> bool {
+        passes
+            .iter()
+            .flatten()
+            .any(|c| c.indices.contains(&i) && c.indices.contains(&j))
+    }
+
+    #[test]
+    fn rect_circle_are_possible_collision_candidates() {
+        let mut spatial_subdivision = SparseSpatialSubdivision::new();
+        let circ = circle(0, zero(), 50.0);
+        let rect = RigidBodyBuilder::default()
+            .id(1)
+            .position(zero())
+            .body_type(RigidBodyType::Rectangle {
+                width: 50.0,
+                height: 50.0,
+            })
+            .build();
+
+        let passes = spatial_subdivision.collision_detection([circ, rect].iter());
+        assert!(contains_pair(&passes, 0, 1));
+    }
+
+    #[test]
+    fn given_bodies_overlapping_the_axes_expect_candidates_across_cells() {
+        let mut spatial_subdivision = SparseSpatialSubdivision::new();
+        let bodies = [
+            circle(0, [-5., -5., 0.], 10.0),
+            circle(1, [5., 5., 0.], 10.0),
+            circle(2, [-5., 8., 0.], 10.0),
+        ];
+        let passes = spatial_subdivision.collision_detection(bodies.iter());
+        assert!(contains_pair(&passes, 0, 1));
+        assert!(contains_pair(&passes, 0, 2));
+        assert!(contains_pair(&passes, 1, 2));
+    }
+
+    #[test]
+    fn given_bodies_far_apart_expect_only_nearby_pairs() {
+        let mut spatial_subdivision = SparseSpatialSubdivision::new();
+        let bodies = [
+            circle(0, [-1.0e6, -1.0e6, 0.], 10.0),
+            circle(1, [-1.0e6 + 15.0, -1.0e6, 0.], 10.0),
+            circle(2, [1.0e6, 1.0e6, 0.], 10.0),
+            circle(3, [1.0e6, 1.0e6 - 15.0, 0.], 10.0),
+        ];
+        let passes = spatial_subdivision.collision_detection(bodies.iter());
+        assert!(contains_pair(&passes, 0, 1));
+        assert!(contains_pair(&passes, 2, 3));
+        assert!(!contains_pair(&passes, 0, 2));
+        assert!(!contains_pair(&passes, 1, 3));
+    }
+
+    #[test]
+    fn given_random_scene_around_origin_expect_all_overlapping_pairs_to_be_candidates() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let bodies: Vec<RigidBody> = (0..500)
+            .map(|i| {
+                let position = [
+                    rng.gen_range(-400.0..400.0),
+                    rng.gen_range(-400.0..400.0),
+                    0.0,
+                ];
+                circle(i, position, rng.gen_range(2.0..10.0))
+            })
+            .collect();
+
+        let mut spatial_subdivision = SparseSpatialSubdivision::new();
+        let passes = spatial_subdivision.collision_detection(bodies.iter());
+        for pass in passes.iter() {
+            assert!(assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(pass));
+        }
+        for i in 0..bodies.len() {
+            for j in (i + 1)..bodies.len() {
+                let (radius_i, radius_j) = match (&bodies[i].body_type, &bodies[j].body_type) {
+                    (
+                        RigidBodyType::Circle { radius: ri },
+                        RigidBodyType::Circle { radius: rj },
+                    ) => (*ri, *rj),
+                    _ => unreachable!(),
+                };
+                let distance = bodies[i].position.distance(bodies[j].position);
+                if distance < radius_i + radius_j {
+                    assert!(contains_pair(&passes, i, j), "Missing pair ({i}, {j})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn given_same_bodies_twice_expect_identical_candidates() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let bodies: Vec<RigidBody> = (0..300)
+            .map(|i| {
+                let position = [
+                    rng.gen_range(-200.0..200.0),
+                    rng.gen_range(-200.0..200.0),
+                    0.0,
+                ];
+                circle(i, position, rng.gen_range(2.0..10.0))
+            })
+            .collect();
+        let indices = |passes: [Vec<CollisionCandidates>; 4]| {
+            passes.map(|pass| pass.into_iter().map(|c| c.indices).collect::<Vec<_>>())
+        };
+
+        let first = indices(SparseSpatialSubdivision::new().collision_detection(bodies.iter()));
+        let second = indices(SparseSpatialSubdivision::new().collision_detection(bodies.iter()));
+
+        assert_eq!(first, second);
+    }
+}
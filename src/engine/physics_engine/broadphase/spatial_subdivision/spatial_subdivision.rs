@@ -1,34 +1,88 @@
 use std::collections::{HashMap, HashSet};
 
-use cgmath::{InnerSpace, MetricSpace, Vector3};
+use cgmath::{MetricSpace, Vector3};
 use rayon::iter::{
     IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
 };
 
 use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
-use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
+use crate::engine::physics_engine::collision::{Aabb, RigidBody, RigidBodyType};
 
-use super::super::BroadPhase;
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+use super::super::{BroadPhase, CandidateStatistics};
 use super::cell_id::{CellId, CellIdType};
 use super::object_id::ObjectId;
 
+/// Axis aligned box around a body. Circles keep their radius such that the diagonal
+/// cells can be tested against the circle instead of the box.
 #[derive(Debug)]
-struct BoundingCircle {
+pub(super) struct BoundingVolume {
     pub center: Vector3<f32>,
-    pub radius: f32,
+    pub half_width: f32,
+    pub half_height: f32,
+    pub radius: Option<f32>,
 }
 
-impl std::fmt::Display for BoundingCircle {
+impl BoundingVolume {
+    pub fn circle(center: Vector3<f32>, radius: f32) -> Self {
+        Self {
+            center,
+            half_width: radius,
+            half_height: radius,
+            radius: Some(radius),
+        }
+    }
+
+    pub fn aabb(aabb: &Aabb) -> Self {
+        Self {
+            center: aabb.center(),
+            half_width: aabb.width() / 2.0,
+            half_height: aabb.height() / 2.0,
+            radius: None,
+        }
+    }
+}
+
+impl std::fmt::Display for BoundingVolume {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "BoundingCircle{{ center: {:?}, radius: {} }}",
-            self.center, self.radius
+            "BoundingVolume{{ center: {:?}, half extents: ({}, {}), radius: {:?} }}",
+            self.center, self.half_width, self.half_height, self.radius
         )
     }
 }
 
-pub struct SpatialSubdivision {}
+/// Measurements of the last call to `collision_detection`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpatialSubdivisionStatistics {
+    /// World position of the corner of cell (0,0)
+    pub origin: [f32; 3],
+    pub cell_width: f32,
+    pub cell_height: f32,
+    /// Bodies larger than a cell, these are tested against their neighbours directly
+    pub num_oversized: usize,
+    pub passes: [CandidateStatistics; 4],
+}
+
+/// Broadphase based on the spatial subdivision of GPU Gems 3, chapter 32. Bodies are
+/// sorted into the cells their bounding box overlaps and the cells are processed in
+/// four passes, one per cell type, such that the candidate lists within a pass share
+/// no bodies and can be resolved in parallel.
+///
+/// Rectangles use the bounding box of their rotated corners. The cells are sized from
+/// a percentile of the body sizes, with separate widths and heights. Bodies larger
+/// than a cell are tested against the bodies overlapping their bounding box and
+/// added to the first pass in which their neighbours are still free.
+///
+/// The lists of a pass are ordered by cell and the bodies of a cell by index, such
+/// that the output of two calls with the same bodies is identical.
+pub struct SpatialSubdivision {
+    cell_size_percentile: f32,
+    statistics: SpatialSubdivisionStatistics,
+    query_grid: QueryGrid,
+}
 
 const CONTROL_BIT_BOUNDING_VOLUME_1: u8 = 0b0000_0001;
 const CONTROL_BIT_BOUNDING_VOLUME_2: u8 = 0b0000_0010;
@@ -42,13 +96,32 @@ const BOUNDING_VOLUME_MASK: u8 = 0b0000_1111;
 const HOME_CELL_MASK: u8 = 0b1111_0000;
 
 impl SpatialSubdivision {
+    /// Cells fit the largest body
     pub fn new() -> Self {
-        Self {}
+        Self::with_cell_size_percentile(1.0)
     }
 
-    fn get_control_bit_for_home_cell_id(cell_id: (u32, u32, u32)) -> u8 {
-        let x_mod = cell_id.0 % 2;
-        let y_mod = cell_id.1 % 2;
+    /// Cells fit the given fraction of the bodies, e.g. 0.9 sizes the cells from the
+    /// 90th percentile of the body widths and heights
+    pub fn with_cell_size_percentile(cell_size_percentile: f32) -> Self {
+        debug_assert!(
+            cell_size_percentile > 0.0 && cell_size_percentile <= 1.0,
+            "Expected percentile in (0, 1], found {cell_size_percentile}"
+        );
+        Self {
+            cell_size_percentile,
+            statistics: SpatialSubdivisionStatistics::default(),
+            query_grid: QueryGrid::new([0.0, 0.0, 0.0], 1.0),
+        }
+    }
+
+    pub fn statistics(&self) -> &SpatialSubdivisionStatistics {
+        &self.statistics
+    }
+
+    fn get_control_bit_for_home_cell_id(cell_id: (i32, i32, i32)) -> u8 {
+        let x_mod = cell_id.0.rem_euclid(2);
+        let y_mod = cell_id.1.rem_euclid(2);
         match (x_mod, y_mod) {
             (0, 0) => CONTROL_BIT_HOME_CELL_1, // Top-left cell
             (1, 0) => CONTROL_BIT_HOME_CELL_2, // Top-right cell
@@ -58,9 +131,9 @@ impl SpatialSubdivision {
         }
     }
 
-    fn get_control_bit_for_bounding_volume_cell_id(cell_id: (u32, u32, u32)) -> u8 {
-        let x_mod = cell_id.0 % 2;
-        let y_mod = cell_id.1 % 2;
+    fn get_control_bit_for_bounding_volume_cell_id(cell_id: (i32, i32, i32)) -> u8 {
+        let x_mod = cell_id.0.rem_euclid(2);
+        let y_mod = cell_id.1.rem_euclid(2);
         match (x_mod, y_mod) {
             (0, 0) => CONTROL_BIT_BOUNDING_VOLUME_1, // Top-left cell
             (1, 0) => CONTROL_BIT_BOUNDING_VOLUME_2, // Top-right cell
@@ -70,209 +143,302 @@ impl SpatialSubdivision {
         }
     }
 
-    /// Create the cell object for a given bounding sphere
-    fn create_cell_object(
-        bcircle: &BoundingCircle,
-        cell_width: f32,
+    /// Create the cell object for a given bounding volume. Cell coordinates are signed,
+    /// a volume overlapping the left or top of cell 0 is assigned to cell -1. The
+    /// volume is expected to be at most as large as a cell.
+    pub(super) fn create_cell_object(
+        bvolume: &BoundingVolume,
+        cell_size: (f32, f32),
         object_id: usize,
     ) -> (ObjectId, Vec<CellId>) {
-        let x = bcircle.center.x;
-        let y = bcircle.center.y;
-        let radius = bcircle.radius;
-        debug_assert!(x >= 0.0, "Expected x to be 0 or more, found {x}");
-        debug_assert!(y >= 0.0, "Expected y to be 0 or more, found {y}");
-
-        let x_norm = x / cell_width;
-        let y_norm = y / cell_width;
-        let r_norm = radius / cell_width;
-        let xy_norm = Vector3::new(x_norm, y_norm, 0.0);
+        let (cell_width, cell_height) = cell_size;
+        let x_norm = bvolume.center.x / cell_width;
+        let y_norm = bvolume.center.y / cell_height;
+        let half_width_norm = bvolume.half_width / cell_width;
+        let half_height_norm = bvolume.half_height / cell_height;
 
         // Global cell mean cell number in entire grid
-        let home_cell_x = x_norm.floor() as u32;
-        let home_cell_y = y_norm.floor() as u32;
+        let home_cell_x = x_norm.floor() as i32;
+        let home_cell_y = y_norm.floor() as i32;
         let home_cell_id = CellId::new((home_cell_x, home_cell_y, 0), CellIdType::Home, object_id);
         let mut control_bits = Self::get_control_bit_for_home_cell_id(home_cell_id.cell_id);
         control_bits |= Self::get_control_bit_for_bounding_volume_cell_id(home_cell_id.cell_id);
 
-        // Determine which quad of its cell the center belongs to
+        // Determine which quad of its cell the center belongs to. Once we have determined
+        // the quad, we only need to check for overlap on 3 cells, sides and diagonal
         let quad_x = x_norm - x_norm.floor();
         let quad_y = y_norm - y_norm.floor();
+        let side_x = match quad_x < 0.5 {
+            true => (quad_x - half_width_norm < 0.0).then_some(-1),
+            false => (quad_x + half_width_norm > 1.0).then_some(1),
+        };
+        let side_y = match quad_y < 0.5 {
+            true => (quad_y - half_height_norm < 0.0).then_some(-1),
+            false => (quad_y + half_height_norm > 1.0).then_some(1),
+        };
 
-        debug_assert!(
-            {
-                let pred =
-                    (home_cell_x == 0 && r_norm > quad_x) || (home_cell_y == 0 && r_norm > quad_y);
-                if pred {
-                    eprintln!("Expected that the object always be offset such it can't overlap the left or top cell if the home cell x or y value is 0.");
-                    eprintln!("The offending object has id {object_id}");
-                    eprintln!("Bounding Circle: {bcircle}");
-                    eprintln!("cell_width: {cell_width}");
-                    eprintln!("x_norm: {x_norm}, y_norm: {y_norm}, r_norm: {r_norm}");
-                    eprintln!("quad_x: {quad_x}, quad_y: {quad_y}");
-                    eprintln!("home_cell_x: {home_cell_x}, home_cell_y: {home_cell_y}")
-                }
-                !pred
-            },
-            "Object spaning over negative values cell x-values, see below for more detail"
-        );
+        let mut neighbours = vec![];
+        if let Some(dx) = side_x {
+            neighbours.push((home_cell_x + dx, home_cell_y));
+        }
+        if let Some(dy) = side_y {
+            neighbours.push((home_cell_x, home_cell_y + dy));
+        }
+        if let (Some(dx), Some(dy)) = (side_x, side_y) {
+            // A box overlapping both sides overlaps the diagonal, a circle only if it
+            // contains the shared corner
+            let corner = Vector3::new(
+                (x_norm.floor() + dx.max(0) as f32) * cell_width,
+                (y_norm.floor() + dy.max(0) as f32) * cell_height,
+                0.0,
+            );
+            let overlaps_corner = match bvolume.radius {
+                Some(radius) => corner.distance2(bvolume.center) < radius.powi(2),
+                None => true,
+            };
+            if overlaps_corner {
+                neighbours.push((home_cell_x + dx, home_cell_y + dy));
+            }
+        }
 
-        // Once we have determined the quad, we only need to check for overlap on 3
-        // cells, sides and diagonal
         let mut cell_ids = vec![home_cell_id];
-        match (quad_x < 0.5, quad_y < 0.5) {
-            (true, true) => {
-                // top left
-                // Overlap check left cell
-                if quad_x - r_norm < 0.0 {
-                    let cell_id = (home_cell_x - 1, home_cell_y, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+        for (x, y) in neighbours {
+            let phantom = CellId::new((x, y, 0), CellIdType::Phantom, object_id);
+            control_bits |= Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
+            cell_ids.push(phantom);
+        }
 
-                // Overlap check top cell
-                if quad_y - r_norm < 0.0 {
-                    let cell_id = (home_cell_x, home_cell_y - 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+        let object_id = ObjectId { control_bits };
+        (object_id, cell_ids)
+    }
 
-                // Overlap check with the top left cell
-                let home_cell_top_left_corner = Vector3::new(x_norm.floor(), y_norm.floor(), 0.0);
-                if home_cell_top_left_corner.distance2(xy_norm) < r_norm.powi(2) {
-                    let cell_id = (home_cell_x - 1, home_cell_y - 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
-            }
-            (false, true) => {
-                // top right
-                // Overlap check right cell
-                if quad_x + r_norm > 1.0 {
-                    let cell_id = (home_cell_x + 1, home_cell_y, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+    /// Computes the bounding volume of each body, offset such that all volumes only
+    /// overlap the positive x and y axis. Returns the volumes and the offset that was
+    /// subtracted.
+    fn bounding_volumes(bodies: &Vec<&RigidBody>) -> (Vec<BoundingVolume>, Vector3<f32>) {
+        let (mut bvolumes, offset) = Self::world_bounding_volumes(bodies);
+        bvolumes.par_iter_mut().for_each(|b| {
+            b.center -= offset;
+        });
+        (bvolumes, offset)
+    }
 
-                // Overlap check top cell
-                if quad_y - r_norm < 0.0 {
-                    let cell_id = (home_cell_x, home_cell_y - 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
+    /// Computes the bounding volume of each body in world coordinates. Returns the
+    /// volumes and the rounded down top left corner of the scene.
+    pub(super) fn world_bounding_volumes(
+        bodies: &Vec<&RigidBody>,
+    ) -> (Vec<BoundingVolume>, Vector3<f32>) {
+        let bvolumes: Vec<BoundingVolume> = bodies
+            .par_iter()
+            .map(|b| match b.body_type {
+                RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                    BoundingVolume::circle(b.position, radius)
                 }
+                RigidBodyType::Rectangle { .. } => BoundingVolume::aabb(&b.aabb()),
+                _ => panic!("Unknown body type {}", b.body_type),
+            })
+            .collect();
+        let (min_x, min_y) = bvolumes
+            .par_iter()
+            .map(|b| (b.center.x - b.half_width, b.center.y - b.half_height))
+            .reduce(
+                || (f32::MAX, f32::MAX),
+                |acc, (x, y)| (acc.0.min(x), acc.1.min(y)),
+            );
 
-                // Overlap check top right cell
-                let home_cell_top_right_corner =
-                    Vector3::new(x_norm.floor() + 1.0, y_norm.floor(), 0.0);
-                if home_cell_top_right_corner.distance2(xy_norm) < r_norm.powi(2) {
-                    let cell_id = (home_cell_x + 1, home_cell_y - 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
-            }
-            (true, false) => {
-                // bottom left
-                // Overlap check left cell
-                if quad_x - r_norm < 0.0 {
-                    let cell_id = (home_cell_x - 1, home_cell_y, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+        // Handle floating point errors by rounding the offset to the larger or smaller number
+        let offset = Vector3::new(min_x.floor(), min_y.floor(), 0.0);
+        (bvolumes, offset)
+    }
 
-                // Overlap check bottom cell
-                if quad_y + r_norm > 1.0 {
-                    let cell_id = (home_cell_x, home_cell_y + 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+    /// Returns the width and height of the cells such that the given fraction of the
+    /// bounding volumes fits in a cell with some room to spare
+    pub(super) fn cell_size(bvolumes: &[BoundingVolume], percentile: f32) -> (f32, f32) {
+        if bvolumes.is_empty() {
+            return (1.0, 1.0);
+        }
+        let nth =
+            ((bvolumes.len() as f32 * percentile).ceil() as usize).clamp(1, bvolumes.len()) - 1;
+        let percentile_extent = |mut extents: Vec<f32>| {
+            let (_, extent, _) = extents.select_nth_unstable_by(nth, |a, b| a.total_cmp(b));
+            (*extent * 2.0 * 1.5).max(f32::EPSILON)
+        };
+        (
+            percentile_extent(bvolumes.iter().map(|b| b.half_width).collect()),
+            percentile_extent(bvolumes.iter().map(|b| b.half_height).collect()),
+        )
+    }
+
+    /// Finds the bodies overlapping each of the volumes too large for the cells. Each
+    /// oversized volume results in a list starting with the oversized body.
+    fn oversized_collisions(
+        bvolumes: &[BoundingVolume],
+        is_oversized: &[bool],
+        cell_size: (f32, f32),
+    ) -> Vec<CollisionCandidates> {
+        let aabb_of =
+            |b: &BoundingVolume| Aabb::from_center(b.center.into(), b.half_width, b.half_height);
+        let mut grid = QueryGrid::new([0.0, 0.0, 0.0], cell_size.0.max(cell_size.1));
+        bvolumes
+            .iter()
+            .enumerate()
+            .for_each(|(i, b)| grid.insert_aabb(i, &aabb_of(b)));
 
-                // Overlap check bottom left cell
-                let home_cell_bottom_left_corner =
-                    Vector3::new(x_norm.floor(), y_norm.floor() + 1.0, 0.0);
-                if home_cell_bottom_left_corner.distance2(xy_norm) < r_norm.powi(2) {
-                    let cell_id = (home_cell_x - 1, home_cell_y + 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
+        (0..bvolumes.len())
+            .filter(|i| is_oversized[*i])
+            .filter_map(|i| {
+                let aabb = aabb_of(&bvolumes[i]);
+                let mut collisions = vec![i];
+                collisions.extend(grid.candidates_in_aabb(&aabb).into_iter().filter(|j| {
+                    // Pairs of oversized bodies are found from the lower index only
+                    *j != i
+                        && !(is_oversized[*j] && *j < i)
+                        && aabb.overlaps(&aabb_of(&bvolumes[*j]))
+                }));
+                (collisions.len() > 1).then(|| CollisionCandidates::new(collisions))
+            })
+            .collect()
+    }
+
+    /// Adds each list to the first pass not containing any of its bodies. The lists
+    /// that fit no pass are merged into the last pass.
+    fn add_to_passes(passes: &mut [Vec<CollisionCandidates>; 4], lists: Vec<CollisionCandidates>) {
+        let mut bodies_in_pass: [HashSet<usize>; 4] = passes.each_ref().map(|pass| {
+            pass.iter()
+                .flat_map(|c| c.indices.iter().copied())
+                .collect()
+        });
+        let mut needs_merge = false;
+        for list in lists {
+            match bodies_in_pass
+                .iter()
+                .position(|bodies| list.indices.iter().all(|i| !bodies.contains(i)))
+            {
+                Some(pass) => {
+                    bodies_in_pass[pass].extend(list.indices.iter().copied());
+                    passes[pass].push(list);
                 }
-            }
-            (false, false) => {
-                // bottom right
-                // Overlap check right cell
-                if quad_x + r_norm > 1.0 {
-                    let cell_id = (home_cell_x + 1, home_cell_y, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
+                None => {
+                    passes[3].push(list);
+                    needs_merge = true;
                 }
+            }
+        }
+        if needs_merge {
+            passes[3] = Self::merge_lists_sharing_bodies(std::mem::take(&mut passes[3]));
+        }
+    }
 
-                // Overlap check bottom cell
-                if quad_y + r_norm > 1.0 {
-                    let cell_id = (home_cell_x, home_cell_y + 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
-                }
+    /// Merges the candidate lists sharing a body such that every body appears in at
+    /// most one list. The merged lists are sorted, as are the bodies within them.
+    fn merge_lists_sharing_bodies(lists: Vec<CollisionCandidates>) -> Vec<CollisionCandidates> {
+        fn find(parents: &mut [usize], mut list: usize) -> usize {
+            while parents[list] != list {
+                parents[list] = parents[parents[list]];
+                list = parents[list];
+            }
+            list
+        }
 
-                // Overlap check bottom right cell
-                let home_cell_bottom_right_corner =
-                    Vector3::new(x_norm.floor() + 1.0, y_norm.floor() + 1.0, 0.0);
-                if home_cell_bottom_right_corner.distance2(xy_norm) < r_norm.powi(2) {
-                    let cell_id = (home_cell_x + 1, home_cell_y + 1, 0);
-                    let phantom = CellId::new(cell_id, CellIdType::Phantom, object_id);
-                    control_bits |=
-                        Self::get_control_bit_for_bounding_volume_cell_id(phantom.cell_id);
-                    cell_ids.push(phantom);
+        let mut parents: Vec<usize> = (0..lists.len()).collect();
+        let mut list_of_body: HashMap<usize, usize> = HashMap::new();
+        for (list, candidates) in lists.iter().enumerate() {
+            for body in candidates.indices.iter() {
+                if let Some(other_list) = list_of_body.insert(*body, list) {
+                    let (root, other_root) =
+                        (find(&mut parents, list), find(&mut parents, other_list));
+                    parents[root] = other_root;
                 }
             }
         }
 
-        let object_id = ObjectId { control_bits };
-        return (object_id, cell_ids);
+        let mut merged: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (list, candidates) in lists.into_iter().enumerate() {
+            let root = find(&mut parents, list);
+            merged.entry(root).or_default().extend(candidates.indices);
+        }
+        let mut merged: Vec<Vec<usize>> = merged
+            .into_values()
+            .map(|bodies| {
+                let mut bodies: Vec<usize> = bodies.into_iter().collect();
+                bodies.sort_unstable();
+                bodies
+            })
+            .collect();
+        merged.sort_unstable();
+        merged.into_iter().map(CollisionCandidates::new).collect()
     }
 
     fn cumsum(l: &[&CellId]) -> Vec<(u32, u32)> {
+        if l.is_empty() {
+            return vec![];
+        }
         let last_index = l.len() as u32 - 1;
         let (_, _, _, sum) = l.iter().fold(
-            (0, 0, 0_u32, vec![]),
+            (0, (0, 0, 0), 0_u32, vec![]),
             |(i, prev_cell_id, count, mut acc), object| {
                 let is_last = i == last_index;
-                let transition = prev_cell_id != Self::hash(object.cell_id);
+                let transition = prev_cell_id != object.cell_id;
                 if transition {
                     acc.push((i - count, count));
                     if is_last {
                         acc.push((i - 1, 1));
                     }
-                    return (i + 1, Self::hash(object.cell_id), 1, acc);
+                    return (i + 1, object.cell_id, 1, acc);
                 } else if is_last {
                     acc.push((i - count, count + 1));
                 }
-                return (i + 1, Self::hash(object.cell_id), count + 1, acc);
+                return (i + 1, object.cell_id, count + 1, acc);
             },
         );
         return sum;
     }
 
-    fn hash(cell_id: (u32, u32, u32)) -> u32 {
-        cell_id.0 + cell_id.1 * 1_000 + cell_id.2 * 1_000_000
+    /// Returns the pass of the cell and the objects in the cell that have to be tested
+    /// against each other during that pass, in increasing order
+    pub(super) fn cell_collisions(
+        cell: &[&CellId],
+        object_id_array: &[ObjectId],
+    ) -> (u8, Vec<usize>) {
+        let mut collision_set = HashSet::new();
+        let pass_num = Self::get_control_bit_for_bounding_volume_cell_id(cell[0].cell_id);
+        // The pass number is the bit of the cell type, the skip test counts passes from 1
+        let t = pass_num.trailing_zeros() as u8 + 1;
+        for i in 0..cell.len() {
+            let cell_id_a = cell[i];
+            let object_id_a = &object_id_array[cell_id_a.object_id];
+            for cell_id_b in cell[(i + 1)..].iter() {
+                let object_id_b = &object_id_array[cell_id_b.object_id];
+                if !Self::can_we_skip_collision_test(t, object_id_a, object_id_b) {
+                    collision_set.insert(cell_id_a.object_id);
+                    collision_set.insert(cell_id_b.object_id);
+                }
+            }
+        }
+        let mut collisions: Vec<usize> = collision_set.into_iter().collect();
+        collisions.sort_unstable();
+        (pass_num, collisions)
+    }
+
+    /// Sorts the candidates of each cell into the pass of the cell
+    pub(super) fn split_into_passes<I>(cell_collisions: I) -> [Vec<CollisionCandidates>; 4]
+    where
+        I: Iterator<Item = (u8, Vec<usize>)>,
+    {
+        let mut passes = [vec![], vec![], vec![], vec![]];
+        cell_collisions
+            .filter(|(_, collisions)| !collisions.is_empty())
+            .for_each(|(pass_num, collisions)| {
+                let pass = match pass_num {
+                    CONTROL_BIT_BOUNDING_VOLUME_1 => 0,
+                    CONTROL_BIT_BOUNDING_VOLUME_2 => 1,
+                    CONTROL_BIT_BOUNDING_VOLUME_3 => 2,
+                    CONTROL_BIT_BOUNDING_VOLUME_4 => 3,
+                    _ => unreachable!("Pass number should always be 1,2,4 or 8"),
+                };
+                passes[pass].push(CollisionCandidates::new(collisions));
+            });
+        passes
     }
 
     fn can_we_skip_collision_test(t: u8, object_id_a: &ObjectId, object_id_b: &ObjectId) -> bool {
@@ -307,75 +473,21 @@ impl SpatialSubdivision {
 }
 
 impl BroadPhase<[Vec<CollisionCandidates>; 4]> for SpatialSubdivision {
-    fn collision_detection<'a, I>(&self, bodies: I) -> [Vec<CollisionCandidates>; 4]
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> [Vec<CollisionCandidates>; 4]
     where
         I: Iterator<Item = &'a RigidBody>,
     {
         let bodies: Vec<&RigidBody> = bodies.collect();
-        let (mut bcircles, largest_radius, min_x, min_y) = bodies
-            .par_iter()
-            .filter_map(|b| match b.body_type {
-                RigidBodyType::Circle { radius } => {
-                    let radius = radius * 1.41;
-                    Some((
-                        BoundingCircle {
-                            center: b.position,
-                            radius,
-                        },
-                        radius,
-                        b.position.x,
-                        b.position.y,
-                    ))
-                }
-                RigidBodyType::Rectangle { width, height } => {
-                    let radius = Vector3::new(width / 2.0, height / 2.0, 0.0).magnitude() * 1.41;
-                    //let radius = (width.max(height) / 2.0)*1.41;
-                    Some((
-                        BoundingCircle {
-                            center: b.position,
-                            radius,
-                        },
-                        radius,
-                        b.position.x,
-                        b.position.y,
-                    ))
-                }
-                _ => panic!("Unknown body type {}", b.body_type),
-            })
-            .fold(
-                || (Vec::new(), 0.0_f32, f32::MAX, f32::MAX),
-                // TODO: No need to open up radius, x and y. Can only use circle
-                |mut acc, (circle, radius, x, y)| {
-                    acc.0.push(circle);
-                    acc.1 = acc.1.max(radius);
-                    acc.2 = acc.2.min(x - radius);
-                    acc.3 = acc.3.min(y - radius);
-                    acc
-                },
-            )
-            .reduce(
-                || (Vec::new(), 0.0, f32::MAX, f32::MAX),
-                |mut acc1, mut acc2| {
-                    acc1.0.append(&mut acc2.0);
-                    acc1.1 = acc1.1.max(acc2.1);
-                    acc1.2 = acc1.2.min(acc2.2);
-                    acc1.3 = acc1.3.min(acc2.3);
-                    acc1
-                },
-            );
-
-        // Handle floating point errors by rounding the offset to the larger or smaller number
-        let offset = Vector3::new(min_x.floor(), min_y.floor(), 0.0);
-        bcircles.par_iter_mut().for_each(|b| {
-            b.center -= offset;
-        });
+        let (bvolumes, offset) = Self::bounding_volumes(&bodies);
 
         debug_assert!(
             {
-                let bad_bodies: Vec<(usize, &BoundingCircle, &&RigidBody)> = bcircles
+                let bad_bodies: Vec<(usize, &BoundingVolume, &&RigidBody)> = bvolumes
                     .iter()
                     .enumerate()
-                    .filter(|(_, b)| (b.center.x - b.radius) < 0.0 || (b.center.y - b.radius) < 0.0)
+                    .filter(|(_, b)| {
+                        (b.center.x - b.half_width) < 0.0 || (b.center.y - b.half_height) < 0.0
+                    })
                     .map(|(i, b)| (i, b, &bodies[i]))
                     .collect();
                 let pred = bad_bodies.len() != 0;
@@ -391,32 +503,31 @@ impl BroadPhase<[Vec<CollisionCandidates>; 4]> for SpatialSubdivision {
             "Expected all objects to only overlap the positive x and y axis"
         );
 
-        let cell_width = largest_radius * 2.0 * 1.5;
-        let (object_id_array, cell_id_array_nested): (Vec<ObjectId>, Vec<Vec<CellId>>) = bcircles
+        let cell_size = Self::cell_size(&bvolumes, self.cell_size_percentile);
+        let is_oversized: Vec<bool> = bvolumes
+            .iter()
+            .map(|b| b.half_width * 2.0 > cell_size.0 || b.half_height * 2.0 > cell_size.1)
+            .collect();
+        let (object_id_array, cell_id_array_nested): (Vec<ObjectId>, Vec<Vec<CellId>>) = bvolumes
             .par_iter()
             .enumerate()
-            .map(|(i, b)| Self::create_cell_object(&b, cell_width, i))
+            .map(|(i, b)| match is_oversized[i] {
+                true => (ObjectId { control_bits: 0 }, vec![]),
+                false => Self::create_cell_object(b, cell_size, i),
+            })
             .unzip();
 
         let mut cell_id_array: Vec<&CellId> = cell_id_array_nested.par_iter().flatten().collect();
 
         // Sort the by cell id and emphasize x,y then z and Home over Phantom.
         cell_id_array.sort_by(|a, b| {
-            let cell_a = Self::hash(a.cell_id);
-            let cell_b = Self::hash(b.cell_id);
-            if cell_a == cell_b {
-                a.cell_object_type.cmp(&b.cell_object_type)
-            } else {
-                cell_a.cmp(&cell_b)
-            }
+            a.cell_id
+                .cmp(&b.cell_id)
+                .then(a.cell_object_type.cmp(&b.cell_object_type))
         });
 
-        let mut pass1 = vec![];
-        let mut pass2 = vec![];
-        let mut pass3 = vec![];
-        let mut pass4 = vec![];
         let cell_index = Self::cumsum(&cell_id_array);
-        cell_index
+        let cell_collisions = cell_index
             .iter()
             .filter(|(_, count)| *count > 1)
             .map(|(index, count)| {
@@ -428,42 +539,41 @@ impl BroadPhase<[Vec<CollisionCandidates>; 4]> for SpatialSubdivision {
                 debug_assert!(
                     {
                         // Verify that all cell objects belong to same cell
-                        let cell_id = Self::hash(slice[0].cell_id);
-                        slice.iter().fold(true, |acc, cell| {
-                            (Self::hash(cell.cell_id) == cell_id) && acc
-                        })
+                        let cell_id = slice[0].cell_id;
+                        slice.iter().all(|cell| cell.cell_id == cell_id)
                     },
                     "Expected all objects in slice to belong to the same home cell type: {slice:?}"
                 );
-                let mut collision_set = HashSet::new();
-                let pass_num = Self::get_control_bit_for_bounding_volume_cell_id(slice[0].cell_id);
-                for i in 0..slice.len() {
-                    let cell_id_a = slice[i];
-                    let object_id_a = &object_id_array[cell_id_a.object_id];
-                    for j in (i + 1)..slice.len() {
-                        let cell_id_b = slice[j];
-                        let object_id_b = &object_id_array[cell_id_b.object_id];
-                        match Self::can_we_skip_collision_test(pass_num, &object_id_a, object_id_b)
-                        {
-                            true => (),
-                            false => {
-                                collision_set.insert(cell_id_a.object_id);
-                                collision_set.insert(cell_id_b.object_id);
-                            }
-                        }
-                    }
-                }
-                let collision_list: Vec<usize> = collision_set.into_iter().collect();
-                return (pass_num, collision_list);
-            })
-            .filter(|(_, collisions)| collisions.len() > 0)
-            .for_each(|(pass_num, collisions)| match pass_num {
-                CONTROL_BIT_BOUNDING_VOLUME_1 => pass1.push(CollisionCandidates::new(collisions)),
-                CONTROL_BIT_BOUNDING_VOLUME_2 => pass2.push(CollisionCandidates::new(collisions)),
-                CONTROL_BIT_BOUNDING_VOLUME_3 => pass3.push(CollisionCandidates::new(collisions)),
-                CONTROL_BIT_BOUNDING_VOLUME_4 => pass4.push(CollisionCandidates::new(collisions)),
-                _ => unreachable!("Pass number should always be 1,2,4 or 8"),
+                Self::cell_collisions(slice, &object_id_array)
             });
+        let mut passes = Self::split_into_passes(cell_collisions);
+
+        let num_oversized = is_oversized.iter().filter(|o| **o).count();
+        if num_oversized > 0 {
+            let oversized_collisions =
+                Self::oversized_collisions(&bvolumes, &is_oversized, cell_size);
+            Self::add_to_passes(&mut passes, oversized_collisions);
+        }
+        let [pass1, pass2, pass3, pass4] = passes;
+
+        // The query grid holds the cells the bodies were assigned to, oversized bodies
+        // every cell their bounding box overlaps
+        self.query_grid
+            .reset_with_cell_size(offset.into(), cell_size);
+        for (i, (b, cell_ids)) in bvolumes.iter().zip(cell_id_array_nested.iter()).enumerate() {
+            let center = b.center + offset;
+            let aabb = Aabb::from_center(center.into(), b.half_width, b.half_height);
+            match is_oversized[i] {
+                true => self.query_grid.insert_aabb(i, &aabb),
+                false => self.query_grid.insert(
+                    i,
+                    &aabb,
+                    cell_ids
+                        .iter()
+                        .map(|c| (c.cell_id.0 as i64, c.cell_id.1 as i64)),
+                ),
+            }
+        }
 
         debug_assert!(
             pass1
@@ -507,15 +617,37 @@ impl BroadPhase<[Vec<CollisionCandidates>; 4]> for SpatialSubdivision {
             "Expected each object id to appear at most once within the same pass(3):\n{pass3:?}"
         );
         debug_assert!(
-            assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(&pass3),
+            assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(&pass4),
             "Expected each object id to appear at most once within the same pass(4):\n{pass4:?}"
         );
 
+        self.statistics = SpatialSubdivisionStatistics {
+            origin: offset.into(),
+            cell_width: cell_size.0,
+            cell_height: cell_size.1,
+            num_oversized,
+            passes: [&pass1, &pass2, &pass3, &pass4]
+                .map(|pass| CandidateStatistics::from_candidates(pass.iter())),
+        };
+
         return [pass1, pass2, pass3, pass4];
     }
 }
 
-fn assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(
+impl SpatialQuery for SpatialSubdivision {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.query_grid.refill(bodies);
+    }
+}
+
+pub(super) fn assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(
     pass: &Vec<CollisionCandidates>,
 ) -> bool {
     let mut index_counts: HashMap<usize, usize> = HashMap::new();
@@ -536,7 +668,7 @@ mod tests {
 
     #[allow(non_snake_case)]
     mod compute_overlapping_cell_types {
-        use super::super::BoundingCircle;
+        use super::super::BoundingVolume;
         use super::super::CellId;
         use super::super::CellIdType;
         use super::super::SpatialSubdivision;
@@ -548,8 +680,8 @@ mod tests {
                     fn $name() {
                         let (x,y) = $xy;
                         let expected_output: Vec<CellId> = $expected_output;
-                        let bcircle = BoundingCircle { center: Vector3::new(x,y,0.0), radius: $r};
-                        let (_object_id, cell_ids) = SpatialSubdivision::create_cell_object(&bcircle, $cell_width, 0);
+                        let bvolume = BoundingVolume::circle(Vector3::new(x,y,0.0), $r);
+                        let (_object_id, cell_ids) = SpatialSubdivision::create_cell_object(&bvolume, ($cell_width, $cell_width), 0);
 
                         assert_eq!(expected_output.len(), cell_ids.len(), "Expected output length {} ({expected_output:?}) but found {} ({cell_ids:?})", expected_output.len(), cell_ids.len());
                         assert_eq!(cell_ids[0].cell_object_type, CellIdType::Home, "Expected the first object to be home cell but is phantom cell");
@@ -627,6 +759,73 @@ mod tests {
                     CellId::new((2,2,0), CellIdType::Phantom, 0),
                     CellId::new((1,2,0), CellIdType::Phantom, 0),]
         }
+
+        #[test]
+        fn given_box_overlapping_right_and_bottom_expect_overlap_with_bottomright() {
+            // A circle of the same extent would not reach the corner of the cell
+            let bvolume = BoundingVolume {
+                center: Vector3::new(0.19, 0.19, 0.0),
+                half_width: 0.0141,
+                half_height: 0.0141,
+                radius: None,
+            };
+            let (_object_id, cell_ids) =
+                SpatialSubdivision::create_cell_object(&bvolume, (0.1, 0.1), 0);
+            assert_eq!(4, cell_ids.len(), "Expected 4 cells but found {cell_ids:?}");
+            assert!(cell_ids.contains(&CellId::new((2, 2, 0), CellIdType::Phantom, 0)));
+        }
+
+        #[test]
+        fn given_non_square_cells_expect_wide_box_to_stay_in_home_cell() {
+            let bvolume = BoundingVolume {
+                center: Vector3::new(5.0, 0.5, 0.0),
+                half_width: 4.0,
+                half_height: 0.25,
+                radius: None,
+            };
+            let (_object_id, cell_ids) =
+                SpatialSubdivision::create_cell_object(&bvolume, (10.0, 1.0), 0);
+            assert_eq!(vec![CellId::new((0, 0, 0), CellIdType::Home, 0)], cell_ids);
+        }
+    }
+
+    mod cell_size {
+        use super::super::BoundingVolume;
+        use super::super::SpatialSubdivision;
+        use cgmath::Vector3;
+        macro_rules! cell_size_tests {
+            ($($name:ident: $half_extents: expr, $percentile: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let half_extents: Vec<(f32, f32)> = $half_extents;
+                        let bvolumes: Vec<BoundingVolume> = half_extents
+                            .into_iter()
+                            .map(|(half_width, half_height)| BoundingVolume {
+                                center: Vector3::new(0.0, 0.0, 0.0),
+                                half_width,
+                                half_height,
+                                radius: None,
+                            })
+                            .collect();
+                        let expected: (f32, f32) = $expected;
+                        let output = SpatialSubdivision::cell_size(&bvolumes, $percentile);
+                        assert_eq!(expected, output, "Expected cell size {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        cell_size_tests! {
+            given_single_body_expect_cell_to_fit_body_with_room_to_spare:
+                vec![(1.0, 2.0)], 1.0, (3.0, 6.0)
+            given_percentile_1_expect_cell_to_fit_largest_body:
+                vec![(1.0, 1.0), (1.0, 1.0), (1.0, 1.0), (100.0, 2.0)], 1.0, (300.0, 6.0)
+            given_percentile_below_1_expect_largest_body_to_be_ignored:
+                vec![(1.0, 1.0), (1.0, 1.0), (1.0, 1.0), (100.0, 2.0)], 0.75, (3.0, 3.0)
+            given_wide_and_tall_bodies_expect_width_and_height_to_be_chosen_separately:
+                vec![(4.0, 1.0), (1.0, 4.0), (1.0, 1.0)], 0.5, (3.0, 3.0)
+        }
     }
 
     #[allow(non_snake_case)]
@@ -713,4 +912,263 @@ mod tests {
 
         }
     }
+
+    mod collision_detection {
+        use cgmath::MetricSpace;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        use super::super::{
+            assert_object_id_in_candidate_list_exists_in_no_other_candidate_list,
+            SpatialSubdivision,
+        };
+        use crate::engine::physics_engine::broadphase::BroadPhase;
+        use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+        use crate::engine::physics_engine::collision::{
+            Aabb, RigidBody, RigidBodyBuilder, RigidBodyType,
+        };
+        use crate::engine::physics_engine::query::SpatialQuery;
+
+        fn contains_pair(passes: &[Vec<CollisionCandidates>; 4], i: usize, j: usize) -> bool {
+            passes
+                .iter()
+                .flatten()
+                .any(|c| c.indices.contains(&i) && c.indices.contains(&j))
+        }
+
+        fn body(id: usize, position: [f32; 3], body_type: RigidBodyType) -> RigidBody {
+            RigidBodyBuilder::default()
+                .id(id)
+                .position(position)
+                .body_type(body_type)
+                .build()
+        }
+
+        /// Circles are tested by their radius, rectangles by their bounding box
+        fn bounds_overlap(a: &RigidBody, b: &RigidBody) -> bool {
+            match (&a.body_type, &b.body_type) {
+                (RigidBodyType::Circle { radius: ra }, RigidBodyType::Circle { radius: rb }) => {
+                    a.position.distance(b.position) < ra + rb
+                }
+                (RigidBodyType::Circle { radius }, _) => {
+                    let aabb = b.aabb();
+                    let closest = [
+                        a.position.x.clamp(aabb.min.x, aabb.max.x),
+                        a.position.y.clamp(aabb.min.y, aabb.max.y),
+                    ];
+                    (a.position.x - closest[0]).hypot(a.position.y - closest[1]) < *radius
+                }
+                (_, RigidBodyType::Circle { .. }) => bounds_overlap(b, a),
+                _ => a.aabb().overlaps(&b.aabb()),
+            }
+        }
+
+        #[test]
+        fn given_circle_below_a_plank_outside_its_bounding_box_expect_no_candidates() {
+            let mut spatial_subdivision = SpatialSubdivision::new();
+            let bodies = [
+                body(
+                    0,
+                    [500.0, 500.0, 0.0],
+                    RigidBodyType::Rectangle {
+                        width: 200.0,
+                        height: 4.0,
+                    },
+                ),
+                body(
+                    1,
+                    [500.0, 510.0, 0.0],
+                    RigidBodyType::Circle { radius: 2.0 },
+                ),
+            ];
+            let passes = spatial_subdivision.collision_detection(bodies.iter());
+            assert!(
+                !contains_pair(&passes, 0, 1),
+                "Expected no candidates but found {passes:?}"
+            );
+        }
+
+        #[test]
+        fn given_non_square_cells_expect_query_grid_in_the_same_cells() {
+            let mut spatial_subdivision = SpatialSubdivision::new();
+            let bodies = [
+                body(
+                    0,
+                    [500.0, 500.0, 0.0],
+                    RigidBodyType::Rectangle {
+                        width: 200.0,
+                        height: 4.0,
+                    },
+                ),
+                body(
+                    1,
+                    [500.0, 510.0, 0.0],
+                    RigidBodyType::Circle { radius: 2.0 },
+                ),
+            ];
+            spatial_subdivision.collision_detection(bodies.iter());
+
+            let statistics = *spatial_subdivision.statistics();
+            let grid = spatial_subdivision.query_grid();
+            assert_eq!([400.0, 498.0, 0.0], statistics.origin);
+            assert_eq!(statistics.origin, grid.origin());
+            assert_eq!(
+                (statistics.cell_width, statistics.cell_height),
+                grid.cell_size()
+            );
+            let mut cells: Vec<Aabb> = grid.occupied_cells().collect();
+            cells.sort_by(|a, b| a.min.y.total_cmp(&b.min.y));
+            assert_eq!(
+                vec![
+                    Aabb::new([400.0, 498.0, 0.0], [700.0, 504.0, 0.0]),
+                    Aabb::new([400.0, 504.0, 0.0], [700.0, 510.0, 0.0]),
+                    Aabb::new([400.0, 510.0, 0.0], [700.0, 516.0, 0.0]),
+                ],
+                cells
+            );
+        }
+
+        #[test]
+        fn given_rotated_plank_expect_circle_inside_its_bounding_box_to_be_candidate() {
+            let mut spatial_subdivision = SpatialSubdivision::new();
+            let mut plank = body(
+                0,
+                [500.0, 500.0, 0.0],
+                RigidBodyType::Rectangle {
+                    width: 200.0,
+                    height: 4.0,
+                },
+            );
+            plank.rotation = 0.3;
+            let bodies = [
+                plank,
+                body(
+                    1,
+                    [590.0, 528.0, 0.0],
+                    RigidBodyType::Circle { radius: 2.0 },
+                ),
+            ];
+            let passes = spatial_subdivision.collision_detection(bodies.iter());
+            assert!(
+                contains_pair(&passes, 0, 1),
+                "Expected candidates but found {passes:?}"
+            );
+        }
+
+        #[test]
+        fn given_percentile_cells_and_mixed_scene_expect_all_overlapping_pairs_to_be_candidates() {
+            let mut rng = StdRng::seed_from_u64(2);
+            let mut bodies: Vec<RigidBody> = (0..400)
+                .map(|i| {
+                    let position = [rng.gen_range(0.0..500.0), rng.gen_range(0.0..500.0), 0.0];
+                    body(
+                        i,
+                        position,
+                        RigidBodyType::Circle {
+                            radius: rng.gen_range(2.0..6.0),
+                        },
+                    )
+                })
+                .collect();
+            for i in 400..420 {
+                let position = [rng.gen_range(0.0..500.0), rng.gen_range(0.0..500.0), 0.0];
+                let mut plank = body(
+                    i,
+                    position,
+                    RigidBodyType::Rectangle {
+                        width: rng.gen_range(40.0..120.0),
+                        height: 4.0,
+                    },
+                );
+                plank.rotation = rng.gen_range(-0.5..0.5);
+                bodies.push(plank);
+            }
+
+            let mut spatial_subdivision = SpatialSubdivision::with_cell_size_percentile(0.9);
+            let passes = spatial_subdivision.collision_detection(bodies.iter());
+            for pass in passes.iter() {
+                assert!(assert_object_id_in_candidate_list_exists_in_no_other_candidate_list(pass));
+            }
+            assert_eq!(20, spatial_subdivision.statistics().num_oversized);
+            for i in 0..bodies.len() {
+                for j in (i + 1)..bodies.len() {
+                    if bounds_overlap(&bodies[i], &bodies[j]) {
+                        assert!(contains_pair(&passes, i, j), "Missing pair ({i}, {j})");
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn given_same_bodies_twice_expect_identical_candidates() {
+            let mut rng = StdRng::seed_from_u64(4);
+            // Dense enough that some oversized planks are merged in the last pass
+            let bodies: Vec<RigidBody> = (0..300)
+                .map(|i| {
+                    let position = [rng.gen_range(0.0..200.0), rng.gen_range(0.0..200.0), 0.0];
+                    let body_type = match i % 10 {
+                        0 => RigidBodyType::Rectangle {
+                            width: rng.gen_range(40.0..80.0),
+                            height: 4.0,
+                        },
+                        _ => RigidBodyType::Circle {
+                            radius: rng.gen_range(2.0..6.0),
+                        },
+                    };
+                    body(i, position, body_type)
+                })
+                .collect();
+            let indices = |passes: [Vec<CollisionCandidates>; 4]| {
+                passes.map(|pass| pass.into_iter().map(|c| c.indices).collect::<Vec<_>>())
+            };
+
+            let first = indices(
+                SpatialSubdivision::with_cell_size_percentile(0.9)
+                    .collision_detection(bodies.iter()),
+            );
+            let second = indices(
+                SpatialSubdivision::with_cell_size_percentile(0.9)
+                    .collision_detection(bodies.iter()),
+            );
+
+            assert_eq!(first, second);
+        }
+
+        #[test]
+        fn given_overlapping_circles_in_every_cell_type_expect_candidates() {
+            let mut spatial_subdivision = SpatialSubdivision::new();
+            // Cells are 30 wide, the first circle moves the scene corner to the origin.
+            // Each pair lies inside a single cell such that only its home cell finds it.
+            let home_cells = [(0., 0.), (1., 0.), (0., 1.), (1., 1.)];
+            let mut positions = vec![[10.0, 10.0, 0.0]];
+            for (x, y) in home_cells {
+                let corner = [(10.0 + x) * 30.0, (10.0 + y) * 30.0];
+                positions.push([corner[0] + 11.0, corner[1] + 15.0, 0.0]);
+                positions.push([corner[0] + 19.0, corner[1] + 15.0, 0.0]);
+            }
+            let bodies: Vec<_> = positions
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    RigidBodyBuilder::default()
+                        .id(i)
+                        .position(*p)
+                        .body_type(RigidBodyType::Circle { radius: 10.0 })
+                        .build()
+                })
+                .collect();
+
+            let passes = spatial_subdivision.collision_detection(bodies.iter());
+            for i in (1..bodies.len()).step_by(2) {
+                assert!(
+                    passes
+                        .iter()
+                        .flatten()
+                        .any(|c| c.indices.contains(&i) && c.indices.contains(&(i + 1))),
+                    "Expected ({i}, {}) to be candidates, found {passes:?}",
+                    i + 1
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,399 @@
+use crate::engine::physics_engine::collision::{
+    collision_candidates::CollisionCandidates,
+    {Aabb, RigidBody, RigidBodyType},
+};
+use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+use super::{update_average_size_query_grid, BroadPhase};
+
+/// Above this many new bodies in a call the endpoints are sorted from scratch
+/// instead of inserting them one by one
+const MAX_INCREMENTAL_INSERTIONS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepAxis {
+    X,
+    Y,
+}
+
+#[derive(Clone, Copy)]
+struct Endpoint {
+    value: f32,
+    body_idx: usize,
+    is_min: bool,
+}
+
+impl Endpoint {
+    /// Start points are ordered before end points at the same value such that
+    /// touching boxes are reported, the same as `Aabb::overlaps`
+    fn is_before(&self, other: &Endpoint) -> bool {
+        self.value < other.value || (self.value == other.value && self.is_min && !other.is_min)
+    }
+}
+
+/// Sort and sweep broadphase. The start and end of every body along the sweep axis
+/// are kept in a sorted list between calls. Bodies move little from one tick to the
+/// next, so re-sorting the list with insertion sort is close to linear. The list is
+/// then swept while keeping track of the bodies whose interval is open, and every
+/// open body is tested against the new body on the other axis.
+///
+/// Works best when the bodies are spread out along the sweep axis, e.g. `SweepAxis::X`
+/// for side scrollers. Each pair of bodies with overlapping bounding boxes is emitted
+/// as its own `CollisionCandidates` holding the two indices, lowest index first.
+///
+/// Bodies are identified by their position in the iterator, bodies of unknown type
+/// are ignored.
+pub struct SweepAndPrune {
+    axis: SweepAxis,
+    endpoints: Vec<Endpoint>,
+    aabbs: Vec<Option<Aabb>>,
+    open_bodies: Vec<usize>,
+    is_sorted: bool,
+    query_grid: QueryGrid,
+}
+
+impl SweepAndPrune {
+    pub fn new(axis: SweepAxis) -> Self {
+        Self {
+            axis,
+            endpoints: Vec::new(),
+            aabbs: Vec::new(),
+            open_bodies: Vec::new(),
+            is_sorted: false,
+            query_grid: QueryGrid::new([0.0, 0.0, 0.0], 1.0),
+        }
+    }
+
+    pub fn axis(&self) -> SweepAxis {
+        self.axis
+    }
+
+    /// Changes the sweep axis, the endpoints are sorted from scratch on the next call
+    pub fn set_axis(&mut self, axis: SweepAxis) {
+        self.axis = axis;
+        self.is_sorted = false;
+    }
+
+    /// Same as `BroadPhase::collision_detection` but writes the candidates into
+    /// `candidates`, reusing the lists already allocated there.
+    pub fn collision_detection_into<'a, I>(
+        &mut self,
+        bodies: I,
+        candidates: &mut Vec<CollisionCandidates>,
+    ) where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.update_endpoints(bodies);
+        let aabbs = self.aabbs.iter().enumerate();
+        update_average_size_query_grid(
+            &mut self.query_grid,
+            aabbs.filter_map(|(i, aabb)| Some((i, (*aabb)?))),
+        );
+
+        let mut num_candidates = 0;
+        self.open_bodies.clear();
+        for endpoint in self.endpoints.iter() {
+            let aabb = match &self.aabbs[endpoint.body_idx] {
+                Some(aabb) => aabb,
+                None => continue,
+            };
+            if !endpoint.is_min {
+                if let Some(pos) = self
+                    .open_bodies
+                    .iter()
+                    .position(|b| *b == endpoint.body_idx)
+                {
+                    self.open_bodies.swap_remove(pos);
+                }
+                continue;
+            }
+
+            for open_body in self.open_bodies.iter() {
+                let overlaps = match &self.aabbs[*open_body] {
+                    Some(open_aabb) => open_aabb.overlaps(aabb),
+                    None => false,
+                };
+                if !overlaps {
+                    continue;
+                }
+                if num_candidates == candidates.len() {
+                    candidates.push(CollisionCandidates::new(Vec::with_capacity(2)));
+                }
+                let indices = &mut candidates[num_candidates].indices;
+                indices.clear();
+                indices.push(endpoint.body_idx.min(*open_body));
+                indices.push(endpoint.body_idx.max(*open_body));
+                num_candidates += 1;
+            }
+            self.open_bodies.push(endpoint.body_idx);
+        }
+        candidates.truncate(num_candidates);
+    }
+
+    /// Updates the bounding boxes and endpoints and sorts the endpoints along the
+    /// sweep axis
+    fn update_endpoints<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut num_bodies = 0;
+        let mut num_added = 0;
+        for (i, body) in bodies.enumerate() {
+            let aabb = match body.body_type {
+                RigidBodyType::Unknown => None,
+                _ => Some(body.aabb()),
+            };
+            if i == self.aabbs.len() {
+                self.aabbs.push(aabb);
+                for is_min in [true, false] {
+                    self.endpoints.push(Endpoint {
+                        value: 0.0,
+                        body_idx: i,
+                        is_min,
+                    });
+                }
+                num_added += 1;
+            } else {
+                self.aabbs[i] = aabb;
+            }
+            num_bodies += 1;
+        }
+        if num_bodies < self.aabbs.len() {
+            self.aabbs.truncate(num_bodies);
+            self.endpoints.retain(|e| e.body_idx < num_bodies);
+        }
+
+        let axis = self.axis;
+        for endpoint in self.endpoints.iter_mut() {
+            endpoint.value = match (&self.aabbs[endpoint.body_idx], endpoint.is_min) {
+                (Some(aabb), true) => Self::axis_value(axis, aabb.min.x, aabb.min.y),
+                (Some(aabb), false) => Self::axis_value(axis, aabb.max.x, aabb.max.y),
+                // Unknown bodies are moved out of the way to the end of the list
+                (None, _) => f32::MAX,
+            };
+        }
+
+        if !self.is_sorted || num_added > MAX_INCREMENTAL_INSERTIONS {
+            self.endpoints
+                .sort_by(|a, b| a.value.total_cmp(&b.value).then(b.is_min.cmp(&a.is_min)));
+            self.is_sorted = true;
+        } else {
+            self.insertion_sort();
+        }
+    }
+
+    /// Sorts the endpoints in place, linear when the endpoints are almost sorted
+    fn insertion_sort(&mut self) {
+        for i in 1..self.endpoints.len() {
+            let endpoint = self.endpoints[i];
+            let mut j = i;
+            while j > 0 && endpoint.is_before(&self.endpoints[j - 1]) {
+                self.endpoints[j] = self.endpoints[j - 1];
+                j -= 1;
+            }
+            self.endpoints[j] = endpoint;
+        }
+    }
+
+    fn axis_value(axis: SweepAxis, x: f32, y: f32) -> f32 {
+        match axis {
+            SweepAxis::X => x,
+            SweepAxis::Y => y,
+        }
+    }
+}
+
+impl std::default::Default for SweepAndPrune {
+    fn default() -> Self {
+        Self::new(SweepAxis::X)
+    }
+}
+
+impl BroadPhase<Vec<CollisionCandidates>> for SweepAndPrune {
+    fn collision_detection<'a, I>(&mut self, bodies: I) -> Vec<CollisionCandidates>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let mut candidates = vec![];
+        self.collision_detection_into(bodies, &mut candidates);
+        candidates
+    }
+}
+
+impl SpatialQuery for SweepAndPrune {
+    fn query_grid(&self) -> &QueryGrid {
+        &self.query_grid
+    }
+
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.query_grid.refill(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::engine::physics_engine::broadphase::{BlockMap, BroadPhase};
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::util::zero;
+
+    use super::{SweepAndPrune, SweepAxis};
+
+    fn circle(id: usize, position: [f32; 3], radius: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius })
+            .build()
+    }
+
+    /// Pairs of bodies sharing a candidate list whose bounding boxes overlap
+    fn overlapping_pairs(
+        candidates: &[CollisionCandidates],
+        bodies: &[RigidBody],
+    ) -> BTreeSet<(usize, usize)> {
+        let mut pairs = BTreeSet::new();
+        for c in candidates {
+            for (n, i) in c.indices.iter().enumerate() {
+                for j in c.indices[n + 1..].iter() {
+                    if bodies[*i].aabb().overlaps(&bodies[*j].aabb()) {
+                        pairs.insert((*i.min(j), *i.max(j)));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn rect_circle_are_possible_collision_candidates() {
+        let mut sweep_and_prune = SweepAndPrune::default();
+        let circ = circle(0, zero(), 50.0);
+        let rect = RigidBodyBuilder::default()
+            .id(1)
+            .position(zero())
+            .body_type(RigidBodyType::Rectangle {
+                width: 50.0,
+                height: 50.0,
+            })
+            .build();
+
+        let candidates = sweep_and_prune.collision_detection([circ, rect].iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(vec![0, 1], candidates[0].indices);
+    }
+
+    #[test]
+    fn given_bodies_overlapping_only_on_sweep_axis_expect_no_candidates() {
+        for axis in [SweepAxis::X, SweepAxis::Y] {
+            let mut sweep_and_prune = SweepAndPrune::new(axis);
+            let bodies = [
+                circle(0, [0., 0., 0.], 10.0),
+                circle(1, [0., 100., 0.], 10.0),
+                circle(2, [100., 0., 0.], 10.0),
+            ];
+            let candidates = sweep_and_prune.collision_detection(bodies.iter());
+            assert!(
+                candidates.is_empty(),
+                "Expected no candidates along {axis:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn given_body_moved_past_other_body_expect_candidates_to_follow() {
+        let mut sweep_and_prune = SweepAndPrune::default();
+        let mut bodies = [
+            circle(0, [0., 0., 0.], 10.0),
+            circle(1, [15., 0., 0.], 10.0),
+            circle(2, [100., 0., 0.], 10.0),
+        ];
+        let candidates = sweep_and_prune.collision_detection(bodies.iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(vec![0, 1], candidates[0].indices);
+
+        bodies[0].position.x = 110.0;
+        let candidates = sweep_and_prune.collision_detection(bodies.iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(vec![0, 2], candidates[0].indices);
+    }
+
+    #[test]
+    fn given_body_removed_expect_no_stale_indices() {
+        let mut sweep_and_prune = SweepAndPrune::default();
+        let bodies = [
+            circle(0, [0., 0., 0.], 10.0),
+            circle(1, [15., 0., 0.], 10.0),
+            circle(2, [5., 5., 0.], 10.0),
+        ];
+        let _ = sweep_and_prune.collision_detection(bodies.iter());
+        let candidates = sweep_and_prune.collision_detection(bodies[..2].iter());
+        assert_eq!(1, candidates.len());
+        assert_eq!(vec![0, 1], candidates[0].indices);
+    }
+
+    #[test]
+    fn given_unknown_body_expect_it_to_be_ignored() {
+        let mut sweep_and_prune = SweepAndPrune::default();
+        let mut bodies = [circle(0, zero(), 10.0), circle(1, zero(), 10.0)];
+        bodies[1].body_type = RigidBodyType::Unknown;
+        let candidates = sweep_and_prune.collision_detection(bodies.iter());
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn given_random_moving_scenes_expect_same_pairs_as_blockmap() {
+        let (width, radius) = (1000.0, 10.0);
+        for (seed, axis) in [(1, SweepAxis::X), (2, SweepAxis::Y), (3, SweepAxis::X)] {
+            let mut rng = StdRng::seed_from_u64(seed);
+            // BlockMap ignores its outer most cells, keep the bodies away from them
+            let range = (4.0 * radius)..(width - 4.0 * radius);
+            let mut bodies: Vec<RigidBody> = (0..400)
+                .map(|i| {
+                    let position = [
+                        rng.gen_range(range.clone()),
+                        rng.gen_range(range.clone()),
+                        0.0,
+                    ];
+                    circle(i, position, radius)
+                })
+                .collect();
+
+            let mut blockmap = BlockMap::new(width);
+            let mut sweep_and_prune = SweepAndPrune::new(axis);
+            for _ in 0..10 {
+                let expected =
+                    overlapping_pairs(&blockmap.collision_detection(bodies.iter()), &bodies);
+                let candidates = sweep_and_prune.collision_detection(bodies.iter());
+                let output: BTreeSet<(usize, usize)> = candidates
+                    .iter()
+                    .map(|c| (c.indices[0], c.indices[1]))
+                    .collect();
+                assert_eq!(
+                    candidates.len(),
+                    output.len(),
+                    "Expected every pair only once"
+                );
+                assert_eq!(
+                    expected, output,
+                    "Expected the same pairs as BlockMap along {axis:?}"
+                );
+
+                bodies.iter_mut().for_each(|b| {
+                    b.position.x = (b.position.x + rng.gen_range(-10.0..10.0))
+                        .clamp(4.0 * radius, width - 4.0 * radius);
+                    b.position.y = (b.position.y + rng.gen_range(-10.0..10.0))
+                        .clamp(4.0 * radius, width - 4.0 * radius);
+                });
+            }
+        }
+    }
+}
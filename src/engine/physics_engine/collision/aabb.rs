@@ -0,0 +1,194 @@
+use cgmath::Vector3;
+
+/// Axis aligned bounding box in world coordinates. The z component is kept for
+/// consistency with `RigidBody::position` and is always zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Self {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    pub fn from_center(center: [f32; 3], half_width: f32, half_height: f32) -> Self {
+        Self::new(
+            [center[0] - half_width, center[1] - half_height, 0.0],
+            [center[0] + half_width, center[1] + half_height, 0.0],
+        )
+    }
+
+    /// Returns the smallest box containing all the points
+    pub fn from_points(points: &[[f32; 3]]) -> Self {
+        let mut min = [f32::MAX, f32::MAX, 0.0];
+        let mut max = [f32::MIN, f32::MIN, 0.0];
+        for p in points {
+            min[0] = min[0].min(p[0]);
+            min[1] = min[1].min(p[1]);
+            max[0] = max[0].max(p[0]);
+            max[1] = max[1].max(p[1]);
+        }
+        Self::new(min, max)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Returns true if the boxes overlap or touch
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    pub fn contains_point(&self, point: (f32, f32)) -> bool {
+        point.0 >= self.min.x
+            && point.0 <= self.max.x
+            && point.1 >= self.min.y
+            && point.1 <= self.max.y
+    }
+
+    /// Returns true if `other` lies completely inside this box
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+    }
+
+    pub fn perimeter(&self) -> f32 {
+        2.0 * (self.width() + self.height())
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            [
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                0.0,
+            ],
+            [
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                0.0,
+            ],
+        )
+    }
+
+    /// Returns the part of the boxes that overlaps, `None` if they do not overlap
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        Some(Aabb::new(
+            [
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                0.0,
+            ],
+            [
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                0.0,
+            ],
+        ))
+    }
+
+    /// Returns a copy of the box grown by `margin` on every side
+    pub fn expand(&self, margin: f32) -> Aabb {
+        Aabb::new(
+            [self.min.x - margin, self.min.y - margin, 0.0],
+            [self.max.x + margin, self.max.y + margin, 0.0],
+        )
+    }
+}
+
+impl std::fmt::Display for Aabb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Aabb{{ min: ({},{}), max: ({},{}) }}",
+            self.min.x, self.min.y, self.max.x, self.max.y
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod overlaps {
+        use super::super::Aabb;
+        macro_rules! overlaps_tests {
+            ($($name:ident: $a: expr, $b: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let a: Aabb = $a;
+                        let b: Aabb = $b;
+                        let expected: bool = $expected;
+                        assert_eq!(expected, a.overlaps(&b), "Expected {a} overlaps {b} to be {expected}");
+                        assert_eq!(expected, b.overlaps(&a), "Expected {b} overlaps {a} to be {expected}");
+                    }
+                )*
+            }
+        }
+
+        overlaps_tests! {
+            given_boxes_are_disjoint_on_x_axis_expect_no_overlap:
+                Aabb::new([0.,0.,0.],[1.,1.,0.]), Aabb::new([2.,0.,0.],[3.,1.,0.]), false
+            given_boxes_are_disjoint_on_y_axis_expect_no_overlap:
+                Aabb::new([0.,0.,0.],[1.,1.,0.]), Aabb::new([0.,-3.,0.],[1.,-2.,0.]), false
+            given_boxes_share_an_edge_expect_overlap:
+                Aabb::new([0.,0.,0.],[1.,1.,0.]), Aabb::new([1.,0.,0.],[2.,1.,0.]), true
+            given_one_box_contains_the_other_expect_overlap:
+                Aabb::new([-5.,-5.,0.],[5.,5.,0.]), Aabb::new([0.,0.,0.],[1.,1.,0.]), true
+        }
+    }
+
+    mod intersection {
+        use super::super::Aabb;
+        macro_rules! intersection_tests {
+            ($($name:ident: $a: expr, $b: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let a: Aabb = $a;
+                        let b: Aabb = $b;
+                        let expected: Option<Aabb> = $expected;
+                        assert_eq!(expected, a.intersection(&b));
+                        assert_eq!(expected, b.intersection(&a));
+                    }
+                )*
+            }
+        }
+
+        intersection_tests! {
+            given_partially_overlapping_boxes_expect_overlapping_part:
+                Aabb::new([0.0, 0.0, 0.0], [10.0, 10.0, 0.0]),
+                Aabb::new([5.0, -5.0, 0.0], [15.0, 5.0, 0.0]),
+                Some(Aabb::new([5.0, 0.0, 0.0], [10.0, 5.0, 0.0]))
+            given_box_inside_other_expect_inner_box:
+                Aabb::new([0.0, 0.0, 0.0], [10.0, 10.0, 0.0]),
+                Aabb::new([2.0, 2.0, 0.0], [4.0, 4.0, 0.0]),
+                Some(Aabb::new([2.0, 2.0, 0.0], [4.0, 4.0, 0.0]))
+            given_separated_boxes_expect_none:
+                Aabb::new([0.0, 0.0, 0.0], [10.0, 10.0, 0.0]),
+                Aabb::new([11.0, 0.0, 0.0], [15.0, 10.0, 0.0]),
+                None
+        }
+    }
+}
@@ -0,0 +1,68 @@
+/// Decides which bodies are allowed to interact with each other. A body is a
+/// member of the layers set in `layer` and only interacts with bodies that are
+/// members of a layer set in its `mask`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionFilter {
+    pub layer: u32,
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    pub const ALL_LAYERS: u32 = u32::MAX;
+
+    pub fn new(layer: u32, mask: u32) -> Self {
+        Self { layer, mask }
+    }
+
+    /// Returns true if the mask of `self` includes a layer of `other`. Used by
+    /// queries where only the filter of the query itself matters.
+    pub fn accepts(&self, other: &CollisionFilter) -> bool {
+        self.mask & other.layer != 0
+    }
+
+    /// Returns true if both filters accept each other
+    pub fn can_collide(&self, other: &CollisionFilter) -> bool {
+        self.accepts(other) && other.accepts(self)
+    }
+}
+
+impl std::default::Default for CollisionFilter {
+    fn default() -> Self {
+        Self {
+            layer: 0b1,
+            mask: Self::ALL_LAYERS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod can_collide {
+        use super::super::CollisionFilter;
+        macro_rules! can_collide_tests {
+            ($($name:ident: $a: expr, $b: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let a: CollisionFilter = $a;
+                        let b: CollisionFilter = $b;
+                        let expected: bool = $expected;
+                        assert_eq!(expected, a.can_collide(&b));
+                        assert_eq!(expected, b.can_collide(&a));
+                    }
+                )*
+            }
+        }
+
+        can_collide_tests! {
+            given_default_filters_expect_collision:
+                CollisionFilter::default(), CollisionFilter::default(), true
+            given_disjoint_layers_and_masks_expect_no_collision:
+                CollisionFilter::new(0b01, 0b01), CollisionFilter::new(0b10, 0b10), false
+            given_one_mask_excludes_the_other_expect_no_collision:
+                CollisionFilter::new(0b01, 0b11), CollisionFilter::new(0b10, 0b10), false
+            given_masks_include_each_other_expect_collision:
+                CollisionFilter::new(0b01, 0b10), CollisionFilter::new(0b10, 0b01), true
+        }
+    }
+}
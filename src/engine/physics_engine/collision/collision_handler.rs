@@ -1,22 +1,79 @@
 use super::rigid_body::RigidBody;
 use super::CollisionInformation;
 
+/// Detects and resolves collisions between pairs of bodies. The contact tests only
+/// read the bodies, such that they can run in parallel, while the resolve steps
+/// update the bodies from a contact found by the tests.
+///
+/// The normal of a contact points from `body_i` to `body_j`, the resolve steps expect
+/// the normal in the same direction.
 pub trait CollisionHandler {
+    fn circle_circle_contact(
+        &self,
+        body_i: &RigidBody,
+        body_j: &RigidBody,
+    ) -> Option<CollisionInformation>;
+
+    fn circle_rect_contact(
+        &self,
+        body_i: &RigidBody,
+        body_j: &RigidBody,
+    ) -> Option<CollisionInformation>;
+
+    fn rect_rect_contact(
+        &self,
+        body_i: &RigidBody,
+        body_j: &RigidBody,
+    ) -> Option<CollisionInformation>;
+
+    fn resolve_circle_circle_collision(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    );
+
+    fn resolve_circle_rect_collision(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    );
+
+    fn resolve_rect_rect_collision(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    );
+
     fn handle_circle_circle_collision(
         &self,
         body_i: &mut RigidBody,
         body_j: &mut RigidBody,
-    ) -> Option<CollisionInformation>;
+    ) -> Option<CollisionInformation> {
+        let info = self.circle_circle_contact(body_i, body_j)?;
+        self.resolve_circle_circle_collision(body_i, body_j, &info);
+        Some(info)
+    }
 
     fn handle_circle_rect_collision(
         &self,
         body_i: &mut RigidBody,
         body_j: &mut RigidBody,
-    ) -> Option<CollisionInformation>;
+    ) -> Option<CollisionInformation> {
+        let info = self.circle_rect_contact(body_i, body_j)?;
+        self.resolve_circle_rect_collision(body_i, body_j, &info);
+        Some(info)
+    }
 
     fn handle_rect_rect_collision(
         &self,
         body_i: &mut RigidBody,
         body_j: &mut RigidBody,
-    ) -> Option<CollisionInformation>;
+    ) -> Option<CollisionInformation> {
+        let info = self.rect_rect_contact(body_i, body_j)?;
+        self.resolve_rect_rect_collision(body_i, body_j, &info);
+        Some(info)
+    }
 }
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::collision_handler::CollisionHandler;
+use super::rigid_body::{RigidBody, RigidBodyType};
+use super::CollisionInformation;
+
+/// The kind of shape of a body, selects the handler of a pair of bodies
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ShapeKind {
+    Circle,
+    Rectangle,
+    Custom(u32),
+}
+
+impl ShapeKind {
+    /// Bodies of unknown type have no kind and never collide
+    pub fn of(body_type: &RigidBodyType) -> Option<Self> {
+        match body_type {
+            RigidBodyType::Circle { .. } => Some(ShapeKind::Circle),
+            RigidBodyType::Rectangle { .. } => Some(ShapeKind::Rectangle),
+            RigidBodyType::Custom { kind, .. } => Some(ShapeKind::Custom(*kind)),
+            RigidBodyType::Unknown => None,
+        }
+    }
+}
+
+type ContactFn = dyn Fn(&RigidBody, &RigidBody) -> Option<CollisionInformation> + Send + Sync;
+type ResolveFn = dyn Fn(&mut RigidBody, &mut RigidBody, &CollisionInformation) + Send + Sync;
+
+struct PairHandler {
+    contact: Box<ContactFn>,
+    resolve: Box<ResolveFn>,
+}
+
+/// Finds the handler of a pair of bodies from the kinds of their shapes. A handler
+/// registered for `(a, b)` also handles `(b, a)`, the bodies are then swapped when
+/// calling the handler and the normal of the contact is flipped. Pairs without a
+/// handler never collide.
+///
+/// The normal of a contact always points from the first body to the second, the
+/// handlers of `CollisionHandler` and registered handlers are expected to follow it.
+#[derive(Default)]
+pub struct CollisionDispatcher {
+    handlers: HashMap<(ShapeKind, ShapeKind), PairHandler>,
+}
+
+impl CollisionDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the circle and rectangle pairs of `handler`
+    pub fn from_handler<H>(handler: H) -> Self
+    where
+        H: CollisionHandler + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let mut dispatcher = Self::new();
+        let (contact, resolve) = (handler.clone(), handler.clone());
+        dispatcher.register(
+            ShapeKind::Circle,
+            ShapeKind::Circle,
+            move |i, j| contact.circle_circle_contact(i, j),
+            move |i, j, info| resolve.resolve_circle_circle_collision(i, j, info),
+        );
+        let (contact, resolve) = (handler.clone(), handler.clone());
+        dispatcher.register(
+            ShapeKind::Circle,
+            ShapeKind::Rectangle,
+            move |i, j| contact.circle_rect_contact(i, j),
+            move |i, j, info| resolve.resolve_circle_rect_collision(i, j, info),
+        );
+        let (contact, resolve) = (handler.clone(), handler);
+        dispatcher.register(
+            ShapeKind::Rectangle,
+            ShapeKind::Rectangle,
+            move |i, j| contact.rect_rect_contact(i, j),
+            move |i, j, info| resolve.resolve_rect_rect_collision(i, j, info),
+        );
+        dispatcher
+    }
+
+    /// Registers the handler of bodies of kind `kind_i` and `kind_j`, replacing any
+    /// earlier handler of the pair in this order. The functions are called with the
+    /// body of kind `kind_i` first.
+    pub fn register<C, R>(&mut self, kind_i: ShapeKind, kind_j: ShapeKind, contact: C, resolve: R)
+    where
+        C: Fn(&RigidBody, &RigidBody) -> Option<CollisionInformation> + Send + Sync + 'static,
+        R: Fn(&mut RigidBody, &mut RigidBody, &CollisionInformation) + Send + Sync + 'static,
+    {
+        let handler = PairHandler {
+            contact: Box::new(contact),
+            resolve: Box::new(resolve),
+        };
+        self.handlers.insert((kind_i, kind_j), handler);
+    }
+
+    /// Returns true if there is a handler of the pair, in either order
+    pub fn contains(&self, kind_i: ShapeKind, kind_j: ShapeKind) -> bool {
+        self.handlers.contains_key(&(kind_i, kind_j))
+            || self.handlers.contains_key(&(kind_j, kind_i))
+    }
+
+    /// Returns the handler of the pair and whether the bodies have to be swapped
+    fn find(&self, body_i: &RigidBody, body_j: &RigidBody) -> Option<(&PairHandler, bool)> {
+        let kind_i = ShapeKind::of(&body_i.body_type)?;
+        let kind_j = ShapeKind::of(&body_j.body_type)?;
+        match self.handlers.get(&(kind_i, kind_j)) {
+            Some(handler) => Some((handler, false)),
+            None => self
+                .handlers
+                .get(&(kind_j, kind_i))
+                .map(|handler| (handler, true)),
+        }
+    }
+
+    pub fn contact(&self, body_i: &RigidBody, body_j: &RigidBody) -> Option<CollisionInformation> {
+        match self.find(body_i, body_j)? {
+            (handler, false) => (handler.contact)(body_i, body_j),
+            (handler, true) => (handler.contact)(body_j, body_i).map(|info| info.flipped()),
+        }
+    }
+
+    /// Resolves a contact found by `contact` for the bodies in the same order
+    pub fn resolve(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    ) {
+        match self.find(body_i, body_j) {
+            Some((handler, false)) => (handler.resolve)(body_i, body_j, info),
+            Some((handler, true)) => (handler.resolve)(body_j, body_i, &info.flipped()),
+            None => (),
+        }
+    }
+
+    pub fn handle(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+    ) -> Option<CollisionInformation> {
+        let info = self.contact(body_i, body_j)?;
+        self.resolve(body_i, body_j, &info);
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::{CollisionDispatcher, ShapeKind};
+    use crate::engine::physics_engine::collision::{
+        CollisionHandler, CollisionInformation, ContactManifold, RigidBody, RigidBodyBuilder,
+        RigidBodyType, SimpleCollisionSolver,
+    };
+    use crate::engine::physics_engine::util::equations;
+
+    const POINT: ShapeKind = ShapeKind::Custom(7);
+
+    fn body(position: [f32; 3], body_type: RigidBodyType) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position(position)
+            .body_type(body_type)
+            .build()
+    }
+
+    fn point(position: [f32; 3]) -> RigidBody {
+        body(
+            position,
+            RigidBodyType::Custom {
+                kind: 7,
+                radius: 1.0,
+            },
+        )
+    }
+
+    fn circle(position: [f32; 3]) -> RigidBody {
+        body(position, RigidBodyType::Circle { radius: 5.0 })
+    }
+
+    /// Points collide with circles they are inside of, the normal points from the
+    /// point to the circle. Resolving pushes the circle away from the point.
+    fn point_circle_dispatcher() -> CollisionDispatcher {
+        let mut dispatcher = CollisionDispatcher::new();
+        dispatcher.register(
+            POINT,
+            ShapeKind::Circle,
+            |point, circle| {
+                let mut normal =
+                    equations::subtract(&circle.position.into(), &point.position.into());
+                let depth = 5.0 - equations::magnitude(&normal);
+                if depth <= 0.0 {
+                    return None;
+                }
+                equations::normalize(&mut normal);
+                Some(CollisionInformation::new(
+                    normal,
+                    ContactManifold::single(point.position.into(), depth),
+                ))
+            },
+            |_point, circle, info| {
+                circle.velocity += Vector3::from(info.normal);
+            },
+        );
+        dispatcher
+    }
+
+    #[test]
+    fn given_registered_custom_pair_expect_contact_from_first_to_second_body() {
+        let dispatcher = point_circle_dispatcher();
+        let (point, circle) = (point([0.0, 0.0, 0.0]), circle([3.0, 0.0, 0.0]));
+
+        let info = dispatcher.contact(&point, &circle).unwrap();
+
+        assert_eq!([1.0, 0.0, 0.0], info.normal);
+        assert_eq!(2.0, info.penetration_depth);
+    }
+
+    #[test]
+    fn given_registered_custom_pair_in_reverse_order_expect_flipped_normal() {
+        let dispatcher = point_circle_dispatcher();
+        let (point, circle) = (point([0.0, 0.0, 0.0]), circle([3.0, 0.0, 0.0]));
+
+        let info = dispatcher.contact(&circle, &point).unwrap();
+
+        assert_eq!([-1.0, 0.0, 0.0], info.normal);
+        assert_eq!(2.0, info.penetration_depth);
+    }
+
+    #[test]
+    fn given_registered_custom_pair_in_reverse_order_expect_handler_to_resolve_swapped_bodies() {
+        let dispatcher = point_circle_dispatcher();
+        let (mut point, mut circle) = (point([0.0, 0.0, 0.0]), circle([3.0, 0.0, 0.0]));
+
+        dispatcher.handle(&mut circle, &mut point);
+
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0), circle.velocity);
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0), point.velocity);
+    }
+
+    mod unhandled {
+        use super::super::CollisionDispatcher;
+        use super::{circle, point, point_circle_dispatcher};
+        use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
+        macro_rules! unhandled_tests {
+            ($($name:ident: $dispatcher: expr, $body_i: expr, $body_j: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let dispatcher: CollisionDispatcher = $dispatcher;
+                        let (mut body_i, mut body_j): (RigidBody, RigidBody) = ($body_i, $body_j);
+
+                        let info = dispatcher.handle(&mut body_i, &mut body_j);
+
+                        assert!(info.is_none(), "Expected no contact but found {info:?}");
+                    }
+                )*
+            }
+        }
+
+        unhandled_tests! {
+            given_no_handler_of_pair_expect_no_contact:
+                point_circle_dispatcher(), point([0.0, 0.0, 0.0]), point([0.0, 0.0, 0.0])
+            given_empty_dispatcher_expect_no_contact:
+                CollisionDispatcher::new(), circle([0.0, 0.0, 0.0]), circle([0.0, 0.0, 0.0])
+            given_body_of_unknown_type_expect_no_contact:
+                point_circle_dispatcher(),
+                point([0.0, 0.0, 0.0]),
+                super::body([0.0, 0.0, 0.0], RigidBodyType::Unknown)
+        }
+    }
+
+    fn square(position: [f32; 3]) -> RigidBody {
+        body(
+            position,
+            RigidBodyType::Rectangle {
+                width: 10.0,
+                height: 10.0,
+            },
+        )
+    }
+
+    mod normal_direction {
+        use super::super::CollisionDispatcher;
+        use super::{circle, square};
+        use crate::engine::physics_engine::collision::{RigidBody, SimpleCollisionSolver};
+        macro_rules! normal_direction_tests {
+            ($($name:ident: $body_i: expr, $body_j: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let dispatcher = CollisionDispatcher::from_handler(SimpleCollisionSolver::new());
+                        let (mut body_i, mut body_j): (RigidBody, RigidBody) = ($body_i, $body_j);
+
+                        let info = dispatcher.handle(&mut body_i, &mut body_j).unwrap();
+
+                        assert!(
+                            (info.normal[0] - 1.0).abs() < 1e-4 && info.normal[1].abs() < 1e-4,
+                            "Expected normal from body i to body j but found {:?}",
+                            info.normal
+                        );
+                        assert!(body_i.position.x < 0.0, "Expected body i pushed back but found {:?}", body_i.position);
+                        assert!(body_j.position.x > 9.0, "Expected body j pushed away but found {:?}", body_j.position);
+                    }
+                )*
+            }
+        }
+
+        normal_direction_tests! {
+            given_circle_and_circle_expect_normal_from_first_to_second_body:
+                circle([0.0, 0.0, 0.0]), circle([9.0, 0.0, 0.0])
+            given_circle_and_rectangle_expect_normal_from_first_to_second_body:
+                circle([0.0, 0.0, 0.0]), square([9.0, 0.0, 0.0])
+            given_rectangle_and_circle_expect_normal_from_first_to_second_body:
+                square([0.0, 0.0, 0.0]), circle([9.0, 0.0, 0.0])
+            given_rectangle_and_rectangle_expect_normal_from_first_to_second_body:
+                square([0.0, 0.0, 0.0]), square([9.0, 0.0, 0.0])
+        }
+    }
+
+    #[test]
+    fn given_rectangle_and_circle_expect_flipped_normal_of_circle_rect_contact() {
+        let solver = SimpleCollisionSolver::new();
+        let dispatcher = CollisionDispatcher::from_handler(SimpleCollisionSolver::new());
+        let circle = circle([0.0, 0.0, 0.0]);
+        let rect = square([7.0, 1.0, 0.0]);
+
+        let expected = solver.circle_rect_contact(&circle, &rect).unwrap();
+        let forward = dispatcher.contact(&circle, &rect).unwrap();
+        let reverse = dispatcher.contact(&rect, &circle).unwrap();
+
+        assert_eq!(expected.normal, forward.normal);
+        assert_eq!(expected.flipped().normal, reverse.normal);
+        assert_eq!(expected.penetration_depth, reverse.penetration_depth);
+        assert_eq!(expected.collision_point, reverse.collision_point);
+    }
+}
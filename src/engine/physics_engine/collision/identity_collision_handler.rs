@@ -11,25 +11,46 @@ impl IdentityCollisionSolver {
 }
 
 impl CollisionHandler for IdentityCollisionSolver {
-    fn handle_circle_circle_collision(
+    fn circle_circle_contact(
         &self,
-        _body_i: &mut RigidBody,
-        _body_j: &mut RigidBody,
+        _body_i: &RigidBody,
+        _body_j: &RigidBody,
     ) -> Option<CollisionInformation> {
         None
     }
-    fn handle_circle_rect_collision(
+    fn circle_rect_contact(
         &self,
-        _body_i: &mut RigidBody,
-        _body_j: &mut RigidBody,
+        _body_i: &RigidBody,
+        _body_j: &RigidBody,
     ) -> Option<CollisionInformation> {
         None
     }
-    fn handle_rect_rect_collision(
+    fn rect_rect_contact(
         &self,
-        _body_i: &mut RigidBody,
-        _body_j: &mut RigidBody,
+        _body_i: &RigidBody,
+        _body_j: &RigidBody,
     ) -> Option<CollisionInformation> {
         None
     }
+    fn resolve_circle_circle_collision(
+        &self,
+        _body_i: &mut RigidBody,
+        _body_j: &mut RigidBody,
+        _info: &CollisionInformation,
+    ) {
+    }
+    fn resolve_circle_rect_collision(
+        &self,
+        _body_i: &mut RigidBody,
+        _body_j: &mut RigidBody,
+        _info: &CollisionInformation,
+    ) {
+    }
+    fn resolve_rect_rect_collision(
+        &self,
+        _body_i: &mut RigidBody,
+        _body_j: &mut RigidBody,
+        _info: &CollisionInformation,
+    ) {
+    }
 }
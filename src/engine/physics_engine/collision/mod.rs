@@ -1,20 +1,26 @@
+mod aabb;
 pub mod collision_candidates;
+mod collision_filter;
 pub mod collision_handler;
+mod dispatch;
 pub mod identity_collision_handler;
 mod rigid_body;
 pub mod sat;
 mod simple_collision_handler;
 
+pub use aabb::Aabb;
+pub use collision_filter::CollisionFilter;
 pub use collision_handler::CollisionHandler;
+pub use dispatch::{CollisionDispatcher, ShapeKind};
 pub use rigid_body::{RigidBody, RigidBodyBuilder, RigidBodyType};
 pub use simple_collision_handler::SimpleCollisionSolver;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct CollisionGraph {
     pub collisions: Vec<CollisionGraphNode>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct CollisionGraphNode {
     pub body_i_idx: usize,
     pub body_j_idx: usize,
@@ -28,9 +34,131 @@ impl std::fmt::Display for CollisionGraph {
     }
 }
 
-#[derive(Debug)]
+/// The deepest contact point is kept in `penetration_depth` and `collision_point`,
+/// all contact points are in `contact_points`
+#[derive(Clone, Debug, PartialEq)]
 pub struct CollisionInformation {
     pub penetration_depth: f32,
     pub normal: [f32; 3],
     pub collision_point: [f32; 3],
+    pub contact_points: ContactManifold,
+}
+
+impl CollisionInformation {
+    pub fn new(normal: [f32; 3], contact_points: ContactManifold) -> Self {
+        let deepest = contact_points.deepest();
+        Self {
+            penetration_depth: deepest.depth,
+            normal,
+            collision_point: deepest.point,
+            contact_points,
+        }
+    }
+
+    /// The same contact seen from the other body, the normal points the other way
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: [-self.normal[0], -self.normal[1], -self.normal[2]],
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ContactPoint {
+    pub point: [f32; 3],
+    pub depth: f32,
+}
+
+/// Up to two points of contact. Two rectangles touching along an edge have two
+/// points, one at each end of the overlapping part of the edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactManifold {
+    points: [ContactPoint; 2],
+    len: usize,
+}
+
+impl ContactManifold {
+    pub fn single(point: [f32; 3], depth: f32) -> Self {
+        Self {
+            points: [ContactPoint { point, depth }, ContactPoint::default()],
+            len: 1,
+        }
+    }
+
+    /// Keeps the two deepest points, returns `None` if there are no points
+    pub fn from_points(points: &[ContactPoint]) -> Option<Self> {
+        if points.is_empty() {
+            return None;
+        }
+        let mut manifold = Self {
+            points: [ContactPoint::default(); 2],
+            len: 0,
+        };
+        for point in points {
+            if manifold.len < 2 {
+                manifold.points[manifold.len] = *point;
+                manifold.len += 1;
+            } else {
+                let shallowest = match manifold.points[0].depth < manifold.points[1].depth {
+                    true => 0,
+                    false => 1,
+                };
+                if point.depth > manifold.points[shallowest].depth {
+                    manifold.points[shallowest] = *point;
+                }
+            }
+        }
+        Some(manifold)
+    }
+
+    pub fn points(&self) -> &[ContactPoint] {
+        &self.points[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The deepest point, the last one if the points are equally deep
+    pub fn deepest(&self) -> &ContactPoint {
+        self.points()
+            .iter()
+            .max_by(|a, b| a.depth.total_cmp(&b.depth))
+            .expect("Expected at least one contact point")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContactManifold, ContactPoint};
+
+    fn contact(x: f32, depth: f32) -> ContactPoint {
+        ContactPoint {
+            point: [x, 0.0, 0.0],
+            depth,
+        }
+    }
+
+    #[test]
+    fn given_no_points_expect_no_manifold() {
+        assert_eq!(None, ContactManifold::from_points(&[]));
+    }
+
+    #[test]
+    fn given_three_points_expect_the_two_deepest() {
+        let manifold = ContactManifold::from_points(&[
+            contact(0.0, 2.0),
+            contact(1.0, 1.0),
+            contact(2.0, 3.0),
+        ])
+        .unwrap();
+
+        assert_eq!(&[contact(0.0, 2.0), contact(2.0, 3.0)], manifold.points());
+        assert_eq!(&contact(2.0, 3.0), manifold.deepest());
+    }
 }
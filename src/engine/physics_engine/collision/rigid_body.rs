@@ -1,13 +1,26 @@
 use cgmath::Vector3;
 
 use crate::engine::physics_engine::util::{circle_equations, equations, rectangle_equations};
+
+use super::{Aabb, CollisionFilter};
 use crate::engine::util::fixed_float::fixed_float_vector::FixedFloatVector;
 use crate::engine::util::zero;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum RigidBodyType {
-    Circle { radius: f32 },
-    Rectangle { width: f32, height: f32 },
+    Circle {
+        radius: f32,
+    },
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    /// A shape only known to the collision handlers registered for `kind`, bounded by
+    /// a circle of `radius`
+    Custom {
+        kind: u32,
+        radius: f32,
+    },
     Unknown,
 }
 
@@ -24,6 +37,8 @@ pub struct RigidBody {
     pub rotation: f32,
     pub prev_rotation: f32,
     pub rotational_velocity: f32,
+
+    pub collision_filter: CollisionFilter,
 }
 
 impl RigidBody {
@@ -59,12 +74,24 @@ impl RigidBody {
         return closest_point_on_rect;
     }
 
+    pub fn area(&self) -> f32 {
+        match self.body_type {
+            RigidBodyType::Rectangle { width, height } => width * height,
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                std::f32::consts::PI * radius.powi(2)
+            }
+            _ => panic!("Rigid body of type {} has no area", self.body_type),
+        }
+    }
+
     pub fn inertia(&self) -> f32 {
         match self.body_type {
             RigidBodyType::Rectangle { width, height } => {
                 rectangle_equations::inertia(height, width, self.mass)
             }
-            RigidBodyType::Circle { radius } => circle_equations::inertia(radius, self.mass),
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                circle_equations::inertia(radius, self.mass)
+            }
             _ => panic!("Unknown body type"),
         }
     }
@@ -74,13 +101,23 @@ impl RigidBody {
             RigidBodyType::Rectangle { width, height } => {
                 rectangle_equations::cardinals(&self.position.into(), width, height, self.rotation)
             }
-            RigidBodyType::Circle { radius } => {
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
                 circle_equations::cardinals(self.position.into(), radius)
             }
             _ => panic!("Unkown body type"),
         }
     }
 
+    pub fn aabb(&self) -> Aabb {
+        match self.body_type {
+            RigidBodyType::Rectangle { .. } => Aabb::from_points(&self.corners()),
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                Aabb::from_center(self.position.into(), radius, radius)
+            }
+            _ => panic!("Rigid body of type {} has no aabb", self.body_type),
+        }
+    }
+
     pub fn corners(&self) -> Vec<[f32; 3]> {
         match self.body_type {
             RigidBodyType::Rectangle { .. } => rectangle_equations::corners(&self),
@@ -91,7 +128,9 @@ impl RigidBody {
     pub fn click_inside(&self, point: (f32, f32)) -> bool {
         match self.body_type {
             RigidBodyType::Rectangle { .. } => rectangle_equations::click_inside(point, &self),
-            RigidBodyType::Circle { .. } => circle_equations::click_inside(point, &self),
+            RigidBodyType::Circle { .. } | RigidBodyType::Custom { .. } => {
+                circle_equations::click_inside(point, &self)
+            }
 
             _ => panic!(
                 "Rigid body of type {} has no click_inside() function",
@@ -99,6 +138,45 @@ impl RigidBody {
             ),
         }
     }
+
+    pub fn overlaps_aabb(&self, aabb: &Aabb) -> bool {
+        match self.body_type {
+            RigidBodyType::Rectangle { .. } => rectangle_equations::overlaps_aabb(self, aabb),
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                circle_equations::overlaps_aabb(&self.position.into(), radius, aabb)
+            }
+            _ => panic!(
+                "Rigid body of type {} has no overlaps_aabb() function",
+                self.body_type
+            ),
+        }
+    }
+
+    /// Returns the distance along the ray to the first intersection with the body and
+    /// the surface normal at that point. `direction` is expected to be normalized.
+    pub fn ray_intersection(
+        &self,
+        origin: &[f32; 3],
+        direction: &[f32; 3],
+    ) -> Option<(f32, [f32; 3])> {
+        match self.body_type {
+            RigidBodyType::Rectangle { width, height } => rectangle_equations::ray_intersection(
+                origin,
+                direction,
+                &self.position.into(),
+                width,
+                height,
+                self.rotation,
+            ),
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                circle_equations::ray_intersection(origin, direction, &self.position.into(), radius)
+            }
+            _ => panic!(
+                "Rigid body of type {} has no ray_intersection() function",
+                self.body_type
+            ),
+        }
+    }
 }
 
 impl std::fmt::Display for RigidBodyType {
@@ -108,6 +186,7 @@ impl std::fmt::Display for RigidBodyType {
             RigidBodyType::Rectangle { width, height } => {
                 write!(f, "Rectangle({},{})", width, height)
             }
+            RigidBodyType::Custom { kind, radius } => write!(f, "Custom({},{})", kind, radius),
             RigidBodyType::Unknown => write!(f, "Uknown"),
         }
     }
@@ -136,6 +215,7 @@ pub struct RigidBodyBuilder {
     pub rotation: f32,
     pub prev_rotation: Option<f32>,
     pub rotational_velocity: f32,
+    pub collision_filter: CollisionFilter,
 }
 
 impl std::default::Default for RigidBodyBuilder {
@@ -150,6 +230,7 @@ impl std::default::Default for RigidBodyBuilder {
         let rotational_velocity = 0.0;
         let body_type = RigidBodyType::Unknown;
         let mass = 1.0;
+        let collision_filter = CollisionFilter::default();
         Self {
             velocity: velocity.into(),
             rotational_velocity,
@@ -161,6 +242,7 @@ impl std::default::Default for RigidBodyBuilder {
             mass,
             rotation, //inertia,
             prev_rotation,
+            collision_filter,
         }
     }
 }
@@ -216,6 +298,11 @@ impl RigidBodyBuilder {
         self
     }
 
+    pub fn collision_filter(mut self, collision_filter: CollisionFilter) -> Self {
+        self.collision_filter = collision_filter;
+        self
+    }
+
     pub fn build(self) -> RigidBody {
         let id = match self.id {
             Some(id) => id,
@@ -243,6 +330,7 @@ impl RigidBodyBuilder {
             rotation: self.rotation,
             rotational_velocity: self.rotational_velocity,
             prev_rotation,
+            collision_filter: self.collision_filter,
         }
     }
 }
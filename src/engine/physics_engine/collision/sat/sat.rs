@@ -1,4 +1,4 @@
-use super::super::CollisionInformation;
+use super::super::{CollisionInformation, ContactManifold, ContactPoint};
 use crate::engine::physics_engine::collision::rigid_body::{RigidBody, RigidBodyType};
 use crate::engine::physics_engine::util::equations;
 
@@ -483,12 +483,11 @@ fn sat_clip(v1: &[f32; 3], v2: &[f32; 3], normal: &[f32; 3], offset: f32) -> Vec
 /// - `body_b`: A reference to the second `RigidBody`.
 ///
 /// # Returns
-/// - `Some((f32, [f32; 3]))` if a collision is detected, where:
-///   - `f32` is the minimum overlap distance (depth of penetration) between the
-///     two rectangles along the collision axis.
-///   - `[f32; 3]` is the collision axis vector, representing the axis along which
-///     the shapes are intersecting. **Note**: No guarantee is made regarding the
-///     direction of this axis (e.g., pointing towards a specific object).
+/// - `Some(CollisionInformation)` if a collision is detected, where:
+///   - `normal` is the collision axis, pointing from `body_a` towards `body_b`.
+///   - `contact_points` holds the clipped points of contact, two when the rectangles
+///     touch along an edge, each with its own depth of penetration.
+///   - `penetration_depth` and `collision_point` are those of the deepest point.
 /// - `None` if no collision is detected, meaning there is an axis along which the
 ///   two rectangles' projections do not overlap.
 ///
@@ -580,21 +579,17 @@ pub fn sat_collision_detection(
     };
 
     let clipping_points = sat_find_clipping_points(&body_a, &body_b, &collision_normal);
-
-    // Note: For now I only return one averaged collision point as there is no need to
-    // return and edge.
-    let clipping_point = clipping_points
+    let contact_points: Vec<ContactPoint> = clipping_points
         .iter()
-        .max_by(|a, b| a.depth.total_cmp(&b.depth));
-
-    match clipping_point {
-        None => None,
-        Some(cp) => Some(CollisionInformation {
-            penetration_depth: cp.depth,
-            normal: collision_normal,
-            collision_point: cp.vertex,
-        }),
-    }
+        .map(|cp| ContactPoint {
+            point: cp.vertex,
+            depth: cp.depth,
+        })
+        .collect();
+    Some(CollisionInformation::new(
+        collision_normal,
+        ContactManifold::from_points(&contact_points)?,
+    ))
 }
 
 #[cfg(test)]
@@ -1113,7 +1108,7 @@ mod sat_test {
 
     mod sat_collision_detection {
         use super::super::sat_collision_detection;
-        use super::super::CollisionInformation;
+        use super::super::{CollisionInformation, ContactManifold};
         use crate::engine::physics_engine::collision::rigid_body::{
             RigidBodyBuilder, RigidBodyType,
         };
@@ -1184,11 +1179,10 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 20.0, height: 10.0 })
                     .position([9.0,0.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 1.0,
-                    normal: [1.0,0.0,0.0],
-                    collision_point: [-1.0,-5.0,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [1.0,0.0,0.0],
+                    ContactManifold::single([-1.0,-5.0,0.0], 1.0)
+                ))
 
             given_rectangles_are_axis_aligned_when_overlap_on_y_axis_but_bodies_have_swapped_order_expect_collision:
                 RigidBodyBuilder::default().id(1)
@@ -1199,11 +1193,10 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 20.0, height: 10.0 })
                     .position([-10.0,0.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 1.0,
-                    normal: [-1.0,0.0,0.0],
-                    collision_point: [0.0,5.0,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [-1.0,0.0,0.0],
+                    ContactManifold::single([0.0,5.0,0.0], 1.0)
+                ))
 
             given_rectangles_are_axis_aligned_and_offset_from_origo_when_overlapping_on_x_axis_expect_collision:
                 RigidBodyBuilder::default().id(0)
@@ -1214,11 +1207,10 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 20.0, height: 10.0 })
                     .position([-10.0,15.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 5.0,
-                    normal: [0.0,-1.0,0.0],
-                    collision_point: [-20.0,20.0,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [0.0,-1.0,0.0],
+                    ContactManifold::single([-20.0,20.0,0.0], 5.0)
+                ))
 
             given_one_rectangle_is_axis_aligned_and_one_rotated_90_degrees_when_overlap_on_y_axis_expect_collision:
                 RigidBodyBuilder::default().id(1)
@@ -1230,11 +1222,10 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 20.0, height: 10.0 })
                     .position([0.0,0.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 5.0,
-                    normal: [-1.0,0.0,0.0],
-                    collision_point: [10.0,5.0,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [-1.0,0.0,0.0],
+                    ContactManifold::single([10.0,5.0,0.0], 5.0)
+                ))
 
             given_rectangles_are_rotated_45_degrees_when_their_sides_overlap_expect_collision:
                 RigidBodyBuilder::default().id(0)
@@ -1247,11 +1238,10 @@ mod sat_test {
                     .rotation(std::f32::consts::PI/4.0)
                     .position([6.071,6.071,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 1.414,
-                    normal: [0.707,0.707,0.0],
-                    collision_point: [6.071,-1.0,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [0.707,0.707,0.0],
+                    ContactManifold::single([6.071,-1.0,0.0], 1.414)
+                ))
 
             given_rectangles_are_rotated_neg_45_degrees_when_their_sides_overlap_expect_collision:
                 RigidBodyBuilder::default().id(0)
@@ -1264,11 +1254,10 @@ mod sat_test {
                     .rotation(-std::f32::consts::PI/4.0)
                     .position([5.0,-5.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 2.929,
-                    normal: [0.707,-0.707,0.0],
-                    collision_point: [5.0,2.071,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [0.707,-0.707,0.0],
+                    ContactManifold::single([5.0,2.071,0.0], 2.929)
+                ))
 
             given_rectangles_are_rotated_neg_45_degrees_when_their_corners_overlap_expect_collision:
                 RigidBodyBuilder::default().id(0)
@@ -1281,11 +1270,10 @@ mod sat_test {
                     .rotation(std::f32::consts::PI/4.0)
                     .position([5.0,0.0,0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 2.929,
-                    normal: [0.707, 0.707,0.0],
-                    collision_point: [0.0,-2.071,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [0.707, 0.707,0.0],
+                    ContactManifold::single([0.0,-2.071,0.0], 2.929)
+                ))
 
             given_rectangles_are_offset_from_each_other_with_no_rotation_with_half_overlap_expect_collision:
                 RigidBodyBuilder::default().id(0)
@@ -1296,11 +1284,10 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 10.0, height: 10.0 })
                     .position([4.0, -2.5, 0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 2.0,
-                    normal: [1.0,0.0,0.0],
-                    collision_point: [-1.0,-2.5,0.0]
-                })
+                Some(CollisionInformation::new(
+                    [1.0,0.0,0.0],
+                    ContactManifold::single([-1.0,-2.5,0.0], 2.0)
+                ))
 
             given_rectangles_are_offset_from_each_other_with_no_rotation_with_half_overlap_expect_collision_2:
                 RigidBodyBuilder::default().id(0)
@@ -1311,12 +1298,74 @@ mod sat_test {
                     .body_type(RigidBodyType::Rectangle{ width: 10.0, height: 10.0 })
                     .position([-4.0, -2.5, 0.0])
                     .build(),
-                Some(CollisionInformation {
-                    penetration_depth: 2.0,
-                    normal: [-1.0,0.0,0.0],
-                    collision_point: [1.0,-2.5,0.0]
+                Some(CollisionInformation::new(
+                    [-1.0,0.0,0.0],
+                    ContactManifold::single([1.0,-2.5,0.0], 2.0)
+                ))
+
+        }
+
+        #[test]
+        fn given_rectangle_resting_on_edge_of_other_rectangle_expect_two_contact_points() {
+            let ground = RigidBodyBuilder::default()
+                .id(0)
+                .body_type(RigidBodyType::Rectangle {
+                    width: 20.0,
+                    height: 10.0,
                 })
+                .position([0.0, 0.0, 0.0])
+                .build();
+            let block = RigidBodyBuilder::default()
+                .id(1)
+                .body_type(RigidBodyType::Rectangle {
+                    width: 10.0,
+                    height: 10.0,
+                })
+                .position([4.0, 9.0, 0.0])
+                .build();
+
+            let info = sat_collision_detection(&ground, &block).unwrap();
+            let points = info.contact_points.points();
+            assert_eq!(2, points.len(), "Expected two contact points in {info:?}");
+            let mut xs: Vec<f32> = points.iter().map(|p| p.point[0]).collect();
+            xs.sort_by(|a, b| a.total_cmp(b));
+            assert_eq!(
+                vec![-1.0, 9.0],
+                xs,
+                "Expected contact at the ends of the overlap"
+            );
+            assert!(
+                points.iter().all(|p| p.depth == 1.0),
+                "Expected depth 1 in {info:?}"
+            );
+        }
 
+        #[test]
+        fn given_corner_of_rotated_rectangle_overlaps_edge_expect_one_contact_point() {
+            let ground = RigidBodyBuilder::default()
+                .id(0)
+                .body_type(RigidBodyType::Rectangle {
+                    width: 20.0,
+                    height: 10.0,
+                })
+                .position([0.0, 0.0, 0.0])
+                .build();
+            let block = RigidBodyBuilder::default()
+                .id(1)
+                .body_type(RigidBodyType::Rectangle {
+                    width: 10.0,
+                    height: 10.0,
+                })
+                .rotation(std::f32::consts::PI / 4.0)
+                .position([0.0, 11.0, 0.0])
+                .build();
+
+            let info = sat_collision_detection(&ground, &block).unwrap();
+            assert_eq!(
+                1,
+                info.contact_points.len(),
+                "Expected one contact point in {info:?}"
+            );
         }
     }
 }
@@ -1,7 +1,7 @@
 use super::rigid_body::{RigidBody, RigidBodyType};
 use super::sat::sat;
 use super::CollisionHandler;
-use super::CollisionInformation;
+use super::{CollisionInformation, ContactManifold};
 use crate::engine::{
     physics_engine::util::equations::{
         self, impulse_magnitude, post_collision_angular_velocity, post_collision_velocity,
@@ -11,18 +11,51 @@ use crate::engine::{
 
 use cgmath::{InnerSpace, MetricSpace, Vector3};
 
+/// Rectangles do not bounce off each other, such that they can rest on each other
+const RECT_RECT_RESTITUTION: f32 = 0.0;
+
 pub struct SimpleCollisionSolver {}
 impl SimpleCollisionSolver {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Finds the non-negative impulses at two contact points such that each point
+    /// reaches its target velocity, or moves apart faster if its impulse is zero.
+    /// `k[a][b]` is the change in velocity at point a per unit impulse at point b.
+    fn solve_two_point_impulses(k: [[f32; 2]; 2], targets: [f32; 2]) -> [f32; 2] {
+        // Both points push
+        let det = k[0][0] * k[1][1] - k[0][1] * k[1][0];
+        if det > f32::EPSILON * k[0][0] * k[1][1] {
+            let impulses = [
+                (k[1][1] * targets[0] - k[0][1] * targets[1]) / det,
+                (k[0][0] * targets[1] - k[1][0] * targets[0]) / det,
+            ];
+            if impulses[0] >= 0.0 && impulses[1] >= 0.0 {
+                return impulses;
+            }
+        }
+
+        // Only one of the points push
+        let first = targets[0] / k[0][0];
+        if first >= 0.0 && k[1][0] * first >= targets[1] {
+            return [first, 0.0];
+        }
+        let second = targets[1] / k[1][1];
+        if second >= 0.0 && k[0][1] * second >= targets[0] {
+            return [0.0, second];
+        }
+
+        // The points move apart
+        [0.0, 0.0]
+    }
 }
 
 impl CollisionHandler for SimpleCollisionSolver {
-    fn handle_circle_circle_collision(
+    fn circle_circle_contact(
         &self,
-        body_i: &mut RigidBody,
-        body_j: &mut RigidBody,
+        body_i: &RigidBody,
+        body_j: &RigidBody,
     ) -> Option<CollisionInformation> {
         let (radius_i, radius_j) = match (&body_i.body_type, &body_j.body_type) {
             (RigidBodyType::Circle { radius: ri }, RigidBodyType::Circle { radius: rj }) => {
@@ -36,15 +69,31 @@ impl CollisionHandler for SimpleCollisionSolver {
             return None;
         }
 
-        let collision_axis = body_i.position - body_j.position;
+        let collision_axis = body_j.position - body_i.position;
         let collision_normal = collision_axis.normalize();
         let dist = collision_axis.magnitude();
-        let correction_direction = collision_axis / dist;
         let collision_depth = radius_i + radius_j - dist;
-        let collision_point = body_j.position + collision_normal * (*radius_j);
+        let collision_point = body_j.position - collision_normal * (*radius_j);
+
+        let info = CollisionInformation::new(
+            collision_normal.into(),
+            ContactManifold::single(collision_point.into(), collision_depth),
+        );
 
-        body_i.position += 0.5 * collision_depth * correction_direction;
-        body_j.position -= 0.5 * collision_depth * correction_direction;
+        return Some(info);
+    }
+
+    fn resolve_circle_circle_collision(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    ) {
+        let collision_normal = Vector3::from(info.normal);
+        let collision_depth = info.penetration_depth;
+
+        body_i.position -= 0.5 * collision_depth * collision_normal;
+        body_j.position += 0.5 * collision_depth * collision_normal;
 
         let p = body_i.velocity.dot(collision_normal) - body_j.velocity.dot(collision_normal);
         body_i.velocity = body_i.velocity - p * collision_normal;
@@ -52,20 +101,12 @@ impl CollisionHandler for SimpleCollisionSolver {
 
         body_i.prev_position = body_i.position - body_i.velocity;
         body_j.prev_position = body_j.position - body_j.velocity;
-
-        let info = CollisionInformation {
-            penetration_depth: collision_depth,
-            normal: collision_normal.into(),
-            collision_point: collision_point.into(),
-        };
-
-        return Some(info);
     }
 
-    fn handle_circle_rect_collision(
+    fn circle_rect_contact(
         &self,
-        circle: &mut RigidBody,
-        rect: &mut RigidBody,
+        circle: &RigidBody,
+        rect: &RigidBody,
     ) -> Option<CollisionInformation> {
         let radius = match circle.body_type {
             RigidBodyType::Circle { radius } => radius,
@@ -90,8 +131,8 @@ impl CollisionHandler for SimpleCollisionSolver {
         debug_assert!(penetration_depth >= 0.0,
             "Penetration depth less than or equal to the radius the circle causes undefined behavior");
 
-        // Collision normal should always point towards object A (or circle in this case)
-        let collision_normal_unit = (circle.position - closest_point_on_rect).normalize();
+        // The normal points from the circle to the rectangle
+        let collision_normal_unit = (closest_point_on_rect - circle.position).normalize();
 
         let circle_center_to_p = closest_point_on_rect - circle.position;
         let rect_center_to_p = closest_point_on_rect - rect.position;
@@ -106,12 +147,43 @@ impl CollisionHandler for SimpleCollisionSolver {
         ];
 
         // If objects are moving away from each other, we do not consider a collision
-        if equations::dot(&relative_vel_at_p, &collision_normal_unit.into()) > 0.0 {
+        if equations::dot(&relative_vel_at_p, &collision_normal_unit.into()) < 0.0 {
             return None;
         }
 
-        let c_r = 1.0;
+        let info = CollisionInformation::new(
+            collision_normal_unit.into(),
+            ContactManifold::single(closest_point_on_rect.into(), penetration_depth),
+        );
+
+        return Some(info);
+    }
+
+    fn resolve_circle_rect_collision(
+        &self,
+        circle: &mut RigidBody,
+        rect: &mut RigidBody,
+        info: &CollisionInformation,
+    ) {
+        // The normal points from the circle to the rectangle, the impulse equations
+        // expect it to point towards the circle
+        let penetration_depth = info.penetration_depth;
+        let collision_normal_unit = -Vector3::from(info.normal);
+        let closest_point_on_rect = Vector3::from(info.collision_point);
+
+        let circle_center_to_p = closest_point_on_rect - circle.position;
+        let rect_center_to_p = closest_point_on_rect - rect.position;
+        let circle_vel_at_p =
+            equations::total_velocity_at_point_p(circle, &circle_center_to_p.into());
+        let rect_vel_at_p = equations::total_velocity_at_point_p(rect, &rect_center_to_p.into());
 
+        let relative_vel_at_p = [
+            circle_vel_at_p[0] - rect_vel_at_p[0],
+            circle_vel_at_p[1] - rect_vel_at_p[1],
+            circle_vel_at_p[2] - rect_vel_at_p[2],
+        ];
+
+        let c_r = 1.0;
         let impulse_magnitude = impulse_magnitude(
             c_r,
             &collision_normal_unit.into(),
@@ -211,20 +283,12 @@ impl CollisionHandler for SimpleCollisionSolver {
 
         circle.prev_rotation = circle.rotation - circle.rotational_velocity;
         rect.prev_rotation = rect.rotation - rect.rotational_velocity;
-
-        let info = CollisionInformation {
-            penetration_depth,
-            normal: collision_normal_unit.into(),
-            collision_point: closest_point_on_rect.into(),
-        };
-
-        return Some(info);
     }
 
-    fn handle_rect_rect_collision(
+    fn rect_rect_contact(
         &self,
-        body_i: &mut RigidBody,
-        body_j: &mut RigidBody,
+        body_i: &RigidBody,
+        body_j: &RigidBody,
     ) -> Option<CollisionInformation> {
         let ((wi, hi), (wj, hj)) = match (&body_i.body_type, &body_j.body_type) {
             (
@@ -252,6 +316,89 @@ impl CollisionHandler for SimpleCollisionSolver {
 
         return Some(info);
     }
+
+    /// Separates the rectangles along the normal and applies an impulse at each contact
+    /// point. The impulses of the points are solved together, such that an edge resting
+    /// on an edge receives no torque.
+    fn resolve_rect_rect_collision(
+        &self,
+        body_i: &mut RigidBody,
+        body_j: &mut RigidBody,
+        info: &CollisionInformation,
+    ) {
+        // The normal points from body i to body j, the impulse equations expect it to
+        // point towards body i
+        let normal = equations::negate(&info.normal);
+        let inv_mass_i = 1.0 / body_i.mass;
+        let inv_mass_j = 1.0 / body_j.mass;
+        let inv_mass_sum = inv_mass_i + inv_mass_j;
+        if inv_mass_sum == 0.0 {
+            return;
+        }
+
+        let points = info.contact_points.points();
+        let arms: Vec<([f32; 3], [f32; 3])> = points
+            .iter()
+            .map(|p| {
+                (
+                    equations::subtract(&p.point, &body_i.position.into()),
+                    equations::subtract(&p.point, &body_j.position.into()),
+                )
+            })
+            .collect();
+        // Change in relative normal velocity needed at each point
+        let targets: Vec<f32> = arms
+            .iter()
+            .map(|(i_to_p, j_to_p)| {
+                let vel_i_at_p = equations::total_velocity_at_point_p(body_i, i_to_p);
+                let vel_j_at_p = equations::total_velocity_at_point_p(body_j, j_to_p);
+                let relative_vel_at_p = equations::subtract(&vel_i_at_p, &vel_j_at_p);
+                -(1.0 + RECT_RECT_RESTITUTION) * equations::dot(&relative_vel_at_p, &normal)
+            })
+            .collect();
+        // Change in relative normal velocity at point k per unit impulse at point l
+        let (inertia_i, inertia_j) = (body_i.inertia(), body_j.inertia());
+        let k = |k: usize, l: usize| {
+            inv_mass_sum
+                + equations::cross_2d(&arms[k].0, &normal)
+                    * equations::cross_2d(&arms[l].0, &normal)
+                    / inertia_i
+                + equations::cross_2d(&arms[k].1, &normal)
+                    * equations::cross_2d(&arms[l].1, &normal)
+                    / inertia_j
+        };
+        let impulses = match points.len() {
+            1 => vec![(targets[0] / k(0, 0)).max(0.0)],
+            _ => Self::solve_two_point_impulses(
+                [[k(0, 0), k(0, 1)], [k(1, 0), k(1, 1)]],
+                [targets[0], targets[1]],
+            )
+            .to_vec(),
+        };
+
+        for (contact, impulse) in points.iter().zip(impulses) {
+            if impulse == 0.0 {
+                continue;
+            }
+            let rotational_velocity_i =
+                post_collision_angular_velocity(&normal, &contact.point, impulse, body_i);
+            let rotational_velocity_j =
+                post_collision_angular_velocity(&normal, &contact.point, -impulse, body_j);
+            body_i.velocity = post_collision_velocity(&normal, impulse, body_i).into();
+            body_j.velocity = post_collision_velocity(&normal, -impulse, body_j).into();
+            body_i.rotational_velocity = rotational_velocity_i;
+            body_j.rotational_velocity = rotational_velocity_j;
+        }
+
+        let correction = Vector3::from(normal) * (info.penetration_depth / inv_mass_sum);
+        body_i.position += correction * inv_mass_i;
+        body_j.position -= correction * inv_mass_j;
+
+        body_i.prev_position = body_i.position - body_i.velocity;
+        body_j.prev_position = body_j.position - body_j.velocity;
+        body_i.prev_rotation = body_i.rotation - body_i.rotational_velocity;
+        body_j.prev_rotation = body_j.rotation - body_j.rotational_velocity;
+    }
 }
 
 #[cfg(test)]
@@ -288,6 +435,100 @@ mod tests {
         //    assert_eq!(bodies[1].position, Vector3::new(100.0,0.0,0.0), "Wrong position for body 1");
 
         //}
+
+        use super::super::CollisionHandler;
+        use super::super::SimpleCollisionSolver;
+        use crate::engine::physics_engine::collision::rigid_body::{
+            RigidBody, RigidBodyBuilder, RigidBodyType,
+        };
+        use crate::engine::physics_engine::integrator::verlet::VerletIntegrator;
+
+        fn ground() -> RigidBody {
+            RigidBodyBuilder::default()
+                .id(0)
+                .position([0.0, 0.0, 0.0])
+                .body_type(RigidBodyType::Rectangle {
+                    width: 200.0,
+                    height: 10.0,
+                })
+                .mass(f32::INFINITY)
+                .build()
+        }
+
+        fn block(id: usize, position: [f32; 3], velocity: [f32; 3]) -> RigidBody {
+            RigidBodyBuilder::default()
+                .id(id)
+                .position(position)
+                .velocity(velocity)
+                .body_type(RigidBodyType::Rectangle {
+                    width: 10.0,
+                    height: 10.0,
+                })
+                .build()
+        }
+
+        #[test]
+        fn given_block_lands_flat_off_center_on_static_ground_expect_no_rotation() {
+            let ch = SimpleCollisionSolver::new();
+            let mut ground = ground();
+            let mut block = block(1, [30.0, 9.5, 0.0], [0.0, -1.0, 0.0]);
+
+            let info = ch.handle_rect_rect_collision(&mut ground, &mut block);
+
+            assert_eq!(2, info.unwrap().contact_points.len());
+            let position: [f32; 3] = block.position.into();
+            let velocity: [f32; 3] = block.velocity.into();
+            let ground_position: [f32; 3] = ground.position.into();
+            assert_eq!([30.0, 10.0, 0.0], position);
+            assert_eq!([0.0, 0.0, 0.0], velocity);
+            assert_eq!(0.0, block.rotational_velocity);
+            assert_eq!([0.0, 0.0, 0.0], ground_position);
+        }
+
+        #[test]
+        fn given_block_hits_ground_with_corner_expect_rotation() {
+            let ch = SimpleCollisionSolver::new();
+            let mut ground = ground();
+            let mut block = block(1, [30.0, 11.0, 0.0], [0.0, -1.0, 0.0]);
+            block.rotation = 0.3;
+
+            ch.handle_rect_rect_collision(&mut ground, &mut block);
+
+            assert!(block.velocity.y > -1.0);
+            assert!(block.rotational_velocity != 0.0);
+        }
+
+        #[test]
+        fn given_stack_of_blocks_on_static_ground_expect_stack_to_stay_upright() {
+            let ch = SimpleCollisionSolver::new();
+            let integrator = VerletIntegrator::new(f32::MAX);
+            // Blocks are offset sideways such that a single contact point would tip them
+            let mut bodies = vec![ground()];
+            for (i, x) in [0.0, 2.0, -2.0].iter().enumerate() {
+                let mut b = block(i + 1, [*x, 10.0 + 10.0 * i as f32, 0.0], [0.0, 0.0, 0.0]);
+                b.acceleration = [0.0, -10.0, 0.0].into();
+                bodies.push(b);
+            }
+
+            for _ in 0..500 {
+                integrator.update(bodies.iter_mut().skip(1), 0.1);
+                for i in 0..bodies.len() {
+                    for j in (i + 1)..bodies.len() {
+                        let (left, right) = bodies.split_at_mut(j);
+                        ch.handle_rect_rect_collision(&mut left[i], &mut right[0]);
+                    }
+                }
+            }
+
+            for (i, b) in bodies.iter().enumerate().skip(1) {
+                assert!(b.rotation.abs() < 1e-4, "Expected block {i} upright: {b}");
+                let expected_y = 10.0 * i as f32;
+                assert!(
+                    (b.position.y - expected_y).abs() < 0.5,
+                    "Expected block {i} to rest at {expected_y}: {b}"
+                );
+            }
+        }
     }
     mod circle_rect_collision {
 
@@ -1,4 +1,6 @@
-use super::{resolver::ConstraintResolver, Constraint};
+use super::{
+    half_plane_contact, polygon_constraint::Segment, resolver::ConstraintResolver, Constraint,
+};
 use crate::engine::physics_engine::collision::RigidBody;
 use cgmath::Vector3;
 
@@ -32,32 +34,128 @@ impl BoxConstraint {
 
 impl Constraint for BoxConstraint {
     fn apply_constraint(&self, body: &mut RigidBody) {
-        let cardinals = body.cardinals();
+        let top_left: [f32; 3] = self.top_left.into();
+        let bottom_right: [f32; 3] = self.bottom_right.into();
+        let walls = [
+            (top_left, [1.0, 0.0, 0.0]),      // Left side
+            (bottom_right, [-1.0, 0.0, 0.0]), // Right side
+            (bottom_right, [0.0, 1.0, 0.0]),  // Bottom side
+            (top_left, [0.0, -1.0, 0.0]),     // Top side
+        ];
+        for (wall_point, normal) in walls.iter() {
+            if let Some(contact) = half_plane_contact(body, wall_point, normal, |_| true) {
+                self.resolver.resolve(&contact, body);
+            }
+        }
+    }
 
-        let left_most = cardinals[0];
-        let right_most = cardinals[1];
-        let top_most = cardinals[2];
-        let bot_most = cardinals[3];
+    fn bounds(&self) -> Vec<Segment> {
+        let top_left: [f32; 3] = self.top_left.into();
+        let bottom_right: [f32; 3] = self.bottom_right.into();
+        let top_right = [bottom_right[0], top_left[1], 0.0];
+        let bottom_left = [top_left[0], bottom_right[1], 0.0];
+        vec![
+            Segment::new(top_left, top_right),
+            Segment::new(top_right, bottom_right),
+            Segment::new(bottom_right, bottom_left),
+            Segment::new(bottom_left, top_left),
+        ]
+    }
+}
 
-        // Left side
-        if left_most[0] < self.top_left.x {
-            let diff = left_most[0] - self.top_left.x;
-            self.resolver.resolve_horizontal(diff, body);
-        }
-        // Right side
-        if right_most[0] > self.bottom_right.x {
-            let diff = right_most[0] - self.bottom_right.x;
-            self.resolver.resolve_horizontal(diff, body);
-        }
-        // Bottom side
-        if bot_most[1] < self.bottom_right.y {
-            let diff = bot_most[1] - self.bottom_right.y;
-            self.resolver.resolve_vertical(diff, body);
-        }
-        // Top side
-        if top_most[1] > self.top_left.y {
-            let diff = top_most[1] - self.top_left.y;
-            self.resolver.resolve_vertical(diff, body);
-        }
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::BoxConstraint;
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::physics_engine::constraint::resolver::elastic::ElasticConstraintResolver;
+    use crate::engine::physics_engine::constraint::resolver::inelastic::InelasticConstraintResolver;
+    use crate::engine::physics_engine::constraint::resolver::ConstraintResolver;
+    use crate::engine::physics_engine::constraint::Constraint;
+
+    fn constraint<T: ConstraintResolver + 'static>(resolver: T) -> BoxConstraint {
+        let mut constraint = BoxConstraint::new(resolver);
+        constraint.set_top_left(Vector3::new(-100.0, 100.0, 0.0));
+        constraint.set_bottom_right(Vector3::new(100.0, -100.0, 0.0));
+        constraint
+    }
+
+    fn block(position: [f32; 3], velocity: [f32; 3], rotation: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position(position)
+            .velocity(velocity)
+            .rotation(rotation)
+            .body_type(RigidBodyType::Rectangle {
+                width: 20.0,
+                height: 10.0,
+            })
+            .build()
+    }
+
+    #[test]
+    fn given_box_expect_bounds_around_its_sides() {
+        let constraint = constraint(ElasticConstraintResolver::new());
+
+        let starts: Vec<[f32; 3]> = constraint.bounds().iter().map(|s| s.start).collect();
+        assert_eq!(
+            vec![
+                [-100.0, 100.0, 0.0],
+                [100.0, 100.0, 0.0],
+                [100.0, -100.0, 0.0],
+                [-100.0, -100.0, 0.0]
+            ],
+            starts
+        );
+    }
+
+    #[test]
+    fn given_block_lands_flat_on_floor_expect_bounce_without_spin() {
+        let constraint = constraint(ElasticConstraintResolver::new());
+        let mut body = block([30.0, -96.0, 0.0], [0.0, -2.0, 0.0], 0.0);
+
+        constraint.apply_constraint(&mut body);
+
+        assert_eq!(Vector3::new(30.0, -95.0, 0.0), body.position);
+        assert_eq!(Vector3::new(0.0, 2.0, 0.0), body.velocity);
+        assert_eq!(0.0, body.rotational_velocity);
+    }
+
+    #[test]
+    fn given_rotated_block_hits_floor_with_corner_expect_spin() {
+        let constraint = constraint(ElasticConstraintResolver::new());
+        let mut body = block([0.0, -94.0, 0.0], [0.0, -2.0, 0.0], 0.3);
+
+        constraint.apply_constraint(&mut body);
+
+        // The lowest corner is left of the center, the block spins clockwise
+        // Part of the bounce goes into the spin
+        assert!(body.rotational_velocity < 0.0, "{body}");
+        assert!(body.velocity.y > -2.0, "{body}");
+        let lowest = body.corners().iter().map(|c| c[1]).fold(f32::MAX, f32::min);
+        assert!(
+            (lowest + 100.0).abs() < 1e-4,
+            "Expected corner on floor but found {lowest}"
+        );
+    }
+
+    #[test]
+    fn given_friction_when_block_slides_into_floor_expect_sliding_slowed() {
+        let rough = constraint(
+            InelasticConstraintResolver::new()
+                .restitution(0.0)
+                .friction(0.5),
+        );
+        let smooth = constraint(InelasticConstraintResolver::new().restitution(0.0));
+        let mut body = block([0.0, -96.0, 0.0], [4.0, -2.0, 0.0], 0.0);
+        let mut sliding_body = body.clone();
+
+        rough.apply_constraint(&mut body);
+        smooth.apply_constraint(&mut sliding_body);
+
+        assert!(body.velocity.x < sliding_body.velocity.x, "{body}");
+        assert_eq!(4.0, sliding_body.velocity.x);
+        assert!(body.velocity.y.abs() < 1e-5, "{body}");
     }
 }
@@ -1,9 +1,11 @@
-use cgmath::InnerSpace;
-use core::panic;
+use cgmath::{InnerSpace, Vector3};
 
 use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
 
-use super::Constraint;
+use super::{polygon_constraint::Segment, Constraint};
+
+/// Number of segments the circle is drawn with
+const NUM_BOUND_SEGMENTS: usize = 64;
 
 pub struct CircleConstraint {
     radius: f32,
@@ -13,15 +15,8 @@ impl CircleConstraint {
     pub fn new(radius: f32) -> Self {
         Self { radius }
     }
-}
-
-impl Constraint for CircleConstraint {
-    fn apply_constraint(&self, body: &mut RigidBody) {
-        let object_radius = match body.body_type {
-            RigidBodyType::Circle { radius } => radius,
-            _ => panic!("Cirlce constraint only supports circle shaped bodies for now"),
-        };
 
+    fn constrain_circle(&self, body: &mut RigidBody, object_radius: f32) {
         let constraint_radius = self.radius;
         let dist_to_center = body.position.magnitude();
         if dist_to_center + object_radius > constraint_radius {
@@ -30,4 +25,102 @@ impl Constraint for CircleConstraint {
             body.position = correction_direction * (dist_to_center - excess_dist);
         }
     }
+
+    /// Moving the body towards the center along the line through the corner furthest
+    /// out puts that corner on the circle
+    fn constrain_rectangle(&self, body: &mut RigidBody) {
+        let corner = body
+            .corners()
+            .into_iter()
+            .map(Vector3::from)
+            .max_by(|a, b| a.magnitude2().total_cmp(&b.magnitude2()))
+            .unwrap();
+        let dist_to_center = corner.magnitude();
+        if dist_to_center > self.radius {
+            body.position -= corner.normalize() * (dist_to_center - self.radius);
+        }
+    }
+}
+
+impl Constraint for CircleConstraint {
+    fn apply_constraint(&self, body: &mut RigidBody) {
+        match body.body_type {
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                self.constrain_circle(body, radius)
+            }
+            RigidBodyType::Rectangle { .. } => self.constrain_rectangle(body),
+            RigidBodyType::Unknown => {}
+        }
+    }
+
+    fn bounds(&self) -> Vec<Segment> {
+        let point = |i: usize| {
+            let angle = i as f32 * std::f32::consts::TAU / NUM_BOUND_SEGMENTS as f32;
+            [self.radius * angle.cos(), self.radius * angle.sin(), 0.0]
+        };
+        (0..NUM_BOUND_SEGMENTS)
+            .map(|i| Segment::new(point(i), point(i + 1)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::CircleConstraint;
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::physics_engine::constraint::Constraint;
+
+    fn rectangle(position: [f32; 3], rotation: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position(position)
+            .rotation(rotation)
+            .body_type(RigidBodyType::Rectangle {
+                width: 10.0,
+                height: 10.0,
+            })
+            .build()
+    }
+
+    #[test]
+    fn given_circle_outside_expect_it_to_touch_the_bound() {
+        let constraint = CircleConstraint::new(100.0);
+        let mut body = RigidBodyBuilder::default()
+            .id(0)
+            .position([98.0, 0.0, 0.0])
+            .body_type(RigidBodyType::Circle { radius: 5.0 })
+            .build();
+
+        constraint.apply_constraint(&mut body);
+
+        assert!((body.position.x - 95.0).abs() < 1e-4, "{:?}", body.position);
+    }
+
+    #[test]
+    fn given_rotated_rectangle_outside_expect_furthest_corner_on_the_bound() {
+        let constraint = CircleConstraint::new(100.0);
+        let mut body = rectangle([0.0, -97.0, 0.0], std::f32::consts::PI / 4.0);
+
+        constraint.apply_constraint(&mut body);
+
+        let expected = -100.0 + 5.0 * std::f32::consts::SQRT_2;
+        assert!(body.position.x.abs() < 1e-4, "{:?}", body.position);
+        assert!(
+            (body.position.y - expected).abs() < 1e-4,
+            "{:?}",
+            body.position
+        );
+    }
+
+    #[test]
+    fn given_rectangle_inside_expect_no_change() {
+        let constraint = CircleConstraint::new(100.0);
+        let mut body = rectangle([50.0, 20.0, 0.0], 0.3);
+
+        constraint.apply_constraint(&mut body);
+
+        assert_eq!(Vector3::new(50.0, 20.0, 0.0), body.position);
+    }
 }
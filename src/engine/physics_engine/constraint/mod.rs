@@ -1,9 +1,72 @@
-use super::collision::RigidBody;
+use super::collision::{RigidBody, RigidBodyType};
+use super::util::equations;
+use polygon_constraint::Segment;
+use resolver::ConstraintContact;
 
 pub mod box_constraint;
 pub mod circle_constraint;
+pub mod polygon_constraint;
 pub mod resolver;
 
 pub trait Constraint {
     fn apply_constraint(&self, body: &mut RigidBody);
+
+    /// The walls the bodies are kept within, used for debug drawing
+    fn bounds(&self) -> Vec<Segment> {
+        vec![]
+    }
+}
+
+/// Finds where the body is past the line through `wall_point`, bodies are kept on the
+/// side `normal` points to. Only the points of the body accepted by `within` count.
+/// A rectangle past the line with several corners touches it at their average, such
+/// that a flat side landing on the line does not start to spin.
+fn half_plane_contact<F>(
+    body: &RigidBody,
+    wall_point: &[f32; 3],
+    normal: &[f32; 3],
+    within: F,
+) -> Option<ConstraintContact>
+where
+    F: Fn(&[f32; 3]) -> bool,
+{
+    let signed_distance =
+        |p: &[f32; 3]| equations::dot(&equations::subtract(p, wall_point), normal);
+    match body.body_type {
+        RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+            let point = [
+                body.position.x - normal[0] * radius,
+                body.position.y - normal[1] * radius,
+                body.position.z - normal[2] * radius,
+            ];
+            let depth = -signed_distance(&point);
+            (depth > 0.0 && within(&point)).then_some(ConstraintContact {
+                point,
+                normal: *normal,
+                depth,
+            })
+        }
+        RigidBodyType::Rectangle { .. } => {
+            let past: Vec<([f32; 3], f32)> = body
+                .corners()
+                .into_iter()
+                .map(|c| (c, -signed_distance(&c)))
+                .filter(|(c, depth)| *depth > 0.0 && within(c))
+                .collect();
+            if past.is_empty() {
+                return None;
+            }
+            let n = past.len() as f32;
+            let point = past.iter().fold([0.0; 3], |sum, (c, _)| {
+                [sum[0] + c[0] / n, sum[1] + c[1] / n, sum[2] + c[2] / n]
+            });
+            let depth = past.iter().map(|(_, depth)| *depth).fold(0.0, f32::max);
+            Some(ConstraintContact {
+                point,
+                normal: *normal,
+                depth,
+            })
+        }
+        RigidBodyType::Unknown => None,
+    }
 }
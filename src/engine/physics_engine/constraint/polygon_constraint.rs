@@ -0,0 +1,390 @@
+use super::resolver::{ConstraintContact, ConstraintResolver};
+use super::{half_plane_contact, Constraint};
+use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
+use crate::engine::physics_engine::util::{equations, polygon_equations};
+
+/// A wall from `start` to `end`. Bodies are kept on its left side, seen from `start`
+/// towards `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+}
+
+impl Segment {
+    pub fn new(start: [f32; 3], end: [f32; 3]) -> Self {
+        Self { start, end }
+    }
+
+    /// Unit vector pointing to the side bodies are kept on
+    fn normal(&self) -> [f32; 3] {
+        let mut normal = equations::perpendicular_2d(&equations::subtract(&self.end, &self.start));
+        equations::normalize(&mut normal);
+        normal
+    }
+
+    /// Distance of the point from the line through the segment, negative behind it
+    fn signed_distance(&self, point: &[f32; 3]) -> f32 {
+        equations::dot(&equations::subtract(point, &self.start), &self.normal())
+    }
+
+    /// Returns true if the point projects onto the segment and not onto the line
+    /// beyond it
+    fn spans(&self, point: &[f32; 3]) -> bool {
+        let segment = equations::subtract(&self.end, &self.start);
+        let t = equations::dot(&equations::subtract(point, &self.start), &segment)
+            / equations::magnitude2(&segment);
+        (0.0..=1.0).contains(&t)
+    }
+}
+
+/// World bounds made of segments, for circles and rotated rectangles. A body touching
+/// a segment is moved out along the normal of the segment by the resolver, or away
+/// from the end of the segment if a circle touches it there.
+///
+/// Bodies are only moved back if they still overlap the segment, bodies moving further
+/// than their size in one step may pass through.
+pub struct PolygonConstraint {
+    resolver: Box<dyn ConstraintResolver>,
+    segments: Vec<Segment>,
+}
+
+impl PolygonConstraint {
+    /// Keeps bodies inside the closed polygon through `vertices`, which may be in
+    /// clockwise or counter clockwise order
+    pub fn new<T: ConstraintResolver + 'static>(resolver: T, vertices: &[[f32; 3]]) -> Self {
+        let mut vertices = vertices.to_vec();
+        if polygon_equations::signed_area(&vertices) < 0.0 {
+            vertices.reverse();
+        }
+        let segments = (0..vertices.len())
+            .map(|i| Segment::new(vertices[i], vertices[(i + 1) % vertices.len()]))
+            .collect();
+        Self::from_segments(resolver, segments)
+    }
+
+    /// Keeps bodies on the left side of each segment
+    pub fn from_segments<T: ConstraintResolver + 'static>(
+        resolver: T,
+        segments: Vec<Segment>,
+    ) -> Self {
+        Self {
+            resolver: Box::new(resolver),
+            segments,
+        }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    fn circle_contact(
+        center: &[f32; 3],
+        radius: f32,
+        segment: &Segment,
+    ) -> Option<ConstraintContact> {
+        let (closest, _) =
+            polygon_equations::closest_point_on_segment(center, &segment.start, &segment.end);
+        let to_center = equations::subtract(center, &closest);
+        let distance = equations::magnitude(&to_center);
+        if distance >= radius {
+            return None;
+        }
+
+        let signed_distance = segment.signed_distance(center);
+        let (depth, normal) = if signed_distance > 0.0 && distance > 0.0 {
+            // In front of the segment or past one of its ends
+            let mut normal = to_center;
+            equations::normalize(&mut normal);
+            (radius - distance, normal)
+        } else if segment.spans(center) {
+            // The center went through the segment
+            (radius - signed_distance, segment.normal())
+        } else {
+            // Behind the end of the segment, the neighbouring segment handles it
+            return None;
+        };
+        Some(ConstraintContact {
+            point: [
+                center[0] - normal[0] * radius,
+                center[1] - normal[1] * radius,
+                center[2] - normal[2] * radius,
+            ],
+            normal,
+            depth,
+        })
+    }
+
+    fn rectangle_contact(
+        body: &RigidBody,
+        width: f32,
+        height: f32,
+        segment: &Segment,
+    ) -> Option<ConstraintContact> {
+        // Rectangles completely behind the segment are out of reach
+        let half_diagonal = (width.powi(2) + height.powi(2)).sqrt() / 2.0;
+        if segment.signed_distance(&body.position.into()) < -half_diagonal {
+            return None;
+        }
+        half_plane_contact(body, &segment.start, &segment.normal(), |corner| {
+            segment.spans(corner)
+        })
+        .or_else(|| Self::vertex_contact(body, width, height, &segment.start))
+    }
+
+    /// A vertex of the polygon inside the rectangle, as an inner corner of the polygon
+    /// pushing into a side of the rectangle with none of its corners past a segment.
+    /// The rectangle is moved out along the normal of the side it is least past.
+    fn vertex_contact(
+        body: &RigidBody,
+        width: f32,
+        height: f32,
+        vertex: &[f32; 3],
+    ) -> Option<ConstraintContact> {
+        let local = equations::rotate_z(
+            &equations::subtract(vertex, &body.position.into()),
+            -body.rotation,
+        );
+        let depth_x = width / 2.0 - local[0].abs();
+        let depth_y = height / 2.0 - local[1].abs();
+        if depth_x <= 0.0 || depth_y <= 0.0 {
+            return None;
+        }
+
+        // The body moves away from the vertex, against the side it is pushed into
+        let (depth, local_normal) = if depth_x < depth_y {
+            (depth_x, [-local[0].signum(), 0.0, 0.0])
+        } else {
+            (depth_y, [0.0, -local[1].signum(), 0.0])
+        };
+        Some(ConstraintContact {
+            point: *vertex,
+            normal: equations::rotate_z(&local_normal, body.rotation),
+            depth,
+        })
+    }
+
+    fn contact(body: &RigidBody, segment: &Segment) -> Option<ConstraintContact> {
+        match body.body_type {
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                Self::circle_contact(&body.position.into(), radius, segment)
+            }
+            RigidBodyType::Rectangle { width, height } => {
+                Self::rectangle_contact(body, width, height, segment)
+            }
+            RigidBodyType::Unknown => None,
+        }
+    }
+}
+
+impl Constraint for PolygonConstraint {
+    fn apply_constraint(&self, body: &mut RigidBody) {
+        for segment in self.segments.iter() {
+            if let Some(contact) = Self::contact(body, segment) {
+                self.resolver.resolve(&contact, body);
+            }
+        }
+    }
+
+    fn bounds(&self) -> Vec<Segment> {
+        self.segments.clone()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::{PolygonConstraint, Segment};
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+    use crate::engine::physics_engine::constraint::resolver::elastic::ElasticConstraintResolver;
+    use crate::engine::physics_engine::constraint::resolver::inelastic::InelasticConstraintResolver;
+    use crate::engine::physics_engine::constraint::resolver::none::NoneConstraintResolver;
+    use crate::engine::physics_engine::constraint::Constraint;
+
+    const SQUARE: [[f32; 3]; 4] = [
+        [-100.0, -100.0, 0.0],
+        [100.0, -100.0, 0.0],
+        [100.0, 100.0, 0.0],
+        [-100.0, 100.0, 0.0],
+    ];
+
+    /// A U open at the top, the arms are between x 0 and 10 and between x 20 and 30
+    const U_SHAPE: [[f32; 3]; 8] = [
+        [0.0, 0.0, 0.0],
+        [30.0, 0.0, 0.0],
+        [30.0, 30.0, 0.0],
+        [20.0, 30.0, 0.0],
+        [20.0, 10.0, 0.0],
+        [10.0, 10.0, 0.0],
+        [10.0, 30.0, 0.0],
+        [0.0, 30.0, 0.0],
+    ];
+
+    fn circle(position: [f32; 3], radius: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius })
+            .build()
+    }
+
+    fn rectangle(position: [f32; 3], size: f32, rotation: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position(position)
+            .rotation(rotation)
+            .body_type(RigidBodyType::Rectangle {
+                width: size,
+                height: size,
+            })
+            .build()
+    }
+
+    mod apply_constraint {
+        use super::*;
+        macro_rules! apply_constraint_tests {
+            ($($name:ident: $constraint: expr, $body: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let constraint: PolygonConstraint = $constraint;
+                        let mut body: RigidBody = $body;
+                        let expected: [f32; 3] = $expected;
+
+                        constraint.apply_constraint(&mut body);
+
+                        let output: [f32; 3] = body.position.into();
+                        assert!(
+                            (0..3).all(|i| (expected[i] - output[i]).abs() < 1e-3),
+                            "Expected {expected:?} but found {output:?}"
+                        );
+                    }
+                )*
+            }
+        }
+
+        apply_constraint_tests! {
+            given_circle_over_right_side_of_square_expect_it_to_touch_the_side:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &SQUARE),
+                circle([95.0, 0.0, 0.0], 10.0),
+                [90.0, 0.0, 0.0]
+            given_clockwise_square_expect_same_inside:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &[SQUARE[3], SQUARE[2], SQUARE[1], SQUARE[0]]),
+                circle([95.0, 0.0, 0.0], 10.0),
+                [90.0, 0.0, 0.0]
+            given_circle_in_corner_expect_it_to_touch_both_sides:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &SQUARE),
+                circle([-97.0, 98.0, 0.0], 5.0),
+                [-95.0, 95.0, 0.0]
+            given_circle_center_through_side_expect_it_back_inside:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &SQUARE),
+                circle([0.0, -102.0, 0.0], 5.0),
+                [0.0, -95.0, 0.0]
+            given_circle_inside_expect_no_change:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &SQUARE),
+                circle([50.0, 50.0, 0.0], 10.0),
+                [50.0, 50.0, 0.0]
+            given_circle_over_slanted_side_of_triangle_expect_it_moved_along_the_normal:
+                PolygonConstraint::new(
+                    ElasticConstraintResolver::new(),
+                    &[[0.0, 0.0, 0.0], [100.0, 0.0, 0.0], [0.0, 100.0, 0.0]],
+                ),
+                circle([48.0, 48.0, 0.0], 5.0),
+                [50.0 - 2.5 * std::f32::consts::SQRT_2, 50.0 - 2.5 * std::f32::consts::SQRT_2, 0.0]
+            given_rotated_rectangle_over_bottom_of_square_expect_lowest_corner_on_bottom:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &SQUARE),
+                rectangle([0.0, -95.0, 0.0], 20.0, std::f32::consts::PI / 4.0),
+                [0.0, -100.0 + 10.0 * std::f32::consts::SQRT_2, 0.0]
+            given_rectangle_over_left_side_of_square_expect_it_to_touch_the_side:
+                PolygonConstraint::new(InelasticConstraintResolver::new(), &SQUARE),
+                rectangle([-98.0, 0.0, 0.0], 10.0, 0.0),
+                [-95.0, 0.0, 0.0]
+            given_none_resolver_expect_no_change:
+                PolygonConstraint::new(NoneConstraintResolver::new(), &SQUARE),
+                circle([95.0, 0.0, 0.0], 10.0),
+                [95.0, 0.0, 0.0]
+            given_circle_in_arm_of_u_expect_walls_of_other_arm_to_be_ignored:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &U_SHAPE),
+                circle([5.0, 20.0, 0.0], 2.0),
+                [5.0, 20.0, 0.0]
+            given_rectangle_in_arm_of_u_expect_walls_of_other_arm_to_be_ignored:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &U_SHAPE),
+                rectangle([25.0, 20.0, 0.0], 4.0, 0.3),
+                [25.0, 20.0, 0.0]
+            given_circle_below_inner_corner_of_u_expect_it_pushed_away_from_the_corner:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &U_SHAPE),
+                circle([7.0, 7.0, 0.0], 5.0),
+                [10.0 - 2.5 * std::f32::consts::SQRT_2, 10.0 - 2.5 * std::f32::consts::SQRT_2, 0.0]
+            given_inner_corner_of_u_in_side_of_rotated_rectangle_expect_it_pushed_out_of_the_side:
+                PolygonConstraint::new(ElasticConstraintResolver::new(), &U_SHAPE),
+                rectangle([9.5, 6.0, 0.0], 8.0, std::f32::consts::PI / 4.0),
+                [
+                    9.5 - (4.0 - 4.5 / std::f32::consts::SQRT_2) / std::f32::consts::SQRT_2,
+                    6.0 - (4.0 - 4.5 / std::f32::consts::SQRT_2) / std::f32::consts::SQRT_2,
+                    0.0,
+                ]
+            given_circle_above_one_sided_floor_expect_it_on_the_floor:
+                PolygonConstraint::from_segments(
+                    ElasticConstraintResolver::new(),
+                    vec![Segment::new([-100.0, 0.0, 0.0], [100.0, 0.0, 0.0])],
+                ),
+                circle([0.0, 3.0, 0.0], 5.0),
+                [0.0, 5.0, 0.0]
+            given_circle_below_one_sided_floor_expect_no_change:
+                PolygonConstraint::from_segments(
+                    ElasticConstraintResolver::new(),
+                    vec![Segment::new([-100.0, 0.0, 0.0], [100.0, 0.0, 0.0])],
+                ),
+                circle([0.0, -10.0, 0.0], 5.0),
+                [0.0, -10.0, 0.0]
+        }
+    }
+
+    #[test]
+    fn given_vertex_inside_top_of_rectangle_expect_contact_pushing_it_down() {
+        let body = rectangle([0.0, 0.0, 0.0], 10.0, 0.0);
+
+        let contact =
+            PolygonConstraint::vertex_contact(&body, 10.0, 10.0, &[1.0, 4.0, 0.0]).unwrap();
+
+        assert_eq!([0.0, -1.0, 0.0], contact.normal);
+        assert_eq!(1.0, contact.depth);
+        assert_eq!([1.0, 4.0, 0.0], contact.point);
+    }
+
+    #[test]
+    fn given_vertex_outside_rotated_rectangle_expect_no_contact() {
+        let body = rectangle([0.0, 0.0, 0.0], 10.0, std::f32::consts::PI / 4.0);
+
+        let contact = PolygonConstraint::vertex_contact(&body, 10.0, 10.0, &[4.5, 4.5, 0.0]);
+
+        assert_eq!(None, contact);
+    }
+
+    #[test]
+    fn given_elastic_resolver_when_circle_hits_slanted_side_expect_reflected_velocity() {
+        let constraint = PolygonConstraint::new(
+            ElasticConstraintResolver::new(),
+            &[[0.0, 0.0, 0.0], [100.0, 0.0, 0.0], [0.0, 100.0, 0.0]],
+        );
+        let mut body = circle([48.0, 48.0, 0.0], 5.0);
+        body.velocity = Vector3::new(2.0, 0.0, 0.0);
+
+        constraint.apply_constraint(&mut body);
+
+        assert!((body.velocity.x - 0.0).abs() < 1e-5, "{:?}", body.velocity);
+        assert!((body.velocity.y + 2.0).abs() < 1e-5, "{:?}", body.velocity);
+        assert_eq!(body.position - body.velocity, body.prev_position);
+    }
+
+    #[test]
+    fn given_inelastic_resolver_when_circle_moves_away_from_side_expect_velocity_kept() {
+        let constraint = PolygonConstraint::new(InelasticConstraintResolver::new(), &SQUARE);
+        let mut body = circle([95.0, 0.0, 0.0], 10.0);
+        body.velocity = Vector3::new(-1.0, 1.0, 0.0);
+
+        constraint.apply_constraint(&mut body);
+
+        assert_eq!(Vector3::new(-1.0, 1.0, 0.0), body.velocity);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::engine::physics_engine::collision::RigidBody;
 
-use super::ConstraintResolver;
+use super::{apply_impulse, ConstraintContact, ConstraintResolver};
 
+/// Bounces bodies off the boundary without losing energy and without friction
 pub struct ElasticConstraintResolver {}
 impl ElasticConstraintResolver {
     pub fn new() -> Self {
@@ -10,15 +11,7 @@ impl ElasticConstraintResolver {
 }
 
 impl ConstraintResolver for ElasticConstraintResolver {
-    fn resolve_vertical(&self, diff: f32, body: &mut RigidBody) {
-        body.position.y -= diff;
-        body.velocity.y *= -1.0;
-        body.prev_position = body.position - body.velocity;
-    }
-
-    fn resolve_horizontal(&self, diff: f32, body: &mut RigidBody) {
-        body.position.x -= diff;
-        body.velocity.x *= -1.0;
-        body.prev_position = body.position - body.velocity;
+    fn resolve(&self, contact: &ConstraintContact, body: &mut RigidBody) {
+        apply_impulse(contact, body, 1.0, 0.0);
     }
 }
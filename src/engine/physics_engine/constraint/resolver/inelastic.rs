@@ -1,40 +1,34 @@
-use crate::engine::physics_engine::{
-    collision::RigidBody, util::equations::inelastic_collision_1d,
-};
+use crate::engine::physics_engine::collision::RigidBody;
 
-use super::ConstraintResolver;
+use super::{apply_impulse, ConstraintContact, ConstraintResolver};
 
-pub struct InelasticConstraintResolver {}
+/// Bounces bodies off the boundary with a coefficient of `restitution` and slows down
+/// bodies sliding along it with a coefficient of `friction`
+pub struct InelasticConstraintResolver {
+    restitution: f32,
+    friction: f32,
+}
 impl InelasticConstraintResolver {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            restitution: 1.0,
+            friction: 0.0,
+        }
     }
-}
-
-impl ConstraintResolver for InelasticConstraintResolver {
-    fn resolve_vertical(&self, diff: f32, body: &mut RigidBody) {
-        let vel_y_body = body.velocity.y;
 
-        let c_r = 1.0;
-        let mass_wall: f32 = 1_000_000_000.0;
-        let vel_y_wall: f32 = 0.0;
-        let (_, new_vel_y_body) =
-            inelastic_collision_1d(mass_wall, body.mass, vel_y_wall, vel_y_body, c_r);
-        body.position.y -= diff;
-        body.velocity.y = new_vel_y_body;
-        body.prev_position = body.position - body.velocity;
+    pub fn restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
     }
 
-    fn resolve_horizontal(&self, diff: f32, body: &mut RigidBody) {
-        let vel_x_body = body.velocity.x;
+    pub fn friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+}
 
-        let c_r = 1.0;
-        let mass_wall: f32 = 1_000_000_000.0;
-        let vel_x_wall: f32 = 0.0;
-        let (_, new_vel_x_body) =
-            inelastic_collision_1d(mass_wall, body.mass, vel_x_wall, vel_x_body, c_r);
-        body.position.x -= diff;
-        body.velocity.x = new_vel_x_body;
-        body.prev_position = body.position - body.velocity;
+impl ConstraintResolver for InelasticConstraintResolver {
+    fn resolve(&self, contact: &ConstraintContact, body: &mut RigidBody) {
+        apply_impulse(contact, body, self.restitution, self.friction);
     }
 }
@@ -1,11 +1,159 @@
+use cgmath::{InnerSpace, Vector3};
+
 use crate::engine::physics_engine::collision::RigidBody;
+use crate::engine::physics_engine::util::equations;
 
 pub mod elastic;
 pub mod inelastic;
 pub mod none;
 
-#[allow(unused_variables)]
+/// Where a body touches a boundary. `normal` is a unit vector pointing away from the
+/// boundary and `depth` is how far the body is past the boundary at `point`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConstraintContact {
+    pub point: [f32; 3],
+    pub normal: [f32; 3],
+    pub depth: f32,
+}
+
 pub trait ConstraintResolver {
-    fn resolve_vertical(&self, diff: f32, body: &mut RigidBody) {}
-    fn resolve_horizontal(&self, diff: f32, body: &mut RigidBody) {}
+    /// Moves the body out of the boundary and responds to the contact
+    fn resolve(&self, contact: &ConstraintContact, body: &mut RigidBody);
+}
+
+/// Moves the body out of the boundary and applies an impulse at the contact point,
+/// the boundary is static. The normal part of the velocity of the body at the point is
+/// reversed and scaled by `restitution`. The sliding part is reduced by friction, with
+/// an impulse of at most `friction` times the normal impulse. Both impulses change the
+/// rotational velocity if the point is not in line with the center of the body.
+fn apply_impulse(
+    contact: &ConstraintContact,
+    body: &mut RigidBody,
+    restitution: f32,
+    friction: f32,
+) {
+    let normal = Vector3::from(contact.normal);
+    let arm = equations::subtract(&contact.point, &body.position.into());
+    body.position += normal * contact.depth;
+
+    let inv_mass = 1.0 / body.mass;
+    let inv_inertia = 1.0 / body.inertia();
+    let velocity_at_point =
+        |body: &RigidBody| Vector3::from(equations::total_velocity_at_point_p(body, &arm));
+
+    let normal_velocity = velocity_at_point(body).dot(normal);
+    if normal_velocity < 0.0 && inv_mass > 0.0 {
+        let arm_normal = equations::cross_2d(&arm, &contact.normal);
+        let impulse =
+            -(1.0 + restitution) * normal_velocity / (inv_mass + arm_normal.powi(2) * inv_inertia);
+        body.velocity += normal * (impulse * inv_mass);
+        body.rotational_velocity += arm_normal * impulse * inv_inertia;
+
+        let velocity = velocity_at_point(body);
+        let sliding = velocity - normal * velocity.dot(normal);
+        let sliding_speed = sliding.magnitude();
+        if sliding_speed > f32::EPSILON && friction > 0.0 {
+            let tangent = sliding / sliding_speed;
+            let arm_tangent = equations::cross_2d(&arm, &tangent.into());
+            let friction_impulse = (sliding_speed / (inv_mass + arm_tangent.powi(2) * inv_inertia))
+                .min(friction * impulse);
+            body.velocity -= tangent * (friction_impulse * inv_mass);
+            body.rotational_velocity -= arm_tangent * friction_impulse * inv_inertia;
+        }
+    }
+
+    body.prev_position = body.position - body.velocity;
+    body.prev_rotation = body.rotation - body.rotational_velocity;
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::{apply_impulse, ConstraintContact};
+    use crate::engine::physics_engine::collision::{RigidBody, RigidBodyBuilder, RigidBodyType};
+
+    fn square(velocity: [f32; 3], rotation: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .velocity(velocity)
+            .rotation(rotation)
+            .body_type(RigidBodyType::Rectangle {
+                width: 10.0,
+                height: 10.0,
+            })
+            .build()
+    }
+
+    fn floor_contact(point: [f32; 3]) -> ConstraintContact {
+        ConstraintContact {
+            point,
+            normal: [0.0, 1.0, 0.0],
+            depth: 0.5,
+        }
+    }
+
+    #[test]
+    fn given_contact_below_center_expect_bounce_without_spin() {
+        let mut body = square([0.0, -2.0, 0.0], 0.0);
+
+        apply_impulse(&floor_contact([0.0, -5.0, 0.0]), &mut body, 1.0, 0.0);
+
+        assert_eq!(Vector3::new(0.0, 2.0, 0.0), body.velocity);
+        assert_eq!(0.0, body.rotational_velocity);
+        assert_eq!(Vector3::new(0.0, 0.5, 0.0), body.position);
+    }
+
+    #[test]
+    fn given_corner_hits_floor_expect_spin_away_from_corner() {
+        let mut body = square([0.0, -2.0, 0.0], 0.3);
+
+        // Corner right of the center, the body spins counter clockwise
+        apply_impulse(&floor_contact([4.0, -6.0, 0.0]), &mut body, 1.0, 0.0);
+
+        assert!(body.rotational_velocity > 0.0);
+        assert!(body.velocity.y > 0.0 && body.velocity.y < 2.0);
+        assert_eq!(body.rotation - body.rotational_velocity, body.prev_rotation);
+    }
+
+    #[test]
+    fn given_corner_hits_floor_expect_energy_kept_without_restitution_loss() {
+        let mut body = square([0.0, -2.0, 0.0], 0.3);
+        let energy = |b: &RigidBody| {
+            b.mass * (b.velocity.x.powi(2) + b.velocity.y.powi(2))
+                + b.inertia() * b.rotational_velocity.powi(2)
+        };
+        let before = energy(&body);
+
+        apply_impulse(&floor_contact([4.0, -6.0, 0.0]), &mut body, 1.0, 0.0);
+
+        assert!(
+            (before - energy(&body)).abs() < 1e-3,
+            "Expected {before} but found {}",
+            energy(&body)
+        );
+    }
+
+    #[test]
+    fn given_sliding_contact_with_friction_expect_sliding_reduced_and_spin() {
+        let mut body = square([3.0, -1.0, 0.0], 0.0);
+
+        apply_impulse(&floor_contact([0.0, -5.0, 0.0]), &mut body, 0.0, 0.5);
+
+        // The friction impulse is at most half of the normal impulse of 1
+        assert!((body.velocity.x - 2.5).abs() < 1e-5, "{:?}", body.velocity);
+        assert!((body.velocity.y - 0.0).abs() < 1e-5, "{:?}", body.velocity);
+        // Friction below the center makes the body roll forwards, clockwise
+        assert!(body.rotational_velocity < 0.0);
+    }
+
+    #[test]
+    fn given_body_moving_away_from_boundary_expect_only_position_correction() {
+        let mut body = square([1.0, 2.0, 0.0], 0.0);
+
+        apply_impulse(&floor_contact([0.0, -5.0, 0.0]), &mut body, 1.0, 0.5);
+
+        assert_eq!(Vector3::new(1.0, 2.0, 0.0), body.velocity);
+        assert_eq!(0.0, body.rotational_velocity);
+    }
 }
@@ -1,6 +1,6 @@
 use crate::engine::physics_engine::collision::RigidBody;
 
-use super::ConstraintResolver;
+use super::{ConstraintContact, ConstraintResolver};
 
 pub struct NoneConstraintResolver {}
 #[allow(dead_code)]
@@ -11,6 +11,5 @@ impl NoneConstraintResolver {
 }
 #[allow(dead_code)]
 impl ConstraintResolver for NoneConstraintResolver {
-    fn resolve_vertical(&self, _diff: f32, _body: &mut RigidBody) {}
-    fn resolve_horizontal(&self, _diff: f32, _body: &mut RigidBody) {}
+    fn resolve(&self, _contact: &ConstraintContact, _body: &mut RigidBody) {}
 }
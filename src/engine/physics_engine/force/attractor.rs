@@ -0,0 +1,79 @@
+use cgmath::{InnerSpace, Vector3};
+
+use super::ForceGenerator;
+use crate::engine::physics_engine::collision::RigidBody;
+
+/// Pulls bodies towards a point like a planet, the acceleration is `strength` divided
+/// by the squared distance to the point. Closer than `min_distance` the acceleration
+/// stays the same as at `min_distance`, such that bodies passing through the point
+/// are not flung away.
+pub struct PointAttractor {
+    position: Vector3<f32>,
+    strength: f32,
+    min_distance: f32,
+}
+
+impl PointAttractor {
+    pub fn new(position: [f32; 3], strength: f32, min_distance: f32) -> Self {
+        Self {
+            position: position.into(),
+            strength,
+            min_distance,
+        }
+    }
+}
+
+impl ForceGenerator for PointAttractor {
+    fn force(&self, body: &RigidBody, _dt: f32) -> Vector3<f32> {
+        let to_point = self.position - body.position;
+        let distance = to_point.magnitude();
+        if distance == 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let acceleration = self.strength / distance.max(self.min_distance).powi(2);
+        to_point / distance * acceleration * body.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod force {
+        use cgmath::Vector3;
+
+        use super::super::PointAttractor;
+        use crate::engine::physics_engine::collision::{RigidBodyBuilder, RigidBodyType};
+        use crate::engine::physics_engine::force::ForceGenerator;
+        macro_rules! force_tests {
+            ($($name:ident: $position: expr, $mass: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let attractor = PointAttractor::new([0.0, 0.0, 0.0], 100.0, 2.0);
+                        let body = RigidBodyBuilder::default()
+                            .id(0)
+                            .position($position)
+                            .mass($mass)
+                            .body_type(RigidBodyType::Circle { radius: 1.0 })
+                            .build();
+                        let expected: Vector3<f32> = $expected.into();
+
+                        let output = attractor.force(&body, 0.1);
+
+                        assert_eq!(expected, output);
+                    }
+                )*
+            }
+        }
+
+        force_tests! {
+            given_body_right_of_point_expect_pull_to_the_left:
+                [10.0, 0.0, 0.0], 1.0, [-1.0, 0.0, 0.0]
+            given_heavier_body_expect_force_proportional_to_mass:
+                [0.0, 10.0, 0.0], 3.0, [0.0, -3.0, 0.0]
+            given_body_within_min_distance_expect_force_at_min_distance:
+                [0.0, -1.0, 0.0], 1.0, [0.0, 25.0, 0.0]
+            given_body_at_point_expect_no_force:
+                [0.0, 0.0, 0.0], 1.0, [0.0, 0.0, 0.0]
+        }
+    }
+}
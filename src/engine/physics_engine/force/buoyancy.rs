@@ -0,0 +1,140 @@
+use cgmath::Vector3;
+
+use super::{velocity, ForceGenerator};
+use crate::engine::physics_engine::collision::{Aabb, RigidBody};
+
+/// A region of fluid. Bodies are pushed against `gravity` by the weight of the fluid
+/// they displace and slowed down by `linear_drag`, both scaled by how much of the body
+/// is submerged. The submerged part is estimated from the bounding box of the body.
+pub struct Buoyancy {
+    region: Aabb,
+    density: f32,
+    gravity: Vector3<f32>,
+    linear_drag: f32,
+}
+
+impl Buoyancy {
+    pub fn new(region: Aabb, density: f32, gravity: [f32; 3], linear_drag: f32) -> Self {
+        Self {
+            region,
+            density,
+            gravity: gravity.into(),
+            linear_drag,
+        }
+    }
+
+    /// The part of the body inside the region, between 0 and 1
+    fn submerged_fraction(&self, body: &RigidBody) -> f32 {
+        let aabb = body.aabb();
+        let area = aabb.width() * aabb.height();
+        match self.region.intersection(&aabb) {
+            Some(submerged) if area > 0.0 => submerged.width() * submerged.height() / area,
+            _ => 0.0,
+        }
+    }
+}
+
+impl ForceGenerator for Buoyancy {
+    fn force(&self, body: &RigidBody, dt: f32) -> Vector3<f32> {
+        let fraction = self.submerged_fraction(body);
+        if fraction == 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let displaced_mass = self.density * body.area() * fraction;
+        -self.gravity * displaced_mass - velocity(body, dt) * self.linear_drag * fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::Buoyancy;
+    use crate::engine::physics_engine::collision::{
+        Aabb, RigidBody, RigidBodyBuilder, RigidBodyType,
+    };
+    use crate::engine::physics_engine::force::{ForceGenerator, ForceRegistry, Gravity};
+    use crate::engine::physics_engine::integrator::verlet::VerletIntegrator;
+
+    fn water() -> Buoyancy {
+        // Surface at y = 0
+        let region = Aabb::new([-100.0, -100.0, 0.0], [100.0, 0.0, 0.0]);
+        Buoyancy::new(region, 1.0, [0.0, -10.0, 0.0], 0.0)
+    }
+
+    fn block(y: f32, mass: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position([0.0, y, 0.0])
+            .mass(mass)
+            .body_type(RigidBodyType::Rectangle {
+                width: 2.0,
+                height: 2.0,
+            })
+            .build()
+    }
+
+    mod force {
+        use cgmath::Vector3;
+
+        use super::{block, water};
+        use crate::engine::physics_engine::force::ForceGenerator;
+        macro_rules! force_tests {
+            ($($name:ident: $y: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: Vector3<f32> = $expected.into();
+
+                        let output = water().force(&block($y, 1.0), 0.1);
+
+                        assert_eq!(expected, output);
+                    }
+                )*
+            }
+        }
+
+        force_tests! {
+            given_submerged_body_expect_weight_of_displaced_fluid:
+                -10.0, [0.0, 40.0, 0.0]
+            given_half_submerged_body_expect_half_the_force:
+                0.0, [0.0, 20.0, 0.0]
+            given_body_above_surface_expect_no_force:
+                5.0, [0.0, 0.0, 0.0]
+        }
+    }
+
+    #[test]
+    fn given_moving_submerged_body_expect_drag_against_velocity() {
+        let region = Aabb::new([-100.0, -100.0, 0.0], [100.0, 0.0, 0.0]);
+        let water = Buoyancy::new(region, 0.0, [0.0, -10.0, 0.0], 2.0);
+        let mut body = block(-10.0, 1.0);
+        body.prev_position = Vector3::new(-1.0, -10.0, 0.0);
+
+        assert_eq!(Vector3::new(-2.0, 0.0, 0.0), water.force(&body, 1.0));
+    }
+
+    #[test]
+    fn given_body_lighter_than_fluid_expect_it_to_float_at_surface() {
+        let dt = 0.05;
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0.0, -10.0, 0.0]));
+        let region = Aabb::new([-100.0, -100.0, 0.0], [100.0, 0.0, 0.0]);
+        forces.add(Buoyancy::new(region, 1.0, [0.0, -10.0, 0.0], 2.0));
+        let integrator = VerletIntegrator::new(f32::MAX);
+        // Half as dense as the fluid
+        let mut body = block(-5.0, 2.0);
+
+        for _ in 0..2000 {
+            forces.apply(std::iter::once(&mut body), dt);
+            integrator.update(std::iter::once(&mut body), dt);
+        }
+
+        // Floats with half of its height of 2 below the surface
+        assert!(
+            body.position.y.abs() < 0.01,
+            "Expected the block to float at 0 but found {}",
+            body.position.y
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use cgmath::{InnerSpace, Vector3};
+
+use super::{velocity, ForceGenerator};
+use crate::engine::physics_engine::collision::RigidBody;
+
+/// Slows bodies down with a force of `linear * v + quadratic * |v| * v` against their
+/// velocity `v`
+pub struct Drag {
+    linear: f32,
+    quadratic: f32,
+}
+
+impl Drag {
+    pub fn new(linear: f32, quadratic: f32) -> Self {
+        Self { linear, quadratic }
+    }
+}
+
+impl ForceGenerator for Drag {
+    fn force(&self, body: &RigidBody, dt: f32) -> Vector3<f32> {
+        let velocity = velocity(body, dt);
+        -velocity * (self.linear + self.quadratic * velocity.magnitude())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod force {
+        use cgmath::Vector3;
+
+        use super::super::Drag;
+        use crate::engine::physics_engine::collision::{RigidBodyBuilder, RigidBodyType};
+        use crate::engine::physics_engine::force::ForceGenerator;
+        macro_rules! force_tests {
+            ($($name:ident: $drag: expr, $velocity: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let drag: Drag = $drag;
+                        let body = RigidBodyBuilder::default()
+                            .id(0)
+                            .velocity($velocity)
+                            .body_type(RigidBodyType::Circle { radius: 1.0 })
+                            .build();
+                        let expected: Vector3<f32> = $expected.into();
+
+                        // Velocities are per step, a step of 0.5 doubles them
+                        let output = drag.force(&body, 0.5);
+
+                        assert_eq!(expected, output);
+                    }
+                )*
+            }
+        }
+
+        force_tests! {
+            given_linear_drag_expect_force_proportional_to_velocity:
+                Drag::new(0.5, 0.0), [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0]
+            given_quadratic_drag_expect_force_proportional_to_squared_speed:
+                Drag::new(0.0, 0.5), [0.0, -2.0, 0.0], [0.0, 8.0, 0.0]
+            given_body_at_rest_expect_no_force:
+                Drag::new(1.0, 1.0), [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use cgmath::Vector3;
+
+use super::ForceGenerator;
+use crate::engine::physics_engine::collision::RigidBody;
+
+/// The same acceleration on every body, independent of its mass
+pub struct Gravity {
+    acceleration: Vector3<f32>,
+}
+
+impl Gravity {
+    pub fn new(acceleration: [f32; 3]) -> Self {
+        Self {
+            acceleration: acceleration.into(),
+        }
+    }
+}
+
+impl ForceGenerator for Gravity {
+    fn force(&self, body: &RigidBody, _dt: f32) -> Vector3<f32> {
+        self.acceleration * body.mass
+    }
+}
@@ -0,0 +1,192 @@
+mod attractor;
+mod buoyancy;
+mod drag;
+mod gravity;
+mod wind;
+
+pub use attractor::PointAttractor;
+pub use buoyancy::Buoyancy;
+pub use drag::Drag;
+pub use gravity::Gravity;
+pub use wind::Wind;
+
+use cgmath::Vector3;
+
+use super::collision::{CollisionFilter, RigidBody, RigidBodyType};
+
+/// Computes a force acting on a body at the start of a step
+pub trait ForceGenerator {
+    /// `dt` is the time step passed to the integrator
+    fn force(&self, body: &RigidBody, dt: f32) -> Vector3<f32>;
+}
+
+/// The velocity the integrator will move the body with during the step, in units per
+/// second rather than per step
+fn velocity(body: &RigidBody, dt: f32) -> Vector3<f32> {
+    (body.position - body.prev_position) / dt
+}
+
+/// The force generators of a simulation. Each generator only acts on the bodies whose
+/// layer is in the mask of its filter, the same way as the filter of a query.
+///
+/// Call `apply` before `VerletIntegrator::update`, it replaces the acceleration of each
+/// body with the sum of the forces acting on it divided by its mass. Bodies of
+/// infinite mass and of unknown type are not accelerated.
+#[derive(Default)]
+pub struct ForceRegistry {
+    generators: Vec<Option<(Box<dyn ForceGenerator>, CollisionFilter)>>,
+}
+
+impl ForceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a generator acting on all bodies and returns its id
+    pub fn add<G: ForceGenerator + 'static>(&mut self, generator: G) -> usize {
+        self.add_filtered(generator, CollisionFilter::default())
+    }
+
+    /// Adds a generator acting on the bodies accepted by `filter` and returns its id
+    pub fn add_filtered<G: ForceGenerator + 'static>(
+        &mut self,
+        generator: G,
+        filter: CollisionFilter,
+    ) -> usize {
+        self.generators.push(Some((Box::new(generator), filter)));
+        self.generators.len() - 1
+    }
+
+    /// Returns true if there was a generator with the id
+    pub fn remove(&mut self, id: usize) -> bool {
+        match self.generators.get_mut(id) {
+            Some(generator) => generator.take().is_some(),
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.generators.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn apply<'a, I>(&self, bodies: I, dt: f32)
+    where
+        I: Iterator<Item = &'a mut RigidBody>,
+    {
+        for body in bodies {
+            if body.body_type == RigidBodyType::Unknown || !body.mass.is_finite() {
+                body.acceleration = Vector3::new(0.0, 0.0, 0.0);
+                continue;
+            }
+            let force: Vector3<f32> = self
+                .generators
+                .iter()
+                .flatten()
+                .filter(|(_, filter)| filter.accepts(&body.collision_filter))
+                .map(|(generator, _)| generator.force(body, dt))
+                .sum();
+            body.acceleration = force / body.mass;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::{Drag, ForceRegistry, Gravity};
+    use crate::engine::physics_engine::collision::{
+        CollisionFilter, RigidBody, RigidBodyBuilder, RigidBodyType,
+    };
+    use crate::engine::physics_engine::integrator::verlet::VerletIntegrator;
+
+    fn circle(id: usize, layer: u32, mass: f32) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .body_type(RigidBodyType::Circle { radius: 1.0 })
+            .mass(mass)
+            .collision_filter(CollisionFilter::new(layer, CollisionFilter::ALL_LAYERS))
+            .build()
+    }
+
+    #[test]
+    fn given_generator_filtered_by_layer_expect_only_bodies_in_layer_to_be_accelerated() {
+        let mut forces = ForceRegistry::new();
+        forces.add_filtered(
+            Gravity::new([0.0, -10.0, 0.0]),
+            CollisionFilter::new(0b1, 0b10),
+        );
+        let mut bodies = [circle(0, 0b01, 1.0), circle(1, 0b10, 1.0)];
+
+        forces.apply(bodies.iter_mut(), 0.1);
+
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0), bodies[0].acceleration);
+        assert_eq!(Vector3::new(0.0, -10.0, 0.0), bodies[1].acceleration);
+    }
+
+    #[test]
+    fn given_several_generators_expect_sum_of_forces_divided_by_mass() {
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0.0, -10.0, 0.0]));
+        forces.add(Drag::new(2.0, 0.0));
+        let mut body = circle(0, 0b1, 2.0);
+        body.prev_position = Vector3::new(-1.0, 0.0, 0.0);
+
+        forces.apply(std::iter::once(&mut body), 1.0);
+
+        // Drag of -2 along x, gravity does not depend on mass
+        assert_eq!(Vector3::new(-1.0, -10.0, 0.0), body.acceleration);
+    }
+
+    #[test]
+    fn given_static_body_expect_no_acceleration() {
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0.0, -10.0, 0.0]));
+        let mut body = circle(0, 0b1, f32::INFINITY);
+
+        forces.apply(std::iter::once(&mut body), 0.1);
+
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0), body.acceleration);
+    }
+
+    #[test]
+    fn given_removed_generator_expect_acceleration_to_be_reset() {
+        let mut forces = ForceRegistry::new();
+        let gravity = forces.add(Gravity::new([0.0, -10.0, 0.0]));
+        let mut body = circle(0, 0b1, 1.0);
+        forces.apply(std::iter::once(&mut body), 0.1);
+
+        assert!(forces.remove(gravity));
+        assert!(!forces.remove(gravity));
+        forces.apply(std::iter::once(&mut body), 0.1);
+
+        assert!(forces.is_empty());
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0), body.acceleration);
+    }
+
+    #[test]
+    fn given_drag_when_falling_expect_terminal_velocity() {
+        let dt = 0.01;
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0.0, -10.0, 0.0]));
+        forces.add(Drag::new(0.0, 0.1));
+        let integrator = VerletIntegrator::new(f32::MAX);
+        let mut body = circle(0, 0b1, 1.0);
+
+        for _ in 0..2000 {
+            forces.apply(std::iter::once(&mut body), dt);
+            integrator.update(std::iter::once(&mut body), dt);
+        }
+
+        // Drag balances gravity at 0.1 * v^2 = 10
+        let speed = body.velocity.y.abs() / dt;
+        assert!(
+            (speed - 10.0).abs() < 0.1,
+            "Expected speed 10 but found {speed}"
+        );
+    }
+}
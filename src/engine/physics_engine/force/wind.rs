@@ -0,0 +1,78 @@
+use cgmath::Vector3;
+
+use super::{velocity, ForceGenerator};
+use crate::engine::physics_engine::collision::{Aabb, RigidBody};
+
+/// Pushes the bodies whose center is inside `region` towards the velocity of the wind,
+/// with a force of `coefficient` times the velocity of the wind relative to the body
+pub struct Wind {
+    region: Aabb,
+    velocity: Vector3<f32>,
+    coefficient: f32,
+}
+
+impl Wind {
+    pub fn new(region: Aabb, velocity: [f32; 3], coefficient: f32) -> Self {
+        Self {
+            region,
+            velocity: velocity.into(),
+            coefficient,
+        }
+    }
+}
+
+impl ForceGenerator for Wind {
+    fn force(&self, body: &RigidBody, dt: f32) -> Vector3<f32> {
+        if !self
+            .region
+            .contains_point((body.position.x, body.position.y))
+        {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        (self.velocity - velocity(body, dt)) * self.coefficient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod force {
+        use cgmath::Vector3;
+
+        use super::super::Wind;
+        use crate::engine::physics_engine::collision::{Aabb, RigidBodyBuilder, RigidBodyType};
+        use crate::engine::physics_engine::force::ForceGenerator;
+        macro_rules! force_tests {
+            ($($name:ident: $position: expr, $velocity: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let region = Aabb::new([0.0, 0.0, 0.0], [10.0, 10.0, 0.0]);
+                        let wind = Wind::new(region, [4.0, 0.0, 0.0], 0.5);
+                        let body = RigidBodyBuilder::default()
+                            .id(0)
+                            .position($position)
+                            .velocity($velocity)
+                            .body_type(RigidBodyType::Circle { radius: 1.0 })
+                            .build();
+                        let expected: Vector3<f32> = $expected.into();
+
+                        let output = wind.force(&body, 1.0);
+
+                        assert_eq!(expected, output);
+                    }
+                )*
+            }
+        }
+
+        force_tests! {
+            given_body_at_rest_in_region_expect_push_along_wind:
+                [5.0, 5.0, 0.0], [0.0, 0.0, 0.0], [2.0, 0.0, 0.0]
+            given_body_moving_with_wind_expect_no_force:
+                [5.0, 5.0, 0.0], [4.0, 0.0, 0.0], [0.0, 0.0, 0.0]
+            given_body_moving_against_wind_expect_larger_push:
+                [5.0, 5.0, 0.0], [-2.0, 1.0, 0.0], [3.0, -0.5, 0.0]
+            given_body_outside_region_expect_no_force:
+                [15.0, 5.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]
+        }
+    }
+}
@@ -2,5 +2,8 @@ pub mod collision;
 pub mod broadphase;
 pub mod narrowphase;
 pub mod constraint;
+pub mod force;
 pub mod integrator;
+pub mod query;
+pub mod state_hash;
 pub mod util;
@@ -1,10 +1,78 @@
 pub mod naive;
+mod pair_cache;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
 use super::collision::{collision_candidates::CollisionCandidates, CollisionGraph, RigidBody};
 
+pub use pair_cache::PairCache;
+
 pub trait NarrowPhase {
+    /// Tests and resolves the pairs of candidates not yet in `pairs`
     fn collision_detection(
         &self,
         bodies: &mut Vec<&mut RigidBody>,
         candidates: &CollisionCandidates,
+        pairs: &mut PairCache,
+    ) -> Option<CollisionGraph>;
+
+    /// Finds the contacts between the candidates without changing the bodies
+    fn contact_detection(
+        &self,
+        bodies: &[&RigidBody],
+        candidates: &CollisionCandidates,
     ) -> Option<CollisionGraph>;
+
+    /// Resolves the contacts in the order of the graph. Each contact is tested again
+    /// before it is resolved, contacts separated by an earlier one are removed from
+    /// the graph and the others are updated.
+    fn resolve_contacts(&self, bodies: &mut Vec<&mut RigidBody>, graph: &mut CollisionGraph);
+
+    /// Detects the contacts of all candidate lists in parallel without changing the
+    /// bodies, then resolves the lists one at a time in the order of `candidates`,
+    /// independent of the number of threads. The bodies, graphs and `pairs` are the
+    /// same as after calling `collision_detection` on each list in order.
+    ///
+    /// A list is resolved with `collision_detection` if it had a contact or holds a
+    /// body moved by an earlier list, resolving a contact may create a new one. The
+    /// pairs of the other lists are only added to `pairs`.
+    fn parallel_collision_detection(
+        &self,
+        bodies: &mut Vec<&mut RigidBody>,
+        candidates: &[CollisionCandidates],
+        pairs: &mut PairCache,
+    ) -> Vec<CollisionGraph>
+    where
+        Self: Sync,
+    {
+        let contacts: Vec<Option<CollisionGraph>> = {
+            let bodies: Vec<&RigidBody> = bodies.iter().map(|b| &**b).collect();
+            candidates
+                .par_iter()
+                .map(|c| self.contact_detection(&bodies, c))
+                .collect()
+        };
+
+        let mut is_moved = vec![false; bodies.len()];
+        candidates
+            .iter()
+            .zip(contacts)
+            .filter_map(|(c, contacts)| {
+                if contacts.is_none() && c.indices.iter().all(|i| !is_moved[*i]) {
+                    // Testing the pairs again would find no contact either
+                    for (i, idx_i) in c.indices.iter().enumerate() {
+                        for idx_j in c.indices[(i + 1)..].iter() {
+                            pairs.insert(*idx_i, *idx_j);
+                        }
+                    }
+                    return None;
+                }
+                let graph = self.collision_detection(bodies, c, pairs)?;
+                for node in graph.collisions.iter() {
+                    is_moved[node.body_i_idx] = true;
+                    is_moved[node.body_j_idx] = true;
+                }
+                Some(graph)
+            })
+            .collect()
+    }
 }
@@ -1,35 +1,40 @@
 use crate::engine::physics_engine::collision::{
-    collision_candidates::CollisionCandidates,
-    collision_handler::CollisionHandler,
-    CollisionGraph, CollisionGraphNode, {RigidBody, RigidBodyType},
+    collision_candidates::CollisionCandidates, collision_handler::CollisionHandler,
+    CollisionDispatcher, CollisionGraph, CollisionGraphNode, RigidBody,
 };
 
-use super::NarrowPhase;
+use super::{NarrowPhase, PairCache};
 
-pub struct Naive<H>
-where
-    H: CollisionHandler,
-{
-    solver: H,
+/// Tests every pair of candidates, the handler of a pair is found from the kinds of
+/// the shapes of the bodies
+pub struct Naive {
+    dispatcher: CollisionDispatcher,
 }
 
-impl<H> Naive<H>
-where
-    H: CollisionHandler,
-{
-    pub fn new(solver: H) -> Self {
-        Self { solver }
+impl Naive {
+    /// Handles circles and rectangles with `solver`
+    pub fn new<H>(solver: H) -> Self
+    where
+        H: CollisionHandler + Send + Sync + 'static,
+    {
+        Self::with_dispatcher(CollisionDispatcher::from_handler(solver))
+    }
+
+    pub fn with_dispatcher(dispatcher: CollisionDispatcher) -> Self {
+        Self { dispatcher }
+    }
+
+    pub fn dispatcher_mut(&mut self) -> &mut CollisionDispatcher {
+        &mut self.dispatcher
     }
 }
 
-impl<H> NarrowPhase for Naive<H>
-where
-    H: CollisionHandler,
-{
+impl NarrowPhase for Naive {
     fn collision_detection(
         &self,
         bodies: &mut Vec<&mut RigidBody>,
         candidates: &CollisionCandidates,
+        pairs: &mut PairCache,
     ) -> Option<CollisionGraph> {
         let num_candidates = candidates.len();
         if num_candidates <= 1 {
@@ -41,6 +46,9 @@ where
             for j in (i + 1)..num_candidates as usize {
                 let idx_i = candidates.indices[i];
                 let idx_j = candidates.indices[j];
+                if !pairs.insert(idx_i, idx_j) {
+                    continue;
+                }
 
                 let (min_idx, max_idx) = if idx_i < idx_j {
                     (idx_i, idx_j)
@@ -49,32 +57,54 @@ where
                 };
                 let (left, right) = bodies.split_at_mut(max_idx);
 
-                let mut body_i = &mut left[min_idx];
-                let mut body_j = &mut right[0];
+                let body_i = &mut left[min_idx];
+                let body_j = &mut right[0];
 
-                let collision_info = match (&body_i.body_type, &body_j.body_type) {
-                    (RigidBodyType::Circle { .. }, RigidBodyType::Circle { .. }) => self
-                        .solver
-                        .handle_circle_circle_collision(&mut body_i, &mut body_j),
-                    (RigidBodyType::Rectangle { .. }, RigidBodyType::Rectangle { .. }) => self
-                        .solver
-                        .handle_rect_rect_collision(&mut body_i, &mut body_j),
-
-                    (RigidBodyType::Rectangle { .. }, RigidBodyType::Circle { .. }) => self
-                        .solver
-                        .handle_circle_rect_collision(&mut body_j, &mut body_i),
+                if !body_i
+                    .collision_filter
+                    .can_collide(&body_j.collision_filter)
+                {
+                    continue;
+                }
 
-                    (RigidBodyType::Circle { .. }, RigidBodyType::Rectangle { .. }) => self
-                        .solver
-                        .handle_circle_rect_collision(&mut body_i, &mut body_j),
+                if let Some(info) = self.dispatcher.handle(body_i, body_j) {
+                    collisions.push(CollisionGraphNode {
+                        body_i_idx: idx_i,
+                        body_j_idx: idx_j,
+                        info,
+                    });
+                }
+            }
+        }
+        match collisions.len() {
+            0 => None,
+            _ => Some(CollisionGraph { collisions }),
+        }
+    }
 
-                    (_, _) => panic!("Unknown body type collision {body_i} and {body_j}"),
+    fn contact_detection(
+        &self,
+        bodies: &[&RigidBody],
+        candidates: &CollisionCandidates,
+    ) -> Option<CollisionGraph> {
+        let mut collisions: Vec<CollisionGraphNode> = vec![];
+        for (i, idx_i) in candidates.indices.iter().enumerate() {
+            for idx_j in candidates.indices[(i + 1)..].iter() {
+                let (body_i, body_j) = match idx_i < idx_j {
+                    true => (bodies[*idx_i], bodies[*idx_j]),
+                    false => (bodies[*idx_j], bodies[*idx_i]),
                 };
+                if !body_i
+                    .collision_filter
+                    .can_collide(&body_j.collision_filter)
+                {
+                    continue;
+                }
 
-                if let Some(info) = collision_info {
+                if let Some(info) = self.dispatcher.contact(body_i, body_j) {
                     collisions.push(CollisionGraphNode {
-                        body_i_idx: idx_i,
-                        body_j_idx: idx_j,
+                        body_i_idx: *idx_i,
+                        body_j_idx: *idx_j,
                         info,
                     });
                 }
@@ -85,4 +115,360 @@ where
             _ => Some(CollisionGraph { collisions }),
         }
     }
+
+    fn resolve_contacts(&self, bodies: &mut Vec<&mut RigidBody>, graph: &mut CollisionGraph) {
+        graph.collisions.retain_mut(|node| {
+            let (min_idx, max_idx) = match node.body_i_idx < node.body_j_idx {
+                true => (node.body_i_idx, node.body_j_idx),
+                false => (node.body_j_idx, node.body_i_idx),
+            };
+            let (left, right) = bodies.split_at_mut(max_idx);
+            // The contact is tested again, earlier contacts may have separated the bodies
+            match self.dispatcher.handle(left[min_idx], right[0]) {
+                Some(info) => {
+                    node.info = info;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::Naive;
+    use crate::engine::physics_engine::broadphase::{BlockMap, BroadPhase, SpatialSubdivision};
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{
+        CollisionGraph, CollisionInformation, ContactManifold, RigidBody, RigidBodyBuilder,
+        RigidBodyType, ShapeKind, SimpleCollisionSolver,
+    };
+    use crate::engine::physics_engine::narrowphase::{NarrowPhase, PairCache};
+
+    /// Overlapping bodies, `bodies_per_cluster` in a row. The rows are far apart such
+    /// that they are never in contact with each other.
+    fn clusters(seed: u64, num_clusters: usize, bodies_per_cluster: usize) -> Vec<RigidBody> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bodies = vec![];
+        for cluster in 0..num_clusters {
+            let origin = [(cluster % 10) as f32 * 100.0, (cluster / 10) as f32 * 100.0];
+            for k in 0..bodies_per_cluster {
+                let body_type = match rng.gen_bool(0.5) {
+                    true => RigidBodyType::Circle { radius: 5.0 },
+                    false => RigidBodyType::Rectangle {
+                        width: 10.0,
+                        height: 10.0,
+                    },
+                };
+                bodies.push(
+                    RigidBodyBuilder::default()
+                        .id(bodies.len())
+                        .position([origin[0] + k as f32 * 8.0, origin[1], 0.0])
+                        .velocity([rng.gen_range(-2..=2) as f32, 0.0, 0.0])
+                        .body_type(body_type)
+                        .build(),
+                );
+            }
+        }
+        bodies
+    }
+
+    fn assert_same_bodies(expected: &[RigidBody], output: &[RigidBody]) {
+        for (e, o) in expected.iter().zip(output.iter()) {
+            assert_eq!(e.position, o.position, "Expected {e} but found {o}");
+            assert_eq!(
+                e.prev_position, o.prev_position,
+                "Expected {e} but found {o}"
+            );
+            assert_eq!(e.velocity, o.velocity, "Expected {e} but found {o}");
+            assert_eq!(
+                e.rotational_velocity, o.rotational_velocity,
+                "Expected {e} but found {o}"
+            );
+        }
+    }
+
+    #[test]
+    fn given_pairs_of_bodies_in_contact_expect_parallel_and_serial_results_to_be_identical() {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let mut serial_bodies = clusters(1, 100, 2);
+        let mut parallel_bodies = serial_bodies.clone();
+        let passes: [Vec<CollisionCandidates>; 4] =
+            SpatialSubdivision::new().collision_detection(serial_bodies.iter());
+
+        let mut num_serial_contacts = 0;
+        let mut num_parallel_contacts = 0;
+        {
+            let mut serial: Vec<&mut RigidBody> = serial_bodies.iter_mut().collect();
+            let mut parallel: Vec<&mut RigidBody> = parallel_bodies.iter_mut().collect();
+            let mut serial_pairs = PairCache::new();
+            let mut parallel_pairs = PairCache::new();
+            for pass in passes.iter() {
+                num_serial_contacts += pass
+                    .iter()
+                    .filter_map(|c| {
+                        narrowphase.collision_detection(&mut serial, c, &mut serial_pairs)
+                    })
+                    .map(|g| g.collisions.len())
+                    .sum::<usize>();
+                num_parallel_contacts += narrowphase
+                    .parallel_collision_detection(&mut parallel, pass, &mut parallel_pairs)
+                    .iter()
+                    .map(|g| g.collisions.len())
+                    .sum::<usize>();
+            }
+        }
+
+        assert!(num_serial_contacts > 0);
+        assert_eq!(num_serial_contacts, num_parallel_contacts);
+        assert_same_bodies(&serial_bodies, &parallel_bodies);
+    }
+
+    #[test]
+    fn given_different_number_of_threads_expect_identical_results() {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let bodies = clusters(2, 100, 3);
+        let passes: [Vec<CollisionCandidates>; 4] =
+            SpatialSubdivision::new().collision_detection(bodies.iter());
+
+        let step = |num_threads: usize| {
+            let mut bodies = bodies.clone();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+                let mut pairs = PairCache::new();
+                for pass in passes.iter() {
+                    narrowphase.parallel_collision_detection(&mut refs, pass, &mut pairs);
+                }
+            });
+            bodies
+        };
+
+        let expected = step(1);
+        assert_same_bodies(&expected, &step(4));
+        assert_same_bodies(&expected, &step(8));
+    }
+
+    #[test]
+    fn given_contact_separated_by_earlier_contact_expect_it_not_to_be_resolved_again() {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let circle = |id: usize, position: [f32; 3]| {
+            RigidBodyBuilder::default()
+                .id(id)
+                .position(position)
+                .body_type(RigidBodyType::Circle { radius: 5.0 })
+                .build()
+        };
+        // Separating the first pair pushes body 1 out of body 2
+        let mut bodies = [
+            circle(0, [0.0, 0.0, 0.0]),
+            circle(1, [2.0, 0.0, 0.0]),
+            circle(2, [3.0, 9.8, 0.0]),
+        ];
+        let candidates = CollisionCandidates::new(vec![0, 1, 2]);
+
+        let mut contacts = {
+            let bodies: Vec<&RigidBody> = bodies.iter().collect();
+            narrowphase.contact_detection(&bodies, &candidates).unwrap()
+        };
+        assert_eq!(2, contacts.collisions.len());
+
+        let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+        narrowphase.resolve_contacts(&mut refs, &mut contacts);
+        assert_eq!(1, contacts.collisions.len());
+        assert_eq!(
+            (0, 1),
+            (
+                contacts.collisions[0].body_i_idx,
+                contacts.collisions[0].body_j_idx
+            )
+        );
+        let positions: Vec<[f32; 3]> = bodies.iter().map(|b| b.position.into()).collect();
+        assert_eq!(
+            vec![[-4.0, 0.0, 0.0], [6.0, 0.0, 0.0], [3.0, 9.8, 0.0]],
+            positions
+        );
+    }
+
+    #[test]
+    fn given_contact_created_by_resolving_earlier_contact_expect_parallel_and_serial_results_to_be_identical(
+    ) {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let circle = |id: usize, position: [f32; 3]| {
+            RigidBodyBuilder::default()
+                .id(id)
+                .position(position)
+                .body_type(RigidBodyType::Circle { radius: 5.0 })
+                .build()
+        };
+        // Separating the first pair pushes body 1 into body 2, which is resolved in
+        // the same step. Body 2 is then pushed into body 4 of the next list, which had
+        // no contact before the step.
+        let mut serial_bodies = vec![
+            circle(0, [0.0, 0.0, 0.0]),
+            circle(1, [2.0, 0.0, 0.0]),
+            circle(2, [12.5, 0.0, 0.0]),
+            circle(3, [40.0, 0.0, 0.0]),
+            circle(4, [23.5, 0.0, 0.0]),
+        ];
+        let mut parallel_bodies = serial_bodies.clone();
+        let candidates = [
+            CollisionCandidates::new(vec![0, 1, 2]),
+            CollisionCandidates::new(vec![2, 3, 4]),
+            CollisionCandidates::new(vec![1, 2]),
+        ];
+
+        let mut serial_pairs = PairCache::new();
+        let mut parallel_pairs = PairCache::new();
+        let (serial_graphs, parallel_graphs) = {
+            let mut serial: Vec<&mut RigidBody> = serial_bodies.iter_mut().collect();
+            let mut parallel: Vec<&mut RigidBody> = parallel_bodies.iter_mut().collect();
+            let serial_graphs: Vec<CollisionGraph> = candidates
+                .iter()
+                .filter_map(|c| narrowphase.collision_detection(&mut serial, c, &mut serial_pairs))
+                .collect();
+            let parallel_graphs = narrowphase.parallel_collision_detection(
+                &mut parallel,
+                &candidates,
+                &mut parallel_pairs,
+            );
+            (serial_graphs, parallel_graphs)
+        };
+
+        assert_eq!(2, serial_graphs.len());
+        assert_eq!(2, serial_graphs[0].collisions.len());
+        assert_eq!(1, serial_graphs[1].collisions.len());
+        assert_eq!(serial_graphs, parallel_graphs);
+        assert_same_bodies(&serial_bodies, &parallel_bodies);
+        assert_eq!(serial_pairs.len(), parallel_pairs.len());
+    }
+
+    /// Circles packed densely enough that neighbouring 3x3 neighbourhoods of the
+    /// `BlockMap` share many pairs
+    fn dense_circles(seed: u64) -> Vec<RigidBody> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..300)
+            .map(|i| {
+                RigidBodyBuilder::default()
+                    .id(i)
+                    .position([rng.gen_range(20.0..180.0), rng.gen_range(20.0..180.0), 0.0])
+                    .body_type(RigidBodyType::Circle { radius: 5.0 })
+                    .build()
+            })
+            .collect()
+    }
+
+    fn assert_no_duplicate_nodes(graphs: &[CollisionGraph]) {
+        let mut pairs = PairCache::new();
+        for node in graphs.iter().flat_map(|g| g.collisions.iter()) {
+            assert!(
+                pairs.insert(node.body_i_idx, node.body_j_idx),
+                "Expected pair ({}, {}) to be handled once",
+                node.body_i_idx,
+                node.body_j_idx
+            );
+        }
+    }
+
+    #[test]
+    fn given_candidates_from_blockmap_expect_pairs_in_several_lists() {
+        let bodies = dense_circles(1);
+        let candidates = BlockMap::new(200.0).collision_detection(bodies.iter());
+        let mut pairs = PairCache::new();
+        let num_duplicates = candidates
+            .iter()
+            .flat_map(|c| {
+                let indices = &c.indices;
+                (0..indices.len()).flat_map(move |i| {
+                    ((i + 1)..indices.len()).map(move |j| (indices[i], indices[j]))
+                })
+            })
+            .filter(|(i, j)| !pairs.insert(*i, *j))
+            .count();
+        assert!(num_duplicates > 0);
+    }
+
+    #[test]
+    fn given_candidates_from_blockmap_expect_no_duplicate_collision_graph_nodes() {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let mut bodies = dense_circles(1);
+        let candidates = BlockMap::new(200.0).collision_detection(bodies.iter());
+
+        let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+        let mut pairs = PairCache::new();
+        let graphs: Vec<CollisionGraph> = candidates
+            .iter()
+            .filter_map(|c| narrowphase.collision_detection(&mut refs, c, &mut pairs))
+            .collect();
+        assert!(!graphs.is_empty());
+        assert_no_duplicate_nodes(&graphs);
+    }
+
+    #[test]
+    fn given_candidates_from_blockmap_when_detecting_in_parallel_expect_no_duplicate_collision_graph_nodes(
+    ) {
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let mut bodies = dense_circles(2);
+        let candidates = BlockMap::new(200.0).collision_detection(bodies.iter());
+
+        let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+        let mut pairs = PairCache::new();
+        let graphs = narrowphase.parallel_collision_detection(&mut refs, &candidates, &mut pairs);
+        assert!(!graphs.is_empty());
+        assert_no_duplicate_nodes(&graphs);
+    }
+
+    #[test]
+    fn given_registered_custom_shape_pair_expect_contact_with_normal_from_body_i_to_body_j() {
+        let mut narrowphase = Naive::new(SimpleCollisionSolver::new());
+        // Custom shapes of kind 1 are pushed out of circles along the x-axis
+        narrowphase.dispatcher_mut().register(
+            ShapeKind::Custom(1),
+            ShapeKind::Circle,
+            |custom, circle| {
+                let depth = 6.0 - (custom.position.x - circle.position.x).abs();
+                (depth > 0.0).then(|| {
+                    let normal = [(circle.position.x - custom.position.x).signum(), 0.0, 0.0];
+                    CollisionInformation::new(
+                        normal,
+                        ContactManifold::single(custom.position.into(), depth),
+                    )
+                })
+            },
+            |custom, _circle, info| custom.position.x -= info.normal[0] * info.penetration_depth,
+        );
+        let mut bodies = [
+            RigidBodyBuilder::default()
+                .id(0)
+                .position([0.0, 0.0, 0.0])
+                .body_type(RigidBodyType::Circle { radius: 5.0 })
+                .build(),
+            RigidBodyBuilder::default()
+                .id(1)
+                .position([4.0, 0.0, 0.0])
+                .body_type(RigidBodyType::Custom {
+                    kind: 1,
+                    radius: 1.0,
+                })
+                .build(),
+        ];
+        let candidates = CollisionCandidates::new(vec![0, 1]);
+
+        let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+        let graph = narrowphase
+            .collision_detection(&mut refs, &candidates, &mut PairCache::new())
+            .unwrap();
+
+        assert_eq!(1, graph.collisions.len());
+        assert_eq!([1.0, 0.0, 0.0], graph.collisions[0].info.normal);
+        assert_eq!(6.0, bodies[1].position.x);
+        assert_eq!(0.0, bodies[0].position.x);
+    }
 }
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+/// The pairs of bodies handled during a step. Broadphases may emit the same pair in
+/// several candidate lists, the cache ensures the narrowphase tests and resolves each
+/// pair once. Clear it at the start of every step.
+#[derive(Debug, Default)]
+pub struct PairCache {
+    pairs: HashSet<(usize, usize)>,
+}
+
+impl PairCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.pairs.clear();
+    }
+
+    /// Returns true if the pair was not handled before, in either order
+    pub fn insert(&mut self, body_i_idx: usize, body_j_idx: usize) -> bool {
+        self.pairs
+            .insert((body_i_idx.min(body_j_idx), body_i_idx.max(body_j_idx)))
+    }
+
+    pub fn contains(&self, body_i_idx: usize, body_j_idx: usize) -> bool {
+        self.pairs
+            .contains(&(body_i_idx.min(body_j_idx), body_i_idx.max(body_j_idx)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod insert {
+        use super::super::PairCache;
+        macro_rules! insert_tests {
+            ($($name:ident: $pairs: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let pairs: Vec<(usize, usize)> = $pairs;
+                        let expected: Vec<bool> = $expected;
+                        let mut cache = PairCache::new();
+                        let output: Vec<bool> = pairs.iter().map(|(i, j)| cache.insert(*i, *j)).collect();
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        insert_tests! {
+            given_distinct_pairs_expect_all_to_be_new:
+                vec![(0, 1), (1, 2), (0, 2)], vec![true, true, true]
+            given_same_pair_twice_expect_second_to_be_handled:
+                vec![(0, 1), (0, 1)], vec![true, false]
+            given_same_pair_in_reverse_order_expect_second_to_be_handled:
+                vec![(3, 1), (1, 3)], vec![true, false]
+        }
+    }
+
+    #[test]
+    fn given_cleared_cache_expect_pairs_to_be_new_again() {
+        use super::PairCache;
+        let mut cache = PairCache::new();
+        cache.insert(0, 1);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.insert(1, 0));
+    }
+}
@@ -0,0 +1,424 @@
+mod query_grid;
+mod ray;
+mod shape_cast;
+
+pub use query_grid::QueryGrid;
+pub use ray::{CastHit, Ray};
+pub use shape_cast::CastShape;
+
+use super::collision::{Aabb, CollisionFilter, RigidBody, RigidBodyType};
+
+/// Scene queries answered from the cell layout of a broadphase. Implementors keep a
+/// grid that is refilled by every call to `BroadPhase::collision_detection` and
+/// `refresh_query_grid`, the candidate selection and the exact tests are shared.
+///
+/// The queries do not rebuild the grid. Candidates are found where the bodies were
+/// during the last refill, the exact tests use the bodies passed to the query. Call
+/// `refresh_query_grid` before querying bodies that moved since the last step, e.g.
+/// when picking after the integrator ran, and before the first step, until then the
+/// grid is empty and the queries find nothing.
+///
+/// Bodies are passed the same way as to the last refill and the returned `body_idx`
+/// is the index of the body in that iterator. Bodies of type `RigidBodyType::Unknown`
+/// and bodies whose layer is not in the mask of `filter` are never reported.
+pub trait SpatialQuery {
+    /// The grid filled by the last call to `collision_detection` or `refresh_query_grid`
+    fn query_grid(&self) -> &QueryGrid;
+
+    /// Refills the grid with the bodies where they are now, keeping the cell layout of
+    /// the last `collision_detection`
+    fn refresh_query_grid<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>;
+
+    /// Returns the indices of all bodies containing the point
+    fn query_point<'a, I>(
+        &self,
+        bodies: I,
+        point: (f32, f32),
+        filter: &CollisionFilter,
+    ) -> Vec<usize>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let candidates = self.query_grid().candidates_in_aabb(&Aabb::from_center(
+            [point.0, point.1, 0.0],
+            0.0,
+            0.0,
+        ));
+        candidate_bodies(bodies, candidates)
+            .filter(|(_, body)| is_queryable(body, filter))
+            .filter(|(_, body)| body.click_inside(point))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the indices of all bodies overlapping the axis aligned rectangle
+    fn query_aabb<'a, I>(&self, bodies: I, aabb: &Aabb, filter: &CollisionFilter) -> Vec<usize>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let candidates = self.query_grid().candidates_in_aabb(aabb);
+        candidate_bodies(bodies, candidates)
+            .filter(|(_, body)| is_queryable(body, filter))
+            .filter(|(_, body)| body.overlaps_aabb(aabb))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the first body hit by the ray
+    fn ray_cast<'a, I>(&self, bodies: I, ray: &Ray, filter: &CollisionFilter) -> Option<CastHit>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        self.ray_cast_all(bodies, ray, filter).into_iter().next()
+    }
+
+    /// Returns every body hit by the ray, ordered by distance from the ray origin
+    fn ray_cast_all<'a, I>(&self, bodies: I, ray: &Ray, filter: &CollisionFilter) -> Vec<CastHit>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let candidates = self
+            .query_grid()
+            .candidates_along_segment(ray.origin, ray.end());
+        let mut hits: Vec<CastHit> = candidate_bodies(bodies, candidates)
+            .filter(|(_, body)| is_queryable(body, filter))
+            .filter_map(|(idx, body)| {
+                let (t, normal) = body.ray_intersection(&ray.origin, &ray.direction)?;
+                if t > ray.max_distance {
+                    return None;
+                }
+                Some(CastHit {
+                    body_idx: idx,
+                    point: ray.point_at(t),
+                    normal,
+                    fraction: fraction(t, ray),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.fraction.total_cmp(&b.fraction));
+        hits
+    }
+
+    /// Sweeps `shape` along the ray and returns the first body it touches
+    fn shape_cast<'a, I>(
+        &self,
+        bodies: I,
+        shape: &CastShape,
+        ray: &Ray,
+        filter: &CollisionFilter,
+    ) -> Option<CastHit>
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let swept_area =
+            Aabb::from_points(&[ray.origin, ray.end()]).expand(shape.bounding_radius());
+        let candidates = self.query_grid().candidates_in_aabb(&swept_area);
+        candidate_bodies(bodies, candidates)
+            .filter(|(_, body)| is_queryable(body, filter))
+            .filter_map(|(idx, body)| {
+                let (t, normal, point) = shape.sweep(ray, body)?;
+                Some(CastHit {
+                    body_idx: idx,
+                    point,
+                    normal,
+                    fraction: fraction(t, ray),
+                })
+            })
+            .min_by(|a, b| a.fraction.total_cmp(&b.fraction))
+    }
+}
+
+/// Pairs the sorted candidate indices with their bodies. The iterator is only
+/// advanced to the candidates, such that the bodies never have to be collected.
+fn candidate_bodies<'a, I>(
+    mut bodies: I,
+    candidates: Vec<usize>,
+) -> impl Iterator<Item = (usize, &'a RigidBody)>
+where
+    I: Iterator<Item = &'a RigidBody>,
+{
+    let mut next_idx = 0;
+    candidates.into_iter().filter_map(move |idx| {
+        let body = bodies.nth(idx - next_idx)?;
+        next_idx = idx + 1;
+        Some((idx, body))
+    })
+}
+
+fn is_queryable(body: &RigidBody, filter: &CollisionFilter) -> bool {
+    body.body_type != RigidBodyType::Unknown && filter.accepts(&body.collision_filter)
+}
+
+fn fraction(distance: f32, ray: &Ray) -> f32 {
+    if ray.max_distance > 0.0 {
+        distance / ray.max_distance
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::physics_engine::broadphase::{BroadPhase, SweepAndPrune};
+    use crate::engine::physics_engine::collision::{
+        Aabb, CollisionFilter, RigidBody, RigidBodyBuilder, RigidBodyType,
+    };
+    use crate::engine::physics_engine::query::SpatialQuery;
+
+    fn scene() -> Vec<RigidBody> {
+        vec![
+            RigidBodyBuilder::default()
+                .id(0)
+                .position([100., 100., 0.])
+                .body_type(RigidBodyType::Circle { radius: 10.0 })
+                .build(),
+            RigidBodyBuilder::default()
+                .id(1)
+                .position([200., 100., 0.])
+                .body_type(RigidBodyType::Rectangle {
+                    width: 20.0,
+                    height: 20.0,
+                })
+                .collision_filter(CollisionFilter::new(0b10, CollisionFilter::ALL_LAYERS))
+                .build(),
+            RigidBodyBuilder::default()
+                .id(2)
+                .position([300., 100., 0.])
+                .body_type(RigidBodyType::Circle { radius: 10.0 })
+                .build(),
+            RigidBodyBuilder::default()
+                .id(3)
+                .position([100., 300., 0.])
+                .body_type(RigidBodyType::Circle { radius: 10.0 })
+                .build(),
+        ]
+    }
+
+    #[test]
+    fn given_unknown_body_expect_it_to_never_be_reported() {
+        let mut bodies = scene();
+        bodies.push(
+            RigidBodyBuilder::default()
+                .id(4)
+                .position([200., 102., 0.])
+                .body_type(RigidBodyType::Unknown)
+                .build(),
+        );
+        let mut broadphase = SweepAndPrune::default();
+        broadphase.collision_detection(bodies.iter());
+
+        let filter = CollisionFilter::default();
+        let aabb = Aabb::new([0., 0., 0.], [1000., 1000., 0.]);
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            broadphase.query_aabb(bodies.iter(), &aabb, &filter)
+        );
+    }
+
+    #[test]
+    fn given_bodies_moved_after_collision_detection_expect_exact_test_on_new_position() {
+        let mut bodies = scene();
+        let mut broadphase = SweepAndPrune::default();
+        broadphase.collision_detection(bodies.iter());
+        bodies[0].position.x += 5.0;
+
+        let filter = CollisionFilter::default();
+        assert_eq!(
+            Vec::<usize>::new(),
+            broadphase.query_point(bodies.iter(), (92., 100.), &filter)
+        );
+        assert_eq!(
+            vec![0],
+            broadphase.query_point(bodies.iter(), (112., 100.), &filter)
+        );
+    }
+
+    macro_rules! spatial_query_tests {
+        ($($mod_name:ident: $broadphase: expr)*) => {
+            $(
+                mod $mod_name {
+                    use super::scene;
+                    use crate::engine::physics_engine::broadphase::BroadPhase;
+                    use crate::engine::physics_engine::collision::{
+                        Aabb, CollisionFilter, RigidBody, RigidBodyBuilder, RigidBodyType,
+                    };
+                    use crate::engine::physics_engine::query::{CastShape, Ray, SpatialQuery};
+
+                    /// Queries are answered from the grid of the last collision detection
+                    fn broadphase(bodies: &[RigidBody]) -> impl SpatialQuery {
+                        let mut broadphase = $broadphase;
+                        broadphase.collision_detection(bodies.iter());
+                        broadphase
+                    }
+
+                    #[test]
+                    fn given_no_collision_detection_expect_bodies_found_after_refresh() {
+                        let bodies = scene();
+                        let filter = CollisionFilter::default();
+                        let mut broadphase = $broadphase;
+                        assert_eq!(Vec::<usize>::new(), broadphase.query_point(bodies.iter(), (205., 95.), &filter));
+
+                        broadphase.refresh_query_grid(bodies.iter());
+                        assert_eq!(vec![1], broadphase.query_point(bodies.iter(), (205., 95.), &filter));
+                        let ray = Ray::new([0., 100., 0.], [1., 0., 0.], 1000.0);
+                        assert_eq!(Some(0), broadphase.ray_cast(bodies.iter(), &ray, &filter).map(|h| h.body_idx));
+                    }
+
+                    #[test]
+                    fn given_body_moved_since_collision_detection_expect_it_found_after_refresh() {
+                        let mut bodies = scene();
+                        let filter = CollisionFilter::default();
+                        let mut broadphase = $broadphase;
+                        broadphase.collision_detection(bodies.iter());
+                        bodies[0].position = [500., 500., 0.].into();
+                        bodies[0].prev_position = bodies[0].position;
+
+                        broadphase.refresh_query_grid(bodies.iter());
+                        assert_eq!(vec![0], broadphase.query_point(bodies.iter(), (500., 500.), &filter));
+                        assert_eq!(Vec::<usize>::new(), broadphase.query_point(bodies.iter(), (100., 100.), &filter));
+                    }
+
+                    #[test]
+                    fn given_point_inside_body_expect_only_that_body() {
+                        let bodies = scene();
+                        let filter = CollisionFilter::default();
+                        assert_eq!(vec![1], broadphase(&bodies).query_point(bodies.iter(), (205., 95.), &filter));
+                        assert_eq!(vec![3], broadphase(&bodies).query_point(bodies.iter(), (100., 308., ), &filter));
+                    }
+
+                    #[test]
+                    fn given_point_in_empty_space_expect_no_bodies() {
+                        let bodies = scene();
+                        let filter = CollisionFilter::default();
+                        let output = broadphase(&bodies).query_point(bodies.iter(), (150., 150.), &filter);
+                        assert_eq!(Vec::<usize>::new(), output);
+                    }
+
+                    #[test]
+                    fn given_point_on_corner_of_circle_bounding_box_expect_no_bodies() {
+                        let bodies = scene();
+                        let filter = CollisionFilter::default();
+                        let output = broadphase(&bodies).query_point(bodies.iter(), (109., 109.), &filter);
+                        assert_eq!(Vec::<usize>::new(), output);
+                    }
+
+                    #[test]
+                    fn given_aabb_over_top_row_expect_row_bodies() {
+                        let bodies = scene();
+                        let aabb = Aabb::new([95., 80., 0.], [305., 120., 0.]);
+                        let output = broadphase(&bodies).query_aabb(bodies.iter(), &aabb, &CollisionFilter::default());
+                        assert_eq!(vec![0, 1, 2], output);
+                    }
+
+                    #[test]
+                    fn given_aabb_and_filter_expect_filtered_bodies_excluded() {
+                        let bodies = scene();
+                        let aabb = Aabb::new([0., 0., 0.], [1000., 1000., 0.]);
+                        let filter = CollisionFilter::new(0b1, 0b10);
+                        let output = broadphase(&bodies).query_aabb(bodies.iter(), &aabb, &filter);
+                        assert_eq!(vec![1], output);
+                    }
+
+                    #[test]
+                    fn given_ray_through_row_of_bodies_expect_all_hits_ordered_by_distance() {
+                        let bodies = scene();
+                        let ray = Ray::new([0., 100., 0.], [1., 0., 0.], 1000.0);
+                        let hits = broadphase(&bodies).ray_cast_all(bodies.iter(), &ray, &CollisionFilter::default());
+                        let hit_ids: Vec<usize> = hits.iter().map(|h| h.body_idx).collect();
+                        assert_eq!(vec![0, 1, 2], hit_ids);
+                        assert_eq!([90., 100., 0.], hits[0].point);
+                        assert_eq!([-1., 0., 0.], hits[0].normal);
+                        assert_eq!(0.09, hits[0].fraction);
+                    }
+
+                    #[test]
+                    fn given_ray_through_row_of_bodies_expect_first_hit_to_be_closest() {
+                        let bodies = scene();
+                        let ray = Ray::new([400., 100., 0.], [-1., 0., 0.], 1000.0);
+                        let hit = broadphase(&bodies).ray_cast(bodies.iter(), &ray, &CollisionFilter::default());
+                        assert_eq!(Some(2), hit.map(|h| h.body_idx));
+                    }
+
+                    #[test]
+                    fn given_filter_excludes_layer_expect_body_on_layer_to_be_skipped() {
+                        let bodies = scene();
+                        let ray = Ray::new([0., 100., 0.], [1., 0., 0.], 1000.0);
+                        let filter = CollisionFilter::new(0b1, 0b1);
+                        let hits = broadphase(&bodies).ray_cast_all(bodies.iter(), &ray, &filter);
+                        let hit_ids: Vec<usize> = hits.iter().map(|h| h.body_idx).collect();
+                        assert_eq!(vec![0, 2], hit_ids);
+                    }
+
+                    #[test]
+                    fn given_ray_too_short_expect_no_hit() {
+                        let bodies = scene();
+                        let ray = Ray::new([0., 100., 0.], [1., 0., 0.], 50.0);
+                        let hit = broadphase(&bodies).ray_cast(bodies.iter(), &ray, &CollisionFilter::default());
+                        assert_eq!(None, hit);
+                    }
+
+                    #[test]
+                    fn given_circle_cast_between_rows_expect_hit_when_radius_reaches_body() {
+                        let bodies = scene();
+                        let ray = Ray::new([0., 125., 0.], [1., 0., 0.], 1000.0);
+                        let thin = CastShape::Circle { radius: 5.0 };
+                        let thick = CastShape::Circle { radius: 20.0 };
+                        let filter = CollisionFilter::default();
+                        assert_eq!(None, broadphase(&bodies).shape_cast(bodies.iter(), &thin, &ray, &filter));
+                        let hit = broadphase(&bodies).shape_cast(bodies.iter(), &thick, &ray, &filter);
+                        assert_eq!(Some(0), hit.map(|h| h.body_idx));
+                    }
+
+                    #[test]
+                    fn given_custom_body_expect_it_to_be_found_by_its_bounding_radius() {
+                        let mut bodies = scene();
+                        bodies.push(
+                            RigidBodyBuilder::default()
+                                .id(4)
+                                .position([400., 100., 0.])
+                                .body_type(RigidBodyType::Custom { kind: 1, radius: 10.0 })
+                                .build(),
+                        );
+                        let filter = CollisionFilter::default();
+                        let ray = Ray::new([500., 100., 0.], [-1., 0., 0.], 1000.0);
+                        let hit = broadphase(&bodies).ray_cast(bodies.iter(), &ray, &filter).unwrap();
+                        assert_eq!(4, hit.body_idx);
+                        assert_eq!([410., 100., 0.], hit.point);
+                        assert_eq!([1., 0., 0.], hit.normal);
+
+                        assert_eq!(vec![4], broadphase(&bodies).query_point(bodies.iter(), (405., 95.), &filter));
+                        let aabb = Aabb::new([315., 80., 0.], [395., 120., 0.]);
+                        assert_eq!(vec![4], broadphase(&bodies).query_aabb(bodies.iter(), &aabb, &filter));
+                        let shape = CastShape::Circle { radius: 20.0 };
+                        let ray = Ray::new([500., 125., 0.], [-1., 0., 0.], 1000.0);
+                        let hit = broadphase(&bodies).shape_cast(bodies.iter(), &shape, &ray, &filter);
+                        assert_eq!(Some(4), hit.map(|h| h.body_idx));
+                    }
+
+                    #[test]
+                    fn given_box_cast_down_column_expect_hit_on_closest_body() {
+                        let bodies = scene();
+                        let ray = Ray::new([100., 500., 0.], [0., -1., 0.], 1000.0);
+                        let shape = CastShape::Box { width: 10.0, height: 10.0, rotation: 0.0 };
+                        let hit = broadphase(&bodies)
+                            .shape_cast(bodies.iter(), &shape, &ray, &CollisionFilter::default())
+                            .unwrap();
+                        assert_eq!(3, hit.body_idx);
+                        assert_eq!([100., 310., 0.], hit.point);
+                    }
+                }
+            )*
+        }
+    }
+
+    spatial_query_tests! {
+        blockmap: crate::engine::physics_engine::broadphase::BlockMap::new(1000.0)
+        incremental_blockmap: crate::engine::physics_engine::broadphase::IncrementalBlockMap::new()
+        spatial_subdivision: crate::engine::physics_engine::broadphase::SpatialSubdivision::new()
+        sparse_spatial_subdivision: crate::engine::physics_engine::broadphase::SparseSpatialSubdivision::new()
+        aabb_tree: crate::engine::physics_engine::broadphase::AabbTree::new()
+        sweep_and_prune: crate::engine::physics_engine::broadphase::SweepAndPrune::default()
+    }
+}
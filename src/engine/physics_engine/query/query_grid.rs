@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use crate::engine::physics_engine::collision::{Aabb, RigidBody, RigidBodyType};
+
+/// Sparse uniform grid mapping cells to the indices of the bodies overlapping
+/// them. Kept by the broadphases in their own cell layout and refilled on every
+/// `collision_detection` or `refresh_query_grid`, such that queries only have to
+/// look at the bodies sharing a cell with the query.
+pub struct QueryGrid {
+    origin: [f32; 3],
+    cell_size: (f32, f32),
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    bounds: Option<Aabb>,
+}
+
+impl QueryGrid {
+    /// Creates an empty grid of square cells where cell (0,0) starts at `origin`
+    pub fn new(origin: [f32; 3], cell_size: f32) -> Self {
+        Self::with_cell_size(origin, (cell_size, cell_size))
+    }
+
+    /// Creates an empty grid of cells with the given width and height where cell
+    /// (0,0) starts at `origin`
+    pub fn with_cell_size(origin: [f32; 3], cell_size: (f32, f32)) -> Self {
+        debug_assert!(
+            cell_size.0 > 0.0 && cell_size.1 > 0.0,
+            "Expected cell size to be larger than 0, found {cell_size:?}"
+        );
+        Self {
+            origin,
+            cell_size,
+            cells: HashMap::new(),
+            bounds: None,
+        }
+    }
+
+    /// Removes all bodies and moves cell (0,0) to `origin`. The cells occupied before
+    /// the call keep their memory, cells that were already empty are dropped.
+    pub fn reset(&mut self, origin: [f32; 3], cell_size: f32) {
+        self.reset_with_cell_size(origin, (cell_size, cell_size));
+    }
+
+    /// Same as `reset` for cells with the given width and height
+    pub fn reset_with_cell_size(&mut self, origin: [f32; 3], cell_size: (f32, f32)) {
+        debug_assert!(
+            cell_size.0 > 0.0 && cell_size.1 > 0.0,
+            "Expected cell size to be larger than 0, found {cell_size:?}"
+        );
+        self.origin = origin;
+        self.cell_size = cell_size;
+        self.cells.retain(|_, bodies| {
+            let was_occupied = !bodies.is_empty();
+            bodies.clear();
+            was_occupied
+        });
+        self.bounds = None;
+    }
+
+    /// Removes all bodies and inserts the bounding boxes of `bodies`, skipping bodies
+    /// of unknown type. The cell layout is kept, a grid that held no bodies is given
+    /// cells the size of the average body.
+    pub fn refill<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let aabbs: Vec<(usize, Aabb)> = bodies
+            .enumerate()
+            .filter(|(_, body)| body.body_type != RigidBodyType::Unknown)
+            .map(|(i, body)| (i, body.aabb()))
+            .collect();
+        match self.bounds {
+            Some(_) => self.reset_with_cell_size(self.origin, self.cell_size),
+            None => self.reset(
+                [0.0, 0.0, 0.0],
+                Self::average_cell_size(aabbs.iter().map(|(_, aabb)| *aabb)),
+            ),
+        }
+        for (i, aabb) in aabbs.iter() {
+            self.insert_aabb(*i, aabb);
+        }
+    }
+
+    /// Size of square cells fitting the average of the boxes, at least 1. The average
+    /// keeps the cells small when a few boxes are huge.
+    pub fn average_cell_size<I>(aabbs: I) -> f32
+    where
+        I: Iterator<Item = Aabb>,
+    {
+        let (total_extent, num_aabbs) = aabbs.fold((0.0, 0), |(extent, n), aabb| {
+            (extent + aabb.width().max(aabb.height()), n + 1)
+        });
+        (total_extent / num_aabbs.max(1) as f32).max(1.0)
+    }
+
+    pub fn origin(&self) -> [f32; 3] {
+        self.origin
+    }
+
+    /// The width and height of a cell
+    pub fn cell_size(&self) -> (f32, f32) {
+        self.cell_size
+    }
+
+    pub fn cell_of(&self, point: (f32, f32)) -> (i64, i64) {
+        (
+            ((point.0 - self.origin[0]) / self.cell_size.0).floor() as i64,
+            ((point.1 - self.origin[1]) / self.cell_size.1).floor() as i64,
+        )
+    }
+
+    /// Returns the bounds of every cell holding at least one body, in no particular order
+    pub fn occupied_cells(&self) -> impl Iterator<Item = Aabb> + '_ {
+        self.cells
+            .iter()
+            .filter(|(_, bodies)| !bodies.is_empty())
+            .map(|((x, y), _)| {
+                let (width, height) = self.cell_size;
+                let min = [
+                    self.origin[0] + *x as f32 * width,
+                    self.origin[1] + *y as f32 * height,
+                    0.0,
+                ];
+                Aabb::new(min, [min[0] + width, min[1] + height, 0.0])
+            })
+    }
+
+    /// Inserts a body into the given cells. The cells are expected to cover the body,
+    /// `aabb` is its bounding box.
+    pub fn insert<I>(&mut self, body_idx: usize, aabb: &Aabb, cells: I)
+    where
+        I: Iterator<Item = (i64, i64)>,
+    {
+        for cell in cells {
+            self.cells.entry(cell).or_default().push(body_idx);
+        }
+        self.bounds = Some(match self.bounds {
+            Some(bounds) => bounds.union(aabb),
+            None => *aabb,
+        });
+    }
+
+    /// Inserts a body into every cell its bounding box overlaps
+    pub fn insert_aabb(&mut self, body_idx: usize, aabb: &Aabb) {
+        let cells = Self::cells_in(self.cell_range(aabb));
+        self.insert(body_idx, aabb, cells);
+    }
+
+    /// Removes a body from the given cells. The cells keep their memory and the bounds
+    /// are not shrunk, see `set_bounds` and `drop_empty_cells`.
+    pub fn remove<I>(&mut self, body_idx: usize, cells: I)
+    where
+        I: Iterator<Item = (i64, i64)>,
+    {
+        for cell in cells {
+            if let Some(bodies) = self.cells.get_mut(&cell) {
+                if let Some(pos) = bodies.iter().position(|b| *b == body_idx) {
+                    bodies.swap_remove(pos);
+                }
+            }
+        }
+    }
+
+    /// Replaces the bounds the queries are clipped against. Expected to cover every
+    /// body in the grid, used to shrink the bounds after bodies were moved or removed.
+    pub fn set_bounds(&mut self, bounds: Option<Aabb>) {
+        self.bounds = bounds;
+    }
+
+    /// Number of cells in the grid, including empty cells that kept their memory
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn drop_empty_cells(&mut self) {
+        self.cells.retain(|_, bodies| !bodies.is_empty());
+    }
+
+    /// The first and last cell overlapped by the box
+    pub fn cell_range(&self, aabb: &Aabb) -> [(i64, i64); 2] {
+        [
+            self.cell_of((aabb.min.x, aabb.min.y)),
+            self.cell_of((aabb.max.x, aabb.max.y)),
+        ]
+    }
+
+    /// The cells between the first and last cell of a range
+    pub fn cells_in(range: [(i64, i64); 2]) -> impl Iterator<Item = (i64, i64)> {
+        let [(min_x, min_y), (max_x, max_y)] = range;
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+
+    /// Returns the sorted and deduplicated indices of all bodies sharing a cell with
+    /// `aabb`. The box is clipped to the bounds of the inserted bodies first, such that
+    /// boxes of any size, including infinite ones, only visit the cells of the grid.
+    pub fn candidates_in_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return vec![],
+        };
+        let min = (aabb.min.x.max(bounds.min.x), aabb.min.y.max(bounds.min.y));
+        let max = (aabb.max.x.min(bounds.max.x), aabb.max.y.min(bounds.max.y));
+        if !(min.0 <= max.0 && min.1 <= max.1) {
+            return vec![];
+        }
+
+        let mut candidates = vec![];
+        let (min_x, min_y) = self.cell_of(min);
+        let (max_x, max_y) = self.cell_of(max);
+        let num_query_cells = max_x
+            .saturating_sub(min_x)
+            .saturating_add(1)
+            .saturating_mul(max_y.saturating_sub(min_y).saturating_add(1));
+        if num_query_cells as usize > self.cells.len() {
+            // Cheaper to visit the occupied cells than every cell inside the box
+            self.cells
+                .iter()
+                .filter(|((x, y), _)| min_x <= *x && *x <= max_x && min_y <= *y && *y <= max_y)
+                .for_each(|(_, bodies)| candidates.extend_from_slice(bodies));
+        } else {
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    if let Some(bodies) = self.cells.get(&(x, y)) {
+                        candidates.extend_from_slice(bodies);
+                    }
+                }
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    /// Returns the sorted and deduplicated indices of all bodies sharing a cell with
+    /// the segment from `start` to `end`. The cells are walked with a DDA traversal
+    /// after the segment has been clipped to the bounds of the inserted bodies.
+    pub fn candidates_along_segment(&self, start: [f32; 3], end: [f32; 3]) -> Vec<usize> {
+        let (start, end) = match self.clip_segment(start, end) {
+            Some(segment) => segment,
+            None => return vec![],
+        };
+
+        let (width, height) = self.cell_size;
+        let x0 = (start[0] - self.origin[0]) / width;
+        let y0 = (start[1] - self.origin[1]) / height;
+        let x1 = (end[0] - self.origin[0]) / width;
+        let y1 = (end[1] - self.origin[1]) / height;
+        let (dx, dy) = (x1 - x0, y1 - y0);
+
+        let (mut cell_x, mut cell_y) = (x0.floor() as i64, y0.floor() as i64);
+        let (end_x, end_y) = (x1.floor() as i64, y1.floor() as i64);
+        let step_x = if dx > 0.0 { 1 } else { -1 };
+        let step_y = if dy > 0.0 { 1 } else { -1 };
+        let t_delta_x = if dx != 0.0 {
+            (1.0 / dx).abs()
+        } else {
+            f32::MAX
+        };
+        let t_delta_y = if dy != 0.0 {
+            (1.0 / dy).abs()
+        } else {
+            f32::MAX
+        };
+        let mut t_max_x = match dx {
+            dx if dx > 0.0 => (cell_x as f32 + 1.0 - x0) / dx,
+            dx if dx < 0.0 => (x0 - cell_x as f32) / -dx,
+            _ => f32::MAX,
+        };
+        let mut t_max_y = match dy {
+            dy if dy > 0.0 => (cell_y as f32 + 1.0 - y0) / dy,
+            dy if dy < 0.0 => (y0 - cell_y as f32) / -dy,
+            _ => f32::MAX,
+        };
+
+        let mut candidates = vec![];
+        let num_steps = (end_x - cell_x).abs() + (end_y - cell_y).abs();
+        for _ in 0..=num_steps {
+            if let Some(bodies) = self.cells.get(&(cell_x, cell_y)) {
+                candidates.extend_from_slice(bodies);
+            }
+            if t_max_x < t_max_y {
+                cell_x += step_x;
+                t_max_x += t_delta_x;
+            } else {
+                cell_y += step_y;
+                t_max_y += t_delta_y;
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    /// Clips the segment against the bounds of all inserted bodies
+    fn clip_segment(&self, start: [f32; 3], end: [f32; 3]) -> Option<([f32; 3], [f32; 3])> {
+        let bounds = self.bounds?;
+        let direction = [end[0] - start[0], end[1] - start[1]];
+        let (mut t_enter, mut t_exit) = (0.0_f32, 1.0_f32);
+        let bounds_min = [bounds.min.x, bounds.min.y];
+        let bounds_max = [bounds.max.x, bounds.max.y];
+        for axis in 0..2 {
+            if direction[axis] == 0.0 {
+                if start[axis] < bounds_min[axis] || start[axis] > bounds_max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (bounds_min[axis] - start[axis]) / direction[axis];
+            let t1 = (bounds_max[axis] - start[axis]) / direction[axis];
+            t_enter = t_enter.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+        if t_enter > t_exit {
+            return None;
+        }
+        let point_at = |t: f32| {
+            [
+                start[0] + direction[0] * t,
+                start[1] + direction[1] * t,
+                0.0,
+            ]
+        };
+        Some((point_at(t_enter), point_at(t_exit)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryGrid;
+    use crate::engine::physics_engine::collision::Aabb;
+
+    #[test]
+    fn given_bodies_sharing_a_cell_expect_each_occupied_cell_once() {
+        let mut grid = QueryGrid::new([-10., 0., 0.], 10.0);
+        grid.insert_aabb(0, &Aabb::from_center([-5., 5., 0.], 2., 2.));
+        grid.insert_aabb(1, &Aabb::from_center([-4., 4., 0.], 2., 2.));
+        grid.insert_aabb(2, &Aabb::from_center([5., 5., 0.], 2., 2.));
+
+        let mut cells: Vec<Aabb> = grid.occupied_cells().collect();
+        cells.sort_by(|a, b| a.min.x.total_cmp(&b.min.x));
+        assert_eq!(
+            vec![
+                Aabb::new([-10., 0., 0.], [0., 10., 0.]),
+                Aabb::new([0., 0., 0.], [10., 10., 0.])
+            ],
+            cells
+        );
+    }
+
+    #[test]
+    fn given_reset_grid_expect_only_bodies_inserted_after_reset() {
+        let mut grid = QueryGrid::new([0., 0., 0.], 10.0);
+        grid.insert_aabb(0, &Aabb::from_center([5., 5., 0.], 2., 2.));
+        grid.insert_aabb(1, &Aabb::from_center([45., 5., 0.], 2., 2.));
+
+        grid.reset([0., 0., 0.], 20.0);
+        grid.insert_aabb(1, &Aabb::from_center([45., 5., 0.], 2., 2.));
+
+        let everything = Aabb::new([-100., -100., 0.], [100., 100., 0.]);
+        assert_eq!(vec![1], grid.candidates_in_aabb(&everything));
+        assert_eq!(1, grid.occupied_cells().count());
+        assert_eq!(
+            Vec::<usize>::new(),
+            grid.candidates_along_segment([0., 5., 0.], [20., 5., 0.])
+        );
+    }
+
+    #[test]
+    fn given_rectangular_cells_expect_cells_of_that_width_and_height() {
+        let mut grid = QueryGrid::with_cell_size([0., 0., 0.], (20.0, 10.0));
+        grid.insert_aabb(0, &Aabb::from_center([25., 5., 0.], 2., 2.));
+        grid.insert_aabb(1, &Aabb::from_center([5., 25., 0.], 2., 2.));
+
+        assert_eq!((1, 0), grid.cell_of((25., 5.)));
+        let mut cells: Vec<Aabb> = grid.occupied_cells().collect();
+        cells.sort_by(|a, b| a.min.x.total_cmp(&b.min.x));
+        assert_eq!(
+            vec![
+                Aabb::new([0., 20., 0.], [20., 30., 0.]),
+                Aabb::new([20., 0., 0.], [40., 10., 0.])
+            ],
+            cells
+        );
+        assert_eq!(
+            vec![0],
+            grid.candidates_along_segment([0., 5., 0.], [100., 5., 0.])
+        );
+        assert_eq!(
+            vec![1],
+            grid.candidates_along_segment([0., 0., 0.], [20., 40., 0.])
+        );
+    }
+
+    #[test]
+    fn given_removed_body_expect_it_no_longer_a_candidate() {
+        let mut grid = QueryGrid::new([0., 0., 0.], 10.0);
+        let aabb = Aabb::from_center([10., 5., 0.], 2., 2.);
+        grid.insert_aabb(0, &aabb);
+        grid.insert_aabb(1, &Aabb::from_center([5., 5., 0.], 2., 2.));
+
+        grid.remove(0, QueryGrid::cells_in(grid.cell_range(&aabb)));
+        let everything = Aabb::new([-100., -100., 0.], [100., 100., 0.]);
+        assert_eq!(vec![1], grid.candidates_in_aabb(&everything));
+        assert_eq!(2, grid.num_cells());
+        assert_eq!(1, grid.occupied_cells().count());
+
+        grid.drop_empty_cells();
+        assert_eq!(1, grid.num_cells());
+    }
+
+    #[test]
+    fn given_unbounded_query_box_expect_all_bodies_without_overflow() {
+        let mut grid = QueryGrid::new([0., 0., 0.], 10.0);
+        grid.insert_aabb(0, &Aabb::from_center([5., 5., 0.], 2., 2.));
+        grid.insert_aabb(1, &Aabb::from_center([-45., 45., 0.], 2., 2.));
+
+        let huge = Aabb::new([-f32::MAX, -f32::MAX, 0.], [f32::MAX, f32::MAX, 0.]);
+        let infinite = Aabb::new(
+            [f32::NEG_INFINITY, f32::NEG_INFINITY, 0.],
+            [f32::INFINITY, f32::INFINITY, 0.],
+        );
+        assert_eq!(vec![0, 1], grid.candidates_in_aabb(&huge));
+        assert_eq!(vec![0, 1], grid.candidates_in_aabb(&infinite));
+        assert_eq!(
+            Vec::<usize>::new(),
+            QueryGrid::new([0., 0., 0.], 10.0).candidates_in_aabb(&huge)
+        );
+    }
+
+    #[test]
+    fn given_query_box_outside_of_all_bodies_expect_no_candidates() {
+        let mut grid = QueryGrid::new([0., 0., 0.], 10.0);
+        grid.insert_aabb(0, &Aabb::from_center([5., 5., 0.], 2., 2.));
+
+        let aabb = Aabb::new([100., 100., 0.], [f32::MAX, f32::MAX, 0.]);
+        assert_eq!(Vec::<usize>::new(), grid.candidates_in_aabb(&aabb));
+    }
+
+    mod candidates_along_segment {
+        use super::super::QueryGrid;
+        use crate::engine::physics_engine::collision::Aabb;
+        macro_rules! candidates_along_segment_tests {
+            ($($name:ident: $aabbs: expr, $start: expr, $end: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let mut grid = QueryGrid::new([0., 0., 0.], 10.0);
+                        let aabbs: Vec<Aabb> = $aabbs;
+                        aabbs.iter().enumerate().for_each(|(i, aabb)| grid.insert_aabb(i, aabb));
+                        let expected: Vec<usize> = $expected;
+                        let output = grid.candidates_along_segment($start, $end);
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        candidates_along_segment_tests! {
+            given_horizontal_segment_expect_only_bodies_in_the_same_row:
+                vec![Aabb::from_center([5., 5., 0.], 2., 2.),
+                     Aabb::from_center([45., 5., 0.], 2., 2.),
+                     Aabb::from_center([45., 45., 0.], 2., 2.)],
+                [0., 5., 0.], [100., 5., 0.], vec![0, 1]
+            given_diagonal_segment_expect_bodies_along_the_diagonal:
+                vec![Aabb::from_center([5., 5., 0.], 2., 2.),
+                     Aabb::from_center([45., 5., 0.], 2., 2.),
+                     Aabb::from_center([45., 45., 0.], 2., 2.)],
+                [0., 0., 0.], [50., 50., 0.], vec![0, 2]
+            given_segment_outside_of_all_bodies_expect_no_candidates:
+                vec![Aabb::from_center([5., 5., 0.], 2., 2.)],
+                [-100., -100., 0.], [-100., 100., 0.], vec![]
+            given_segment_in_negative_direction_expect_bodies_behind_start:
+                vec![Aabb::from_center([-35., 5., 0.], 2., 2.),
+                     Aabb::from_center([35., 5., 0.], 2., 2.)],
+                [0., 5., 0.], [-100., 5., 0.], vec![0]
+        }
+    }
+}
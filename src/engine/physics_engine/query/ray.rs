@@ -0,0 +1,58 @@
+use crate::engine::physics_engine::util::equations;
+
+/// A ray segment starting at `origin` and extending `max_distance` units along
+/// the normalized `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+    pub max_distance: f32,
+}
+
+impl Ray {
+    pub fn new(origin: [f32; 3], direction: [f32; 3], max_distance: f32) -> Self {
+        let mut direction = [direction[0], direction[1], 0.0];
+        equations::normalize(&mut direction);
+        debug_assert!(
+            direction != [0.0, 0.0, 0.0],
+            "Expected ray direction to be non-zero"
+        );
+        Self {
+            origin,
+            direction,
+            max_distance,
+        }
+    }
+
+    /// Creates a ray from `start` to `end`
+    pub fn between(start: [f32; 3], end: [f32; 3]) -> Self {
+        let direction = equations::subtract(&end, &start);
+        Self::new(start, direction, equations::magnitude(&direction))
+    }
+
+    pub fn point_at(&self, distance: f32) -> [f32; 3] {
+        [
+            self.origin[0] + self.direction[0] * distance,
+            self.origin[1] + self.direction[1] * distance,
+            0.0,
+        ]
+    }
+
+    pub fn end(&self) -> [f32; 3] {
+        self.point_at(self.max_distance)
+    }
+}
+
+/// The result of a ray or shape cast.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CastHit {
+    /// Index of the body in the iterator passed to the query, the same indexing
+    /// as `CollisionGraphNode`
+    pub body_idx: usize,
+    /// Point of contact on the surface of the body
+    pub point: [f32; 3],
+    /// Surface normal of the body at the point of contact
+    pub normal: [f32; 3],
+    /// Distance travelled before the hit divided by `Ray::max_distance`
+    pub fraction: f32,
+}
@@ -0,0 +1,277 @@
+use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
+use crate::engine::physics_engine::util::{
+    circle_equations, equations, polygon_equations, rectangle_equations,
+};
+
+use super::Ray;
+
+/// Shape swept along a ray by `SpatialQuery::shape_cast`. The shape is centered
+/// on the ray origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastShape {
+    Circle {
+        radius: f32,
+    },
+    Box {
+        width: f32,
+        height: f32,
+        rotation: f32,
+    },
+}
+
+impl CastShape {
+    /// Radius of the smallest circle around the shape center containing the shape
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            CastShape::Circle { radius } => radius,
+            CastShape::Box { width, height, .. } => (width.powi(2) + height.powi(2)).sqrt() / 2.0,
+        }
+    }
+
+    /// Sweeps the shape along the ray and intersects it with `body`. The sweep is
+    /// reduced to a ray cast against the Minkowski sum of the body and the shape.
+    ///
+    /// # Returns
+    /// - `Some((t, normal, point))` where `t` is the distance the shape travelled
+    ///   before touching the body, `normal` the surface normal of the body and
+    ///   `point` the point of contact.
+    /// - `None` if the shape never touches the body.
+    pub fn sweep(&self, ray: &Ray, body: &RigidBody) -> Option<(f32, [f32; 3], [f32; 3])> {
+        let center: [f32; 3] = body.position.into();
+        let hit = match (*self, body.body_type) {
+            (
+                CastShape::Circle { radius },
+                RigidBodyType::Circle {
+                    radius: body_radius,
+                }
+                | RigidBodyType::Custom {
+                    radius: body_radius,
+                    ..
+                },
+            ) => circle_equations::ray_intersection(
+                &ray.origin,
+                &ray.direction,
+                &center,
+                radius + body_radius,
+            ),
+            (CastShape::Circle { radius }, RigidBodyType::Rectangle { width, height }) => {
+                rounded_rectangle_ray_intersection(
+                    &ray.origin,
+                    &ray.direction,
+                    &center,
+                    width,
+                    height,
+                    body.rotation,
+                    radius,
+                )
+            }
+            (
+                CastShape::Box {
+                    width,
+                    height,
+                    rotation,
+                },
+                RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. },
+            ) => rounded_rectangle_ray_intersection(
+                &ray.origin,
+                &ray.direction,
+                &center,
+                width,
+                height,
+                rotation,
+                radius,
+            ),
+            (
+                CastShape::Box {
+                    width,
+                    height,
+                    rotation,
+                },
+                RigidBodyType::Rectangle { .. },
+            ) => {
+                let shape_corners = box_corners(width, height, rotation);
+                let minkowski_sum: Vec<[f32; 3]> = body
+                    .corners()
+                    .iter()
+                    .flat_map(|b| {
+                        shape_corners
+                            .iter()
+                            .map(move |a| [b[0] - a[0], b[1] - a[1], 0.0])
+                    })
+                    .collect();
+                let hull = polygon_equations::convex_hull(&minkowski_sum);
+                polygon_equations::ray_intersection(&ray.origin, &ray.direction, &hull)
+            }
+            (_, _) => panic!("Unknown body type {} in shape cast", body.body_type),
+        };
+
+        let (t, normal) = hit?;
+        if t > ray.max_distance {
+            return None;
+        }
+        let shape_center = ray.point_at(t);
+        let point = match (*self, body.body_type) {
+            (CastShape::Circle { radius }, _) => [
+                shape_center[0] - normal[0] * radius,
+                shape_center[1] - normal[1] * radius,
+                0.0,
+            ],
+            (
+                CastShape::Box { .. },
+                RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. },
+            ) => [
+                center[0] + normal[0] * radius,
+                center[1] + normal[1] * radius,
+                0.0,
+            ],
+            (
+                CastShape::Box {
+                    width,
+                    height,
+                    rotation,
+                },
+                _,
+            ) => {
+                // The corner of the box furthest into the body
+                let inward = equations::negate(&normal);
+                let support = box_corners(width, height, rotation)
+                    .into_iter()
+                    .max_by(|a, b| {
+                        equations::dot(a, &inward)
+                            .partial_cmp(&equations::dot(b, &inward))
+                            .unwrap()
+                    })
+                    .unwrap();
+                [
+                    shape_center[0] + support[0],
+                    shape_center[1] + support[1],
+                    0.0,
+                ]
+            }
+        };
+        Some((t, normal, point))
+    }
+}
+
+fn box_corners(width: f32, height: f32, rotation: f32) -> [[f32; 3]; 4] {
+    [
+        equations::rotate_z(&[-width / 2.0, height / 2.0, 0.0], rotation),
+        equations::rotate_z(&[width / 2.0, height / 2.0, 0.0], rotation),
+        equations::rotate_z(&[width / 2.0, -height / 2.0, 0.0], rotation),
+        equations::rotate_z(&[-width / 2.0, -height / 2.0, 0.0], rotation),
+    ]
+}
+
+/// Intersects a ray with a rectangle whose edges have been pushed out by `radius`
+/// and whose corners are rounded with `radius`. The shape is the union of two
+/// stretched rectangles and four corner circles, the earliest hit of which is
+/// returned.
+fn rounded_rectangle_ray_intersection(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    center: &[f32; 3],
+    width: f32,
+    height: f32,
+    rotation: f32,
+    radius: f32,
+) -> Option<(f32, [f32; 3])> {
+    let wide = rectangle_equations::ray_intersection(
+        origin,
+        direction,
+        center,
+        width + 2.0 * radius,
+        height,
+        rotation,
+    );
+    let tall = rectangle_equations::ray_intersection(
+        origin,
+        direction,
+        center,
+        width,
+        height + 2.0 * radius,
+        rotation,
+    );
+    let corners = box_corners(width, height, rotation).map(|corner| {
+        let corner = [center[0] + corner[0], center[1] + corner[1], 0.0];
+        circle_equations::ray_intersection(origin, direction, &corner, radius)
+    });
+
+    [wide, tall]
+        .into_iter()
+        .chain(corners)
+        .flatten()
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    mod sweep {
+        use super::super::CastShape;
+        use crate::engine::physics_engine::collision::{
+            RigidBody, RigidBodyBuilder, RigidBodyType,
+        };
+        use crate::engine::physics_engine::query::Ray;
+        use crate::engine::util::fixed_float::fixed_float_vector::FixedFloatVector;
+        macro_rules! sweep_tests {
+            ($($name:ident: $shape: expr, $ray: expr, $body: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let shape: CastShape = $shape;
+                        let body: RigidBody = $body;
+                        let expected: Option<(f32, [f32; 3], [f32; 3])> = $expected;
+                        let output = shape.sweep(&$ray, &body).map(|(t, n, p)| (
+                            (t * 1000.0).round() / 1000.0,
+                            FixedFloatVector::from(n).into(),
+                            FixedFloatVector::from(p).into()));
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        sweep_tests! {
+            given_circle_swept_at_circle_expect_hit_when_surfaces_touch:
+                CastShape::Circle { radius: 1.0 },
+                Ray::new([-10., 0., 0.], [1., 0., 0.], 100.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Circle { radius: 2.0 }).build(),
+                Some((7.0, [-1., 0., 0.], [-2., 0., 0.]))
+
+            given_circle_swept_past_rect_corner_expect_hit_on_rounded_corner:
+                CastShape::Circle { radius: 1.0 },
+                Ray::new([-10., 1.5, 0.], [1., 0., 0.], 100.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Rectangle { width: 2.0, height: 2.0 }).build(),
+                Some((8.134, [-0.866, 0.5, 0.], [-1., 1., 0.]))
+
+            given_circle_swept_above_rect_expect_miss:
+                CastShape::Circle { radius: 1.0 },
+                Ray::new([-10., 2.5, 0.], [1., 0., 0.], 100.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Rectangle { width: 2.0, height: 2.0 }).build(),
+                None
+
+            given_box_swept_at_rect_expect_hit_when_faces_touch:
+                CastShape::Box { width: 2.0, height: 2.0, rotation: 0.0 },
+                Ray::new([-10., 0., 0.], [1., 0., 0.], 100.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Rectangle { width: 4.0, height: 4.0 }).build(),
+                Some((7.0, [-1., 0., 0.], [-2., -1., 0.]))
+
+            given_box_swept_at_circle_expect_hit_on_circle_surface:
+                CastShape::Box { width: 2.0, height: 2.0, rotation: 0.0 },
+                Ray::new([0., 10., 0.], [0., -1., 0.], 100.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Circle { radius: 2.0 }).build(),
+                Some((7.0, [0., 1., 0.], [0., 2., 0.]))
+
+            given_box_swept_short_of_rect_expect_miss:
+                CastShape::Box { width: 2.0, height: 2.0, rotation: 0.0 },
+                Ray::new([-10., 0., 0.], [1., 0., 0.], 5.0),
+                RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                    .body_type(RigidBodyType::Rectangle { width: 4.0, height: 4.0 }).build(),
+                None
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use super::collision::RigidBody;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hashes the exact bits of the state the integrator carries from one step to the next,
+/// the position, previous position, acceleration and rotation of each body in order.
+/// Two simulations have diverged as soon as their hashes differ, even if the difference
+/// is far below the tolerance of `FixedFloat`.
+///
+/// The hash is FNV-1a such that it is the same between builds and machines.
+pub fn state_hash<'a, I>(bodies: I) -> u64
+where
+    I: Iterator<Item = &'a RigidBody>,
+{
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    for body in bodies {
+        write(&(body.id as u64).to_le_bytes());
+        let state = [
+            body.position.x,
+            body.position.y,
+            body.position.z,
+            body.prev_position.x,
+            body.prev_position.y,
+            body.prev_position.z,
+            body.acceleration.x,
+            body.acceleration.y,
+            body.acceleration.z,
+            body.rotation,
+            body.prev_rotation,
+        ];
+        for value in state {
+            write(&value.to_bits().to_le_bytes());
+        }
+    }
+    hash
+}
+
+/// The state hash of every tick of a simulation. Record each tick of two runs and
+/// compare the logs to find the first tick at which they diverged.
+///
+/// A run is reproducible when the scene is created from `init_utils::seeded_rng`,
+/// the broadphase is one of the spatial subdivisions, the contacts are resolved with
+/// `NarrowPhase::parallel_collision_detection` one pass at a time and the constraints
+/// are applied to the bodies in order. The result is then also independent of the
+/// number of threads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateHashLog {
+    hashes: Vec<u64>,
+}
+
+impl StateHashLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the hash of the bodies after a tick and returns it
+    pub fn record<'a, I>(&mut self, bodies: I) -> u64
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        let hash = state_hash(bodies);
+        self.hashes.push(hash);
+        hash
+    }
+
+    pub fn hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Returns the first tick at which the two logs differ. A log that ends early
+    /// diverges at the first tick missing from it.
+    pub fn first_divergence(&self, other: &StateHashLog) -> Option<usize> {
+        self.hashes
+            .iter()
+            .zip(other.hashes.iter())
+            .position(|(a, b)| a != b)
+            .or_else(|| (self.len() != other.len()).then(|| self.len().min(other.len())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{state_hash, StateHashLog};
+    use crate::engine::init_utils::{
+        create_grid_positions_with_rng, generate_random_radii_with_rng, seeded_rng,
+    };
+    use crate::engine::physics_engine::broadphase::{BroadPhase, SparseSpatialSubdivision};
+    use crate::engine::physics_engine::collision::collision_candidates::CollisionCandidates;
+    use crate::engine::physics_engine::collision::{
+        RigidBody, RigidBodyBuilder, RigidBodyType, SimpleCollisionSolver,
+    };
+    use crate::engine::physics_engine::constraint::box_constraint::BoxConstraint;
+    use crate::engine::physics_engine::constraint::resolver::inelastic::InelasticConstraintResolver;
+    use crate::engine::physics_engine::constraint::Constraint;
+    use crate::engine::physics_engine::force::{ForceRegistry, Gravity};
+    use crate::engine::physics_engine::integrator::verlet::VerletIntegrator;
+    use crate::engine::physics_engine::narrowphase::naive::Naive;
+    use crate::engine::physics_engine::narrowphase::{NarrowPhase, PairCache};
+    use cgmath::Vector3;
+    use rand::Rng;
+
+    fn circle(id: usize, position: [f32; 3]) -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(id)
+            .position(position)
+            .body_type(RigidBodyType::Circle { radius: 1.0 })
+            .build()
+    }
+
+    /// Drops a jittered grid of circles into a box, recording every tick
+    fn simulate(seed: u64, num_ticks: usize, num_threads: usize) -> StateHashLog {
+        let mut rng = seeded_rng(seed);
+        let positions = create_grid_positions_with_rng(12, 12, 5.0, Some(1.0), &mut rng);
+        let radii = generate_random_radii_with_rng(144, 2.0, 0.5, &mut rng);
+        let mut bodies: Vec<RigidBody> = positions
+            .into_iter()
+            .zip(radii)
+            .enumerate()
+            .map(|(i, (position, radius))| {
+                RigidBodyBuilder::default()
+                    .id(i)
+                    .position(position.into())
+                    .velocity([rng.gen_range(-0.5..0.5), 0.0, 0.0])
+                    .body_type(RigidBodyType::Circle { radius })
+                    .build()
+            })
+            .collect();
+
+        let mut forces = ForceRegistry::new();
+        forces.add(Gravity::new([0.0, -50.0, 0.0]));
+        let integrator = VerletIntegrator::new(f32::MAX);
+        let mut constraint = BoxConstraint::new(InelasticConstraintResolver::new().friction(0.3));
+        constraint.set_top_left(Vector3::new(-40.0, 40.0, 0.0));
+        constraint.set_bottom_right(Vector3::new(40.0, -40.0, 0.0));
+        let mut broadphase = SparseSpatialSubdivision::new();
+        let narrowphase = Naive::new(SimpleCollisionSolver::new());
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+
+        let mut log = StateHashLog::new();
+        for _ in 0..num_ticks {
+            forces.apply(bodies.iter_mut(), 1.0 / 60.0);
+            integrator.update(bodies.iter_mut(), 1.0 / 60.0);
+            bodies
+                .iter_mut()
+                .for_each(|b| constraint.apply_constraint(b));
+            pool.install(|| {
+                let passes: [Vec<CollisionCandidates>; 4] =
+                    broadphase.collision_detection(bodies.iter());
+                let mut refs: Vec<&mut RigidBody> = bodies.iter_mut().collect();
+                let mut pairs = PairCache::new();
+                for pass in passes.iter() {
+                    narrowphase.parallel_collision_detection(&mut refs, pass, &mut pairs);
+                }
+            });
+            log.record(bodies.iter());
+        }
+        log
+    }
+
+    #[test]
+    fn given_same_seed_expect_identical_hash_every_tick() {
+        let first = simulate(1, 120, 4);
+        let second = simulate(1, 120, 4);
+
+        assert_eq!(120, first.len());
+        assert_eq!(None, first.first_divergence(&second));
+    }
+
+    #[test]
+    fn given_different_number_of_threads_expect_identical_hash_every_tick() {
+        let expected = simulate(2, 120, 1);
+
+        assert_eq!(None, expected.first_divergence(&simulate(2, 120, 3)));
+        assert_eq!(None, expected.first_divergence(&simulate(2, 120, 8)));
+    }
+
+    #[test]
+    fn given_different_seed_expect_divergence_at_first_tick() {
+        assert_eq!(
+            Some(0),
+            simulate(1, 10, 2).first_divergence(&simulate(3, 10, 2))
+        );
+    }
+
+    #[test]
+    fn given_smallest_change_of_a_position_expect_different_hash() {
+        let bodies = [circle(0, [1.0, 2.0, 0.0]), circle(1, [3.0, 4.0, 0.0])];
+        let mut moved = bodies.clone();
+        moved[1].position.x = f32::from_bits(moved[1].position.x.to_bits() + 1);
+
+        assert_eq!(state_hash(bodies.iter()), state_hash(bodies.clone().iter()));
+        assert_ne!(state_hash(bodies.iter()), state_hash(moved.iter()));
+    }
+
+    #[test]
+    fn given_different_acceleration_expect_different_hash() {
+        let bodies = [circle(0, [1.0, 2.0, 0.0])];
+        let mut accelerated = bodies.clone();
+        accelerated[0].acceleration.y = -9.82;
+
+        assert_ne!(state_hash(bodies.iter()), state_hash(accelerated.iter()));
+    }
+
+    #[test]
+    fn given_logs_of_different_length_expect_divergence_at_end_of_shorter() {
+        let bodies = [circle(0, [1.0, 2.0, 0.0])];
+        let mut short = StateHashLog::new();
+        let mut long = StateHashLog::new();
+        for _ in 0..3 {
+            short.record(bodies.iter());
+            long.record(bodies.iter());
+        }
+        long.record(bodies.iter());
+
+        assert_eq!(Some(3), short.first_divergence(&long));
+        assert_eq!(Some(3), long.first_divergence(&short));
+        assert_eq!(None, short.first_divergence(&short.clone()));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::engine::physics_engine::collision::{RigidBody, RigidBodyType};
+use crate::engine::physics_engine::collision::{Aabb, RigidBody, RigidBodyType};
 
 use cgmath::{InnerSpace, MetricSpace, Vector3};
 
@@ -19,10 +19,89 @@ pub fn cardinals(center: [f32; 3], radius: f32) -> [[f32; 3]; 4] {
 
 pub fn click_inside(point: (f32, f32), circle: &RigidBody) -> bool {
     let radius = match circle.body_type {
-        RigidBodyType::Circle { radius } => radius,
+        RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => radius,
         _ => unreachable!(),
     };
     let click_position = Vector3::new(point.0, point.1, 0.0);
 
     click_position.distance2(circle.position) < radius.powi(2)
 }
+
+/// Returns true if the circle overlaps the axis aligned box
+pub fn overlaps_aabb(center: &[f32; 3], radius: f32, aabb: &Aabb) -> bool {
+    let closest_x = center[0].clamp(aabb.min.x, aabb.max.x);
+    let closest_y = center[1].clamp(aabb.min.y, aabb.max.y);
+    (center[0] - closest_x).powi(2) + (center[1] - closest_y).powi(2) <= radius.powi(2)
+}
+
+/// Intersects a ray with a circle.
+///
+/// # Parameters
+/// - `origin`: Start of the ray.
+/// - `direction`: Unit direction of the ray.
+/// - `center`, `radius`: The circle.
+///
+/// # Returns
+/// - `Some((t, normal))` where `t` is the distance along the ray to the first
+///   intersection and `normal` the outward surface normal at that point. If the
+///   origin is inside the circle `t` is zero and the normal opposes the ray.
+/// - `None` if the ray misses the circle.
+pub fn ray_intersection(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    center: &[f32; 3],
+    radius: f32,
+) -> Option<(f32, [f32; 3])> {
+    let m = [origin[0] - center[0], origin[1] - center[1], 0.0];
+    let b = m[0] * direction[0] + m[1] * direction[1];
+    let c = m[0] * m[0] + m[1] * m[1] - radius.powi(2);
+    if c <= 0.0 {
+        return Some((0.0, [-direction[0], -direction[1], 0.0]));
+    }
+    if b > 0.0 {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    let normal = [
+        (m[0] + t * direction[0]) / radius,
+        (m[1] + t * direction[1]) / radius,
+        0.0,
+    ];
+    Some((t, normal))
+}
+
+#[cfg(test)]
+mod circle_equations_test {
+    mod ray_intersection {
+        use super::super::ray_intersection;
+        use crate::engine::util::fixed_float::fixed_float_vector::FixedFloatVector;
+        macro_rules! ray_intersection_tests {
+            ($($name:ident: $origin: expr, $direction: expr, $center: expr, $radius: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: Option<(f32, [f32; 3])> = $expected;
+                        let output = ray_intersection(&$origin, &$direction, &$center, $radius)
+                            .map(|(t, n)| (t, FixedFloatVector::from(n).into()));
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        ray_intersection_tests! {
+            given_ray_points_at_circle_expect_hit_on_near_side:
+                [-10.,0.,0.], [1.,0.,0.], [0.,0.,0.], 2.0, Some((8.0, [-1.,0.,0.]))
+            given_ray_points_away_from_circle_expect_miss:
+                [-10.,0.,0.], [-1.,0.,0.], [0.,0.,0.], 2.0, None
+            given_ray_passes_beside_circle_expect_miss:
+                [-10.,5.,0.], [1.,0.,0.], [0.,0.,0.], 2.0, None
+            given_ray_starts_inside_circle_expect_hit_at_origin:
+                [0.5,0.,0.], [0.,1.,0.], [0.,0.,0.], 2.0, Some((0.0, [0.,-1.,0.]))
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod equations;
 pub mod rectangle_equations;
 pub mod circle_equations;
+pub mod polygon_equations;
@@ -0,0 +1,243 @@
+use super::equations;
+
+/// Returns the convex hull of a set of points in counter clockwise order using
+/// Andrew's monotone chain algorithm. Collinear points are dropped.
+pub fn convex_hull(points: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    let mut sorted: Vec<[f32; 3]> = points.to_vec();
+    sorted.sort_by(|a, b| {
+        a[0].partial_cmp(&b[0])
+            .unwrap()
+            .then(a[1].partial_cmp(&b[1]).unwrap())
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let turn = |o: &[f32; 3], a: &[f32; 3], b: &[f32; 3]| {
+        equations::cross_2d(&equations::subtract(a, o), &equations::subtract(b, o))
+    };
+
+    let mut hull: Vec<[f32; 3]> = Vec::with_capacity(sorted.len() * 2);
+    for p in sorted.iter() {
+        while hull.len() >= 2 && turn(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+    let lower_len = hull.len() + 1;
+    for p in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower_len
+            && turn(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+    hull.pop();
+    hull
+}
+
+/// Returns the area of a polygon, positive if the vertices are in counter clockwise
+/// order and negative if they are in clockwise order
+pub fn signed_area(vertices: &[[f32; 3]]) -> f32 {
+    let n = vertices.len();
+    (0..n)
+        .map(|i| equations::cross_2d(&vertices[i], &vertices[(i + 1) % n]))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Returns the point on the segment from `start` to `end` closest to `point` and how
+/// far along the segment it is, from 0 at `start` to 1 at `end`
+pub fn closest_point_on_segment(
+    point: &[f32; 3],
+    start: &[f32; 3],
+    end: &[f32; 3],
+) -> ([f32; 3], f32) {
+    let segment = equations::subtract(end, start);
+    let length2 = equations::magnitude2(&segment);
+    if length2 == 0.0 {
+        return (*start, 0.0);
+    }
+    let t =
+        (equations::dot(&equations::subtract(point, start), &segment) / length2).clamp(0.0, 1.0);
+    (
+        [
+            start[0] + segment[0] * t,
+            start[1] + segment[1] * t,
+            start[2] + segment[2] * t,
+        ],
+        t,
+    )
+}
+
+/// Intersects a ray with a convex polygon using Cyrus-Beck clipping.
+///
+/// # Parameters
+/// - `origin`: Start of the ray.
+/// - `direction`: Unit direction of the ray.
+/// - `vertices`: Corners of a convex polygon in counter clockwise order.
+///
+/// # Returns
+/// - `Some((t, normal))` where `t` is the distance along the ray to the first
+///   intersection and `normal` the outward normal of the edge that was hit. If
+///   the origin is inside the polygon `t` is zero and the normal opposes the ray.
+/// - `None` if the ray misses the polygon.
+pub fn ray_intersection(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    vertices: &[[f32; 3]],
+) -> Option<(f32, [f32; 3])> {
+    let num_vertices = vertices.len();
+    if num_vertices < 3 {
+        return None;
+    }
+
+    let mut t_enter = f32::MIN;
+    let mut t_exit = f32::MAX;
+    let mut normal = [0.0, 0.0, 0.0];
+    for i in 0..num_vertices {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % num_vertices];
+        let edge = equations::subtract(&b, &a);
+        // Counter clockwise winding means the outward normal is to the right of the edge
+        let mut edge_normal = [edge[1], -edge[0], 0.0];
+        equations::normalize(&mut edge_normal);
+
+        let distance = equations::dot(&edge_normal, &equations::subtract(&a, origin));
+        let denominator = equations::dot(&edge_normal, direction);
+        if denominator.abs() < f32::EPSILON {
+            if distance < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = distance / denominator;
+        if denominator < 0.0 {
+            if t > t_enter {
+                t_enter = t;
+                normal = edge_normal;
+            }
+        } else {
+            t_exit = t_exit.min(t);
+        }
+        if t_enter > t_exit || t_exit < 0.0 {
+            return None;
+        }
+    }
+
+    if t_enter <= 0.0 {
+        return Some((0.0, [-direction[0], -direction[1], 0.0]));
+    }
+    Some((t_enter, normal))
+}
+
+#[cfg(test)]
+mod polygon_equations_test {
+    mod convex_hull {
+        use super::super::convex_hull;
+        macro_rules! convex_hull_tests {
+            ($($name:ident: $points: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: Vec<[f32; 3]> = $expected;
+                        let output = convex_hull(&$points);
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        convex_hull_tests! {
+            given_square_with_inner_point_expect_inner_point_removed:
+                [[0.,0.,0.],[1.,1.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]],
+                vec![[0.,0.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]]
+            given_duplicated_points_expect_duplicates_removed:
+                [[0.,0.,0.],[2.,0.,0.],[2.,0.,0.],[0.,2.,0.],[0.,0.,0.]],
+                vec![[0.,0.,0.],[2.,0.,0.],[0.,2.,0.]]
+        }
+    }
+
+    mod ray_intersection {
+        use super::super::ray_intersection;
+        macro_rules! ray_intersection_tests {
+            ($($name:ident: $origin: expr, $direction: expr, $vertices: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: Option<(f32, [f32; 3])> = $expected;
+                        let output = ray_intersection(&$origin, &$direction, &$vertices);
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        ray_intersection_tests! {
+            given_ray_points_at_square_expect_hit_on_left_edge:
+                [-10.,1.,0.], [1.,0.,0.], [[0.,0.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]],
+                Some((10.0, [-1.,0.,0.]))
+            given_ray_points_away_from_square_expect_miss:
+                [-10.,1.,0.], [-1.,0.,0.], [[0.,0.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]], None
+            given_ray_passes_below_triangle_expect_miss:
+                [-10.,-1.,0.], [1.,0.,0.], [[0.,0.,0.],[2.,0.,0.],[0.,2.,0.]], None
+            given_ray_starts_inside_square_expect_hit_at_origin:
+                [1.,1.,0.], [0.,1.,0.], [[0.,0.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]],
+                Some((0.0, [0.,-1.,0.]))
+        }
+    }
+
+    mod signed_area {
+        use super::super::signed_area;
+        macro_rules! signed_area_tests {
+            ($($name:ident: $vertices: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: f32 = $expected;
+                        let output = signed_area(&$vertices);
+                        assert_eq!(expected, output, "Expected {expected} but found {output}");
+                    }
+                )*
+            }
+        }
+
+        signed_area_tests! {
+            given_counter_clockwise_square_expect_positive_area:
+                [[0.,0.,0.],[2.,0.,0.],[2.,2.,0.],[0.,2.,0.]], 4.0
+            given_clockwise_square_expect_negative_area:
+                [[0.,0.,0.],[0.,2.,0.],[2.,2.,0.],[2.,0.,0.]], -4.0
+            given_counter_clockwise_triangle_away_from_origin_expect_positive_area:
+                [[10.,10.,0.],[14.,10.,0.],[10.,13.,0.]], 6.0
+        }
+    }
+
+    mod closest_point_on_segment {
+        use super::super::closest_point_on_segment;
+        macro_rules! closest_point_on_segment_tests {
+            ($($name:ident: $point: expr, $start: expr, $end: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: ([f32; 3], f32) = $expected;
+                        let output = closest_point_on_segment(&$point, &$start, &$end);
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        closest_point_on_segment_tests! {
+            given_point_above_segment_expect_projection:
+                [1.,5.,0.], [0.,0.,0.], [4.,0.,0.], ([1.,0.,0.], 0.25)
+            given_point_before_start_expect_start:
+                [-3.,1.,0.], [0.,0.,0.], [4.,0.,0.], ([0.,0.,0.], 0.0)
+            given_point_after_end_expect_end:
+                [6.,-1.,0.], [0.,0.,0.], [4.,0.,0.], ([4.,0.,0.], 1.0)
+            given_degenerate_segment_expect_start:
+                [6.,-1.,0.], [1.,1.,0.], [1.,1.,0.], ([1.,1.,0.], 0.0)
+        }
+    }
+}
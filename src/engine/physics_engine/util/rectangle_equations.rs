@@ -1,6 +1,6 @@
 use super::equations;
 use crate::engine::{
-    physics_engine::collision::{RigidBody, RigidBodyType},
+    physics_engine::collision::{Aabb, RigidBody, RigidBodyType},
     util::fixed_float::fixed_float_vector::FixedFloatVector,
 };
 
@@ -88,6 +88,101 @@ pub fn click_inside(point: (f32, f32), rectangle: &RigidBody) -> bool {
     local_point[0].abs() <= width / 2.0 && local_point[1].abs() <= height / 2.0
 }
 
+/// Returns true if the rotated rectangle overlaps the axis aligned box. Separating
+/// axis test over the box axes and the two edge normals of the rectangle.
+pub fn overlaps_aabb(rectangle: &RigidBody, aabb: &Aabb) -> bool {
+    let corners = corners(rectangle);
+    let rect_aabb = Aabb::from_points(&corners);
+    if !rect_aabb.overlaps(aabb) {
+        return false;
+    }
+
+    let aabb_corners = [
+        [aabb.min.x, aabb.min.y, 0.0],
+        [aabb.max.x, aabb.min.y, 0.0],
+        [aabb.max.x, aabb.max.y, 0.0],
+        [aabb.min.x, aabb.max.y, 0.0],
+    ];
+    let axii = [
+        equations::rotate_z(&[1.0, 0.0, 0.0], rectangle.rotation),
+        equations::rotate_z(&[0.0, 1.0, 0.0], rectangle.rotation),
+    ];
+    let project = |points: &[[f32; 3]], axis: &[f32; 3]| {
+        points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+            let d = equations::dot(p, axis);
+            (min.min(d), max.max(d))
+        })
+    };
+    axii.iter().all(|axis| {
+        let (min_a, max_a) = project(&corners, axis);
+        let (min_b, max_b) = project(&aabb_corners, axis);
+        min_a <= max_b && min_b <= max_a
+    })
+}
+
+/// Intersects a ray with a rotated rectangle using the slab method in the local
+/// frame of the rectangle.
+///
+/// # Parameters
+/// - `origin`: Start of the ray.
+/// - `direction`: Unit direction of the ray.
+/// - `center`, `width`, `height`, `rotation`: The rectangle.
+///
+/// # Returns
+/// - `Some((t, normal))` where `t` is the distance along the ray to the first
+///   intersection and `normal` the outward normal of the edge that was hit. If
+///   the origin is inside the rectangle `t` is zero and the normal opposes the ray.
+/// - `None` if the ray misses the rectangle.
+pub fn ray_intersection(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    center: &[f32; 3],
+    width: f32,
+    height: f32,
+    rotation: f32,
+) -> Option<(f32, [f32; 3])> {
+    let local_origin = equations::rotate_z(
+        &[origin[0] - center[0], origin[1] - center[1], 0.0],
+        -rotation,
+    );
+    let local_direction = equations::rotate_z(direction, -rotation);
+    let half_extents = [width / 2.0, height / 2.0];
+
+    let mut t_enter = f32::MIN;
+    let mut t_exit = f32::MAX;
+    let mut local_normal = [0.0, 0.0, 0.0];
+    for axis in 0..2 {
+        if local_direction[axis].abs() < f32::EPSILON {
+            if local_origin[axis].abs() > half_extents[axis] {
+                return None;
+            }
+            continue;
+        }
+        let inv = 1.0 / local_direction[axis];
+        let mut t_near = (-half_extents[axis] - local_origin[axis]) * inv;
+        let mut t_far = (half_extents[axis] - local_origin[axis]) * inv;
+        let mut sign = -1.0;
+        if t_near > t_far {
+            std::mem::swap(&mut t_near, &mut t_far);
+            sign = 1.0;
+        }
+        if t_near > t_enter {
+            t_enter = t_near;
+            local_normal = [0.0, 0.0, 0.0];
+            local_normal[axis] = sign;
+        }
+        t_exit = t_exit.min(t_far);
+        if t_enter > t_exit || t_exit < 0.0 {
+            return None;
+        }
+    }
+
+    if t_enter <= 0.0 {
+        return Some((0.0, [-direction[0], -direction[1], 0.0]));
+    }
+    Some((t_enter, equations::rotate_z(&local_normal, rotation)))
+}
+
 /// Computes the world-space coordinates of the four corners of a rectangle,
 /// taking into account its position and rotation.
 ///
@@ -198,6 +293,71 @@ mod rectangle_equations_test {
         }
     }
 
+    mod overlaps_aabb {
+        use super::super::overlaps_aabb;
+        use crate::engine::physics_engine::collision::{Aabb, RigidBodyBuilder, RigidBodyType};
+        macro_rules! overlaps_aabb_tests {
+            ($($name:ident: $rotation: expr, $aabb: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let rect = RigidBodyBuilder::default().id(0).position([0., 0., 0.])
+                            .rotation($rotation)
+                            .body_type(RigidBodyType::Rectangle { width: 10., height: 10. })
+                            .build();
+                        let aabb: Aabb = $aabb;
+                        let expected: bool = $expected;
+                        assert_eq!(expected, overlaps_aabb(&rect, &aabb));
+                    }
+                )*
+            }
+        }
+
+        overlaps_aabb_tests! {
+            given_axis_aligned_rect_and_overlapping_box_expect_overlap:
+                0.0, Aabb::new([4., 4., 0.], [8., 8., 0.]), true
+            given_axis_aligned_rect_and_disjoint_box_expect_no_overlap:
+                0.0, Aabb::new([6., 6., 0.], [8., 8., 0.]), false
+            given_rotated_rect_and_box_inside_bounding_box_but_outside_rect_expect_no_overlap:
+                std::f32::consts::PI/4.0, Aabb::new([5., 5., 0.], [6., 6., 0.]), false
+            given_rotated_rect_and_box_over_corner_expect_overlap:
+                std::f32::consts::PI/4.0, Aabb::new([6., -1., 0.], [8., 1., 0.]), true
+        }
+    }
+
+    mod ray_intersection {
+        use super::super::ray_intersection;
+        use crate::engine::util::fixed_float::fixed_float_vector::FixedFloatVector;
+        macro_rules! ray_intersection_tests {
+            ($($name:ident: $origin: expr, $direction: expr, $center: expr, $width: expr,
+                $height: expr, $rotation: expr, $expected: expr)*) => {
+                $(
+                    #[test]
+                    fn $name() {
+                        let expected: Option<(f32, [f32; 3])> = $expected;
+                        let output = ray_intersection(&$origin, &$direction, &$center, $width, $height, $rotation)
+                            .map(|(t, n)| ((t * 1000.0).round() / 1000.0, FixedFloatVector::from(n).into()));
+                        assert_eq!(expected, output, "Expected {expected:?} but found {output:?}");
+                    }
+                )*
+            }
+        }
+
+        ray_intersection_tests! {
+            given_ray_points_at_axis_aligned_rect_expect_hit_on_left_edge:
+                [-10.,0.,0.], [1.,0.,0.], [0.,0.,0.], 4.0, 2.0, 0.0, Some((8.0, [-1.,0.,0.]))
+            given_ray_points_down_at_axis_aligned_rect_expect_hit_on_top_edge:
+                [0.,10.,0.], [0.,-1.,0.], [0.,0.,0.], 4.0, 2.0, 0.0, Some((9.0, [0.,1.,0.]))
+            given_ray_passes_above_rect_expect_miss:
+                [-10.,2.,0.], [1.,0.,0.], [0.,0.,0.], 4.0, 2.0, 0.0, None
+            given_rect_rotated_90_degrees_expect_hit_on_rotated_long_edge:
+                [-10.,0.,0.], [1.,0.,0.], [0.,0.,0.], 4.0, 2.0, std::f32::consts::PI/2.0,
+                Some((9.0, [-1.,0.,0.]))
+            given_ray_starts_inside_rect_expect_hit_at_origin:
+                [0.,0.,0.], [1.,0.,0.], [0.,0.,0.], 4.0, 2.0, 0.0, Some((0.0, [-1.,0.,0.]))
+        }
+    }
+
     mod get_corners {
         use super::super::corners;
         use crate::engine::physics_engine::collision::{RigidBodyBuilder, RigidBodyType};
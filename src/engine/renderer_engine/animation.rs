@@ -0,0 +1,340 @@
+use super::asset::sprite_sheet::SpriteCoordinate;
+
+/// How a clip continues after its last frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Starts over at the first frame
+    #[default]
+    Loop,
+    /// Plays the frames backwards to the first frame and then forwards again
+    PingPong,
+    /// Stops at the last frame
+    Once,
+}
+
+/// Raised by an animator when its clip reaches the end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A looping or ping-pong clip starts over
+    Looped { clip: String },
+    /// A one-shot clip stopped at its last frame
+    Finished { clip: String },
+}
+
+/// A cell region of the sprite sheet shown for a number of ticks
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    pub sprite_coord: SpriteCoordinate,
+    pub ticks: u32,
+}
+
+/// A named sequence of frames over the cells of a sprite sheet
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    name: String,
+    frames: Vec<AnimationFrame>,
+    mode: AnimationMode,
+}
+
+impl AnimationClip {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            frames: vec![],
+            mode: AnimationMode::default(),
+        }
+    }
+
+    pub fn mode(mut self, mode: AnimationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Adds a frame showing the cells from `top_left_cell` to `bot_right_cell` for
+    /// `ticks` game ticks
+    pub fn frame(mut self, top_left_cell: [f32; 2], bot_right_cell: [f32; 2], ticks: u32) -> Self {
+        debug_assert!(ticks > 0, "Expected a frame to last at least one tick");
+        self.frames.push(AnimationFrame {
+            sprite_coord: SpriteCoordinate::new(top_left_cell, bot_right_cell),
+            ticks,
+        });
+        self
+    }
+
+    /// Adds a frame for each of `num_frames` single cells of the sprite sheet, from
+    /// left to right along `row` starting at `first_column`
+    pub fn row(mut self, row: u32, first_column: u32, num_frames: u32, ticks: u32) -> Self {
+        for column in first_column..first_column + num_frames {
+            let (x, y) = (column as f32, row as f32);
+            self = self.frame([x, y], [x + 1.0, y + 1.0], ticks);
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// The number of ticks to play every frame once
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|f| f.ticks).sum()
+    }
+}
+
+/// Plays one of its clips, advanced once per game tick with `tick`. Added to an
+/// entity, `EntityComponentStorage::animate` copies the current frame into the sprite
+/// coordinate of the render body.
+#[derive(Clone, Debug)]
+pub struct Animator {
+    clips: Vec<AnimationClip>,
+    clip: usize,
+    frame: usize,
+    ticks_in_frame: u32,
+    forward: bool,
+    finished: bool,
+}
+
+impl Animator {
+    /// Starts playing `clip`
+    pub fn new(clip: AnimationClip) -> Self {
+        debug_assert!(
+            !clip.frames.is_empty(),
+            "Expected clip {} to have frames",
+            clip.name
+        );
+        Self {
+            clips: vec![clip],
+            clip: 0,
+            frame: 0,
+            ticks_in_frame: 0,
+            forward: true,
+            finished: false,
+        }
+    }
+
+    /// Adds a clip that can be switched to with `play`
+    pub fn clip(mut self, clip: AnimationClip) -> Self {
+        debug_assert!(
+            !clip.frames.is_empty(),
+            "Expected clip {} to have frames",
+            clip.name
+        );
+        debug_assert!(
+            self.clips.iter().all(|c| c.name != clip.name),
+            "Expected unique clip names, found {} twice",
+            clip.name
+        );
+        self.clips.push(clip);
+        self
+    }
+
+    /// Switches to the clip from its first frame. Playing the clip that is already
+    /// playing does nothing, unless it has finished.
+    pub fn play(&mut self, name: &str) {
+        let clip = self
+            .clips
+            .iter()
+            .position(|c| c.name == name)
+            .unwrap_or_else(|| panic!("Animation clip {name} does not exist"));
+        if clip == self.clip && !self.finished {
+            return;
+        }
+        self.clip = clip;
+        self.frame = 0;
+        self.ticks_in_frame = 0;
+        self.forward = true;
+        self.finished = false;
+    }
+
+    pub fn current_clip(&self) -> &AnimationClip {
+        &self.clips[self.clip]
+    }
+
+    pub fn current_frame(&self) -> &AnimationFrame {
+        &self.current_clip().frames[self.frame]
+    }
+
+    pub fn sprite_coord(&self) -> &SpriteCoordinate {
+        &self.current_frame().sprite_coord
+    }
+
+    /// Returns true once a one-shot clip has stopped at its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advances the clip by one game tick, returns an event if the clip reached its end
+    pub fn tick(&mut self) -> Option<AnimationEvent> {
+        if self.finished {
+            return None;
+        }
+        self.ticks_in_frame += 1;
+        if self.ticks_in_frame < self.current_frame().ticks {
+            return None;
+        }
+        self.ticks_in_frame = 0;
+        self.next_frame()
+    }
+
+    fn next_frame(&mut self) -> Option<AnimationEvent> {
+        let clip = &self.clips[self.clip];
+        let last = clip.frames.len() - 1;
+        let looped = || AnimationEvent::Looped {
+            clip: clip.name.clone(),
+        };
+        match clip.mode {
+            AnimationMode::Loop if self.frame == last => {
+                self.frame = 0;
+                Some(looped())
+            }
+            AnimationMode::Once if self.frame == last => {
+                self.finished = true;
+                Some(AnimationEvent::Finished {
+                    clip: clip.name.clone(),
+                })
+            }
+            AnimationMode::Loop | AnimationMode::Once => {
+                self.frame += 1;
+                None
+            }
+            AnimationMode::PingPong if last == 0 => Some(looped()),
+            AnimationMode::PingPong if self.forward => {
+                match self.frame == last {
+                    true => {
+                        self.forward = false;
+                        self.frame -= 1;
+                    }
+                    false => self.frame += 1,
+                }
+                // Back at the first frame the clip starts over
+                match self.frame == 0 {
+                    true => {
+                        self.forward = true;
+                        Some(looped())
+                    }
+                    false => None,
+                }
+            }
+            AnimationMode::PingPong => {
+                self.frame -= 1;
+                match self.frame == 0 {
+                    true => {
+                        self.forward = true;
+                        Some(looped())
+                    }
+                    false => None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationClip, AnimationEvent, AnimationMode, Animator};
+
+    fn walk(mode: AnimationMode) -> AnimationClip {
+        AnimationClip::new("walk").row(0, 0, 3, 2).mode(mode)
+    }
+
+    /// Ticks the animator `ticks` times and returns the column shown after each tick
+    fn columns(animator: &mut Animator, ticks: usize) -> Vec<f32> {
+        (0..ticks)
+            .map(|_| {
+                animator.tick();
+                animator.sprite_coord().coordinate[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_row_expect_single_cell_frames_along_it() {
+        let clip = AnimationClip::new("walk").row(2, 1, 3, 4);
+
+        let cells: Vec<[f32; 4]> = clip
+            .frames()
+            .iter()
+            .map(|f| f.sprite_coord.coordinate)
+            .collect();
+        assert_eq!(
+            vec![
+                [1.0, 2.0, 2.0, 3.0],
+                [2.0, 2.0, 3.0, 3.0],
+                [3.0, 2.0, 4.0, 3.0]
+            ],
+            cells
+        );
+        assert_eq!(12, clip.duration());
+    }
+
+    #[test]
+    fn given_looping_clip_expect_first_frame_after_last() {
+        let mut animator = Animator::new(walk(AnimationMode::Loop));
+
+        assert_eq!(
+            vec![0.0, 1.0, 1.0, 2.0, 2.0, 0.0, 0.0, 1.0],
+            columns(&mut animator, 8)
+        );
+    }
+
+    #[test]
+    fn given_ping_pong_clip_expect_frames_played_back_and_forth() {
+        let mut animator = Animator::new(
+            AnimationClip::new("walk")
+                .row(0, 0, 3, 1)
+                .mode(AnimationMode::PingPong),
+        );
+
+        assert_eq!(
+            vec![1.0, 2.0, 1.0, 0.0, 1.0, 2.0, 1.0],
+            columns(&mut animator, 7)
+        );
+    }
+
+    #[test]
+    fn given_one_shot_clip_expect_stop_at_last_frame_with_one_event() {
+        let mut animator = Animator::new(walk(AnimationMode::Once));
+
+        let events: Vec<Option<AnimationEvent>> = (0..10).map(|_| animator.tick()).collect();
+        let finished = AnimationEvent::Finished {
+            clip: "walk".to_string(),
+        };
+        assert_eq!(Some(&Some(finished)), events.get(5));
+        assert_eq!(1, events.iter().flatten().count());
+        assert!(animator.is_finished());
+        assert_eq!(2.0, animator.sprite_coord().coordinate[0]);
+    }
+
+    #[test]
+    fn given_looping_clip_expect_event_at_every_end() {
+        let mut animator = Animator::new(walk(AnimationMode::Loop));
+
+        let events: Vec<AnimationEvent> = (0..12).filter_map(|_| animator.tick()).collect();
+        let looped = AnimationEvent::Looped {
+            clip: "walk".to_string(),
+        };
+        assert_eq!(vec![looped.clone(), looped], events);
+    }
+
+    #[test]
+    fn given_other_clip_played_expect_restart_at_its_first_frame() {
+        let mut animator = Animator::new(walk(AnimationMode::Loop))
+            .clip(AnimationClip::new("jump").row(1, 4, 2, 1));
+        columns(&mut animator, 3);
+
+        animator.play("walk");
+        assert_eq!(1.0, animator.sprite_coord().coordinate[0]);
+        animator.play("jump");
+        assert_eq!([4.0, 1.0, 5.0, 2.0], animator.sprite_coord().coordinate);
+    }
+
+    #[test]
+    #[should_panic]
+    fn given_unknown_clip_expect_panic() {
+        Animator::new(walk(AnimationMode::Loop)).play("run");
+    }
+}
@@ -38,7 +38,7 @@ impl Default for SpriteSheet {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SpriteCoordinate {
     pub coordinate: [f32; 4],
 }
@@ -0,0 +1,88 @@
+/// How the color of an instance is combined with what is already drawn. The shaders
+/// write colors premultiplied by their alpha, such that every mode fades out with the
+/// alpha of the instance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Draws over the target, covering it by the alpha of the color
+    #[default]
+    Alpha,
+    /// Adds the color to the target, used for light and glow
+    Additive,
+    /// Multiplies the target with the color, used for shadows and tinting
+    Multiply,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 3] = [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply];
+
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        let color = match self {
+            BlendMode::Alpha => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            BlendMode::Additive => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            // dst * src * alpha + dst * (1 - alpha)
+            BlendMode::Multiply => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        wgpu::BlendState {
+            color,
+            alpha: wgpu::BlendComponent::OVER,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlendMode;
+
+    /// Blends one color channel of a premultiplied source into the target like the gpu
+    fn blend(mode: BlendMode, color: f32, alpha: f32, target: f32) -> f32 {
+        let state = mode.blend_state().color;
+        let factor = |f: wgpu::BlendFactor| match f {
+            wgpu::BlendFactor::One => 1.0,
+            wgpu::BlendFactor::OneMinusSrcAlpha => 1.0 - alpha,
+            wgpu::BlendFactor::Dst => target,
+            _ => unimplemented!(),
+        };
+        let source = color * alpha;
+        source * factor(state.src_factor) + target * factor(state.dst_factor)
+    }
+
+    #[test]
+    fn given_alpha_blend_expect_premultiplied_alpha_blending() {
+        assert_eq!(
+            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Alpha.blend_state()
+        );
+        assert_eq!(0.6, blend(BlendMode::Alpha, 1.0, 0.5, 0.2));
+    }
+
+    #[test]
+    fn given_transparent_color_expect_target_unchanged_for_every_mode() {
+        for mode in BlendMode::ALL {
+            assert_eq!(0.4, blend(mode, 0.8, 0.0, 0.4), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn given_opaque_color_expect_sum_and_product_of_additive_and_multiply() {
+        assert_eq!(0.75, blend(BlendMode::Additive, 0.5, 1.0, 0.25));
+        assert_eq!(0.125, blend(BlendMode::Multiply, 0.5, 1.0, 0.25));
+    }
+
+    #[test]
+    fn given_half_transparent_multiply_expect_half_way_to_product() {
+        // Half way between the target 0.5 and the product 0.25
+        assert_eq!(0.375, blend(BlendMode::Multiply, 0.5, 0.5, 0.5));
+    }
+}
@@ -0,0 +1,292 @@
+use cgmath::{InnerSpace, Matrix2, Matrix4, Rad, Vector2, Vector3, Vector4};
+
+/// The view-projection matrix as it is passed to the shaders, column major
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view_projection: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    /// Maps pixels around the center of the viewport to clip space, used for overlays
+    /// that stay in place when the camera moves
+    pub fn screen(viewport: (u32, u32)) -> Self {
+        Camera2D::new(viewport).uniform()
+    }
+}
+
+/// How the camera follows a target with `Camera2D::track`. The target may move freely
+/// within the deadzone, a box of `width` by `height` world units around the center of
+/// the view. Once it leaves the box the camera closes the distance at a rate of
+/// `stiffness` per second, an infinite stiffness keeps the target on the edge of the
+/// box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraFollow {
+    half_extents: Vector2<f32>,
+    stiffness: f32,
+}
+
+impl CameraFollow {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            half_extents: Vector2::new(width.abs() / 2.0, height.abs() / 2.0),
+            stiffness: f32::INFINITY,
+        }
+    }
+
+    pub fn stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+/// Camera looking at the plane of the world. `position` is the world point shown at
+/// the center of the viewport, `zoom` the number of pixels per world unit and
+/// `rotation` the counter clockwise rotation of the camera in radians. The viewport is
+/// the size of the window in physical pixels.
+///
+/// Screen coordinates are physical pixels from the top left corner of the window with
+/// y pointing down, world coordinates have y pointing up. The default camera maps one
+/// world unit to one pixel with the origin at the center of the window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    position: Vector2<f32>,
+    zoom: f32,
+    rotation: f32,
+    viewport: (u32, u32),
+    follow: CameraFollow,
+}
+
+impl Camera2D {
+    pub fn new(viewport: (u32, u32)) -> Self {
+        Self {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            follow: CameraFollow::default(),
+        }
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.position.into()
+    }
+
+    pub fn set_position(&mut self, position: [f32; 2]) {
+        self.position = position.into();
+    }
+
+    /// Moves the camera by `offset` world units
+    pub fn pan(&mut self, offset: [f32; 2]) {
+        self.position += Vector2::from(offset);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        debug_assert!(zoom > 0.0, "Expected zoom larger than 0, found {zoom}");
+        self.zoom = zoom;
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    pub fn viewport(&self) -> (u32, u32) {
+        self.viewport
+    }
+
+    pub fn set_viewport(&mut self, viewport: (u32, u32)) {
+        self.viewport = viewport;
+    }
+
+    pub fn set_follow(&mut self, follow: CameraFollow) {
+        self.follow = follow;
+    }
+
+    /// Moves the camera towards `target` as set by `set_follow`, `dt` is the time in
+    /// seconds since the last call. The deadzone is aligned with the view, it rotates
+    /// with the camera.
+    pub fn track(&mut self, target: [f32; 2], dt: f32) {
+        let to_view = Matrix2::from_angle(Rad(-self.rotation));
+        let offset = to_view * (Vector2::from(target) - self.position);
+        let half_extents = self.follow.half_extents;
+        let outside = Vector2::new(
+            offset.x - offset.x.clamp(-half_extents.x, half_extents.x),
+            offset.y - offset.y.clamp(-half_extents.y, half_extents.y),
+        );
+        if outside.magnitude2() == 0.0 {
+            return;
+        }
+        let fraction = match self.follow.stiffness {
+            stiffness if stiffness.is_infinite() => 1.0,
+            stiffness => 1.0 - (-stiffness * dt).exp(),
+        };
+        let to_world = Matrix2::from_angle(Rad(self.rotation));
+        self.position += to_world * outside * fraction;
+    }
+
+    /// Transforms world coordinates to clip space. The z coordinate is kept as is.
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        let (width, height) = self.viewport;
+        let projection = Matrix4::from_nonuniform_scale(
+            2.0 * self.zoom / width.max(1) as f32,
+            2.0 * self.zoom / height.max(1) as f32,
+            1.0,
+        );
+        let rotation = Matrix4::from_angle_z(Rad(-self.rotation));
+        let translation =
+            Matrix4::from_translation(Vector3::new(-self.position.x, -self.position.y, 0.0));
+        projection * rotation * translation
+    }
+
+    pub fn uniform(&self) -> CameraUniform {
+        CameraUniform {
+            view_projection: self.view_projection().into(),
+        }
+    }
+
+    /// Returns the world point under the pixel at `screen`
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        let (width, height) = self.viewport;
+        let from_center = Vector2::new(
+            screen[0] - width as f32 / 2.0,
+            height as f32 / 2.0 - screen[1],
+        );
+        let world =
+            self.position + Matrix2::from_angle(Rad(self.rotation)) * from_center / self.zoom;
+        world.into()
+    }
+
+    /// Returns the pixel showing the world point `world`
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let clip = self.view_projection() * Vector4::new(world[0], world[1], 0.0, 1.0);
+        let (width, height) = self.viewport;
+        [
+            (clip.x + 1.0) * width as f32 / 2.0,
+            (1.0 - clip.y) * height as f32 / 2.0,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector4;
+
+    use super::{Camera2D, CameraFollow};
+
+    fn assert_close(expected: [f32; 2], output: [f32; 2]) {
+        assert!(
+            (expected[0] - output[0]).abs() < 1e-3 && (expected[1] - output[1]).abs() < 1e-3,
+            "Expected {expected:?} but found {output:?}"
+        );
+    }
+
+    #[test]
+    fn given_default_camera_expect_pixels_around_window_center() {
+        let camera = Camera2D::new((800, 600));
+
+        assert_close([0.0, 0.0], camera.screen_to_world([400.0, 300.0]));
+        assert_close([-400.0, 300.0], camera.screen_to_world([0.0, 0.0]));
+        assert_close([100.0, -50.0], camera.screen_to_world([500.0, 350.0]));
+    }
+
+    #[test]
+    fn given_default_camera_expect_same_clip_coordinates_as_window_size_scaling() {
+        let camera = Camera2D::new((800, 600));
+        let clip = camera.view_projection() * Vector4::new(200.0, -150.0, 0.5, 1.0);
+
+        assert_eq!(Vector4::new(0.5, -0.5, 0.5, 1.0), clip);
+    }
+
+    #[test]
+    fn given_panned_zoomed_and_rotated_camera_expect_screen_to_world_to_invert_world_to_screen() {
+        let mut camera = Camera2D::new((1024, 768));
+        camera.set_position([120.0, -40.0]);
+        camera.set_zoom(2.5);
+        camera.set_rotation(0.7);
+
+        for world in [[0.0, 0.0], [120.0, -40.0], [-300.0, 250.0]] {
+            assert_close(world, camera.screen_to_world(camera.world_to_screen(world)));
+        }
+        assert_close([512.0, 384.0], camera.world_to_screen([120.0, -40.0]));
+    }
+
+    #[test]
+    fn given_zoom_expect_world_unit_to_cover_zoom_pixels() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_zoom(4.0);
+        camera.pan([10.0, 0.0]);
+
+        assert_close([404.0, 300.0], camera.world_to_screen([11.0, 0.0]));
+    }
+
+    #[test]
+    fn given_quarter_turn_expect_world_up_to_point_right_on_screen() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_rotation(std::f32::consts::FRAC_PI_2);
+
+        // Turning the camera counter clockwise turns the world clockwise on screen
+        assert_close([500.0, 300.0], camera.world_to_screen([0.0, 100.0]));
+    }
+
+    #[test]
+    fn given_target_inside_deadzone_expect_camera_to_stay() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_follow(CameraFollow::new(100.0, 50.0));
+
+        camera.track([40.0, -20.0], 1.0 / 60.0);
+
+        assert_eq!([0.0, 0.0], camera.position());
+    }
+
+    #[test]
+    fn given_target_outside_deadzone_expect_target_on_edge_of_deadzone() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_follow(CameraFollow::new(100.0, 50.0));
+
+        camera.track([80.0, -10.0], 1.0 / 60.0);
+
+        assert_close([30.0, 0.0], camera.position());
+    }
+
+    #[test]
+    fn given_stiffness_expect_camera_to_ease_towards_target() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_follow(CameraFollow::new(0.0, 0.0).stiffness(5.0));
+
+        camera.track([100.0, 0.0], 0.1);
+        let first = camera.position();
+        for _ in 0..100 {
+            camera.track([100.0, 0.0], 0.1);
+        }
+
+        assert!(0.0 < first[0] && first[0] < 100.0, "Found {first:?}");
+        assert_close([100.0, 0.0], camera.position());
+    }
+
+    #[test]
+    fn given_rotated_camera_expect_deadzone_aligned_with_view() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.set_rotation(std::f32::consts::FRAC_PI_2);
+        camera.set_follow(CameraFollow::new(100.0, 20.0));
+
+        // Along the world y axis the deadzone is 100 wide after the quarter turn
+        camera.track([0.0, 40.0], 1.0 / 60.0);
+        assert_close([0.0, 0.0], camera.position());
+        camera.track([40.0, 0.0], 1.0 / 60.0);
+        assert_close([30.0, 0.0], camera.position());
+    }
+}
@@ -0,0 +1,398 @@
+use std::ops::Range;
+
+use super::{
+    asset::font::FontInstance,
+    blend_mode::BlendMode,
+    layer::{SpriteBatch, SpriteInstance},
+    post_process::PostProcessFilterId,
+    render_pass::primitive_pass::PrimitiveSpace,
+    shapes::{
+        circle::CircleInstance,
+        primitive::{PrimitiveVertex, Primitives},
+        rectangle::RectangleInstance,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FrameCommand {
+    Clear,
+    Background,
+    Rectangles(Range<u32>, BlendMode),
+    Circles(Range<u32>, BlendMode),
+    Text(Range<u32>),
+    Primitives(Range<u32>, BlendMode, PrimitiveSpace),
+    Filter(PostProcessFilterId),
+}
+
+/// A part of the frame recorded as one pass of the command encoder
+#[derive(Debug, PartialEq)]
+pub(crate) enum FramePass<'a> {
+    Draw {
+        clear: bool,
+        draws: &'a [FrameCommand],
+    },
+    Filter(PostProcessFilterId),
+}
+
+/// The draws of a frame in the order they are drawn, rendered and presented with a
+/// single submit by `RenderEngineControl::render_frame`.
+///
+/// The instances of all draws of a kind are written to the instance buffer at once,
+/// each draw then renders its own range of it. Draws between two filters share one
+/// render pass, a filter reads everything drawn before it.
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    commands: Vec<FrameCommand>,
+    rectangles: Vec<RectangleInstance>,
+    circles: Vec<CircleInstance>,
+    text: Vec<FontInstance>,
+    primitives: Vec<PrimitiveVertex>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears everything drawn before
+    pub fn clear(mut self) -> Self {
+        self.commands.push(FrameCommand::Clear);
+        self
+    }
+
+    pub fn background(mut self) -> Self {
+        self.commands.push(FrameCommand::Background);
+        self
+    }
+
+    pub fn rectangles(self, instances: &[RectangleInstance]) -> Self {
+        self.blended_rectangles(instances, BlendMode::Alpha)
+    }
+
+    pub fn blended_rectangles(
+        mut self,
+        instances: &[RectangleInstance],
+        blend_mode: BlendMode,
+    ) -> Self {
+        let range = Self::append(&mut self.rectangles, instances);
+        self.commands
+            .push(FrameCommand::Rectangles(range, blend_mode));
+        self
+    }
+
+    pub fn circles(self, instances: &[CircleInstance]) -> Self {
+        self.blended_circles(instances, BlendMode::Alpha)
+    }
+
+    pub fn blended_circles(mut self, instances: &[CircleInstance], blend_mode: BlendMode) -> Self {
+        let range = Self::append(&mut self.circles, instances);
+        self.commands.push(FrameCommand::Circles(range, blend_mode));
+        self
+    }
+
+    pub fn text(mut self, instances: &[FontInstance]) -> Self {
+        let range = Self::append(&mut self.text, instances);
+        self.commands.push(FrameCommand::Text(range));
+        self
+    }
+
+    /// Draws the primitives in world coordinates
+    pub fn primitives(self, primitives: &Primitives) -> Self {
+        self.blended_primitives(primitives, BlendMode::Alpha, PrimitiveSpace::World)
+    }
+
+    /// Draws the primitives in pixels around the center of the window, for overlays
+    pub fn screen_primitives(self, primitives: &Primitives) -> Self {
+        self.blended_primitives(primitives, BlendMode::Alpha, PrimitiveSpace::Screen)
+    }
+
+    pub fn blended_primitives(
+        mut self,
+        primitives: &Primitives,
+        blend_mode: BlendMode,
+        space: PrimitiveSpace,
+    ) -> Self {
+        let range = Self::append(&mut self.primitives, primitives.vertices());
+        self.commands
+            .push(FrameCommand::Primitives(range, blend_mode, space));
+        self
+    }
+
+    /// Draws the sprites of the batch from back to front. Each run of sprites of the
+    /// same shape kind and blend mode becomes one draw, such that sprites of different
+    /// kinds overlap in the order of their layers and z values.
+    pub fn sprites(mut self, batch: &SpriteBatch) -> Self {
+        let sorted = batch.sorted();
+        for run in sorted.chunk_by(|(mode_a, a), (mode_b, b)| {
+            mode_a == mode_b && std::mem::discriminant(a) == std::mem::discriminant(b)
+        }) {
+            let (blend_mode, first) = run[0];
+            match first {
+                SpriteInstance::Circle(_) => {
+                    let circles: Vec<CircleInstance> = run
+                        .iter()
+                        .filter_map(|(_, s)| match s {
+                            SpriteInstance::Circle(c) => Some(*c),
+                            _ => None,
+                        })
+                        .collect();
+                    self = self.blended_circles(&circles, blend_mode);
+                }
+                SpriteInstance::Rectangle(_) => {
+                    let rectangles: Vec<RectangleInstance> = run
+                        .iter()
+                        .filter_map(|(_, s)| match s {
+                            SpriteInstance::Rectangle(r) => Some(*r),
+                            _ => None,
+                        })
+                        .collect();
+                    self = self.blended_rectangles(&rectangles, blend_mode);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn post_process_filter(mut self, filter_id: PostProcessFilterId) -> Self {
+        self.commands.push(FrameCommand::Filter(filter_id));
+        self
+    }
+
+    fn append<T: Copy>(all: &mut Vec<T>, instances: &[T]) -> Range<u32> {
+        let start = all.len() as u32;
+        all.extend_from_slice(instances);
+        start..all.len() as u32
+    }
+
+    pub(crate) fn rectangle_instances(&self) -> &[RectangleInstance] {
+        &self.rectangles
+    }
+
+    pub(crate) fn circle_instances(&self) -> &[CircleInstance] {
+        &self.circles
+    }
+
+    pub(crate) fn text_instances(&self) -> &[FontInstance] {
+        &self.text
+    }
+
+    pub(crate) fn primitive_vertices(&self) -> &[PrimitiveVertex] {
+        &self.primitives
+    }
+
+    /// Splits the commands into passes. A pass of draws ends at a filter or a clear,
+    /// passes without draws are skipped unless they clear.
+    pub(crate) fn passes(&self) -> Vec<FramePass<'_>> {
+        let mut passes = vec![];
+        let mut start = 0;
+        let mut clear = false;
+        for (i, command) in self.commands.iter().enumerate() {
+            if matches!(command, FrameCommand::Filter(_) | FrameCommand::Clear) {
+                if clear || start < i {
+                    passes.push(FramePass::Draw {
+                        clear,
+                        draws: &self.commands[start..i],
+                    });
+                }
+                start = i + 1;
+                clear = false;
+            }
+            match command {
+                FrameCommand::Filter(filter_id) => passes.push(FramePass::Filter(*filter_id)),
+                FrameCommand::Clear => clear = true,
+                _ => (),
+            }
+        }
+        if clear || start < self.commands.len() {
+            passes.push(FramePass::Draw {
+                clear,
+                draws: &self.commands[start..],
+            });
+        }
+        passes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::{Frame, FrameCommand, FramePass};
+    use crate::engine::renderer_engine::asset::font::FontInstance;
+    use crate::engine::renderer_engine::blend_mode::BlendMode;
+    use crate::engine::renderer_engine::layer::{RenderLayers, SpriteBatch};
+    use crate::engine::renderer_engine::post_process::PostProcessFilterId;
+    use crate::engine::renderer_engine::render_pass::primitive_pass::PrimitiveSpace;
+    use crate::engine::renderer_engine::shapes::circle::CircleInstance;
+    use crate::engine::renderer_engine::shapes::primitive::Primitives;
+    use crate::engine::renderer_engine::shapes::rectangle::RectangleInstance;
+
+    #[test]
+    fn given_draws_of_same_kind_expect_consecutive_instance_ranges() {
+        let circles = vec![CircleInstance::default(); 3];
+        let text = vec![FontInstance::zeroed(); 2];
+        let frame = Frame::new()
+            .circles(&circles)
+            .text(&text)
+            .circles(&circles[..1])
+            .text(&text);
+
+        assert_eq!(4, frame.circle_instances().len());
+        assert_eq!(4, frame.text_instances().len());
+        assert_eq!(
+            vec![FramePass::Draw {
+                clear: false,
+                draws: &[
+                    FrameCommand::Circles(0..3, BlendMode::Alpha),
+                    FrameCommand::Text(0..2),
+                    FrameCommand::Circles(3..4, BlendMode::Alpha),
+                    FrameCommand::Text(2..4),
+                ]
+            }],
+            frame.passes()
+        );
+    }
+
+    #[test]
+    fn given_filter_between_draws_expect_draw_order_kept_across_passes() {
+        let circles = vec![CircleInstance::default(); 2];
+        let frame = Frame::new()
+            .clear()
+            .background()
+            .rectangles(&[])
+            .post_process_filter(PostProcessFilterId::Gray)
+            .circles(&circles);
+
+        assert_eq!(
+            vec![
+                FramePass::Draw {
+                    clear: true,
+                    draws: &[
+                        FrameCommand::Background,
+                        FrameCommand::Rectangles(0..0, BlendMode::Alpha)
+                    ]
+                },
+                FramePass::Filter(PostProcessFilterId::Gray),
+                FramePass::Draw {
+                    clear: false,
+                    draws: &[FrameCommand::Circles(0..2, BlendMode::Alpha)]
+                },
+            ],
+            frame.passes()
+        );
+    }
+
+    #[test]
+    fn given_clear_without_draws_expect_pass_that_only_clears() {
+        let frame = Frame::new()
+            .post_process_filter(PostProcessFilterId::Tint)
+            .clear();
+
+        assert_eq!(
+            vec![
+                FramePass::Filter(PostProcessFilterId::Tint),
+                FramePass::Draw {
+                    clear: true,
+                    draws: &[]
+                },
+            ],
+            frame.passes()
+        );
+    }
+
+    #[test]
+    fn given_clear_between_draws_expect_new_pass_that_clears() {
+        let frame = Frame::new().background().clear().text(&[]);
+
+        assert_eq!(
+            vec![
+                FramePass::Draw {
+                    clear: false,
+                    draws: &[FrameCommand::Background]
+                },
+                FramePass::Draw {
+                    clear: true,
+                    draws: &[FrameCommand::Text(0..0)]
+                },
+            ],
+            frame.passes()
+        );
+    }
+
+    #[test]
+    fn given_sprites_of_mixed_kinds_expect_one_draw_per_run_in_depth_order() {
+        let layers = RenderLayers::new().layer("ground").layer("bodies");
+        let at_z = |z: f32| [0.0, 0.0, z];
+        let mut batch = SpriteBatch::new(&layers);
+        batch.add(
+            "bodies",
+            CircleInstance {
+                position: at_z(2.0),
+                ..Default::default()
+            },
+        );
+        batch.add(
+            "bodies",
+            RectangleInstance {
+                position: at_z(1.0),
+                ..Default::default()
+            },
+        );
+        batch.add(
+            "bodies",
+            CircleInstance {
+                position: at_z(-1.0),
+                ..Default::default()
+            },
+        );
+        batch.add("ground", RectangleInstance::default());
+        let frame = Frame::new()
+            .circles(&[CircleInstance::default()])
+            .sprites(&batch);
+
+        // The circle behind the rectangle on the same layer is drawn before it
+        assert_eq!(
+            vec![FramePass::Draw {
+                clear: false,
+                draws: &[
+                    FrameCommand::Circles(0..1, BlendMode::Alpha),
+                    FrameCommand::Rectangles(0..1, BlendMode::Alpha),
+                    FrameCommand::Circles(1..2, BlendMode::Alpha),
+                    FrameCommand::Rectangles(1..2, BlendMode::Alpha),
+                    FrameCommand::Circles(2..3, BlendMode::Alpha),
+                ]
+            }],
+            frame.passes()
+        );
+        assert_eq!(-1.0, frame.circle_instances()[1].position[2]);
+        assert_eq!(2.0, frame.circle_instances()[2].position[2]);
+    }
+
+    #[test]
+    fn given_world_and_screen_primitives_expect_consecutive_vertex_ranges() {
+        let mut primitives = Primitives::new();
+        primitives.line([0.0, 0.0], [1.0, 0.0], 1.0, [1.0, 1.0, 1.0, 1.0]);
+        let frame = Frame::new()
+            .primitives(&primitives)
+            .circles(&[])
+            .screen_primitives(&primitives);
+
+        assert_eq!(12, frame.primitive_vertices().len());
+        assert_eq!(
+            vec![FramePass::Draw {
+                clear: false,
+                draws: &[
+                    FrameCommand::Primitives(0..6, BlendMode::Alpha, PrimitiveSpace::World),
+                    FrameCommand::Circles(0..0, BlendMode::Alpha),
+                    FrameCommand::Primitives(6..12, BlendMode::Alpha, PrimitiveSpace::Screen),
+                ]
+            }],
+            frame.passes()
+        );
+    }
+
+    #[test]
+    fn given_empty_frame_expect_no_passes() {
+        assert!(Frame::new().passes().is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use std::marker::PhantomData;
+
+use super::{graphics_context::GraphicsContext, render_error::RenderError};
+
+/// How many instances an `InstanceBuffer` holds. The capacity doubles when a frame
+/// needs more instances than it has, up to the limit if one is set. With shrinking
+/// enabled the capacity is halved once a frame uses at most a quarter of it, but never
+/// below the initial capacity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InstanceCapacity {
+    capacity: usize,
+    initial: usize,
+    limit: Option<usize>,
+    shrink: bool,
+}
+
+impl InstanceCapacity {
+    pub fn new(initial: usize) -> Self {
+        let initial = initial.max(1);
+        Self {
+            capacity: initial,
+            initial,
+            limit: None,
+            shrink: false,
+        }
+    }
+
+    /// The largest number of instances of a single frame
+    pub fn limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        if let Some(limit) = limit {
+            self.initial = self.initial.min(limit.max(1));
+            self.capacity = self.initial;
+        }
+        self
+    }
+
+    pub fn shrink(mut self, shrink: bool) -> Self {
+        self.shrink = shrink;
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the capacity needed for a frame of `len` instances, or `None` if the
+    /// current capacity is kept
+    fn resize_for(&self, len: usize) -> Option<usize> {
+        if len > self.capacity {
+            let grown = len.max(self.capacity * 2);
+            Some(self.limit.map_or(grown, |limit| grown.min(limit)))
+        } else if self.shrink && len <= self.capacity / 4 && self.capacity > self.initial {
+            Some((self.capacity / 2).max(self.initial))
+        } else {
+            None
+        }
+    }
+
+    /// Checks `len` against the limit and updates the capacity. Returns true if the
+    /// capacity changed.
+    pub fn reserve(&mut self, label: &'static str, len: usize) -> Result<bool, RenderError> {
+        if let Some(limit) = self.limit {
+            if len > limit {
+                return Err(RenderError::InstanceLimitExceeded {
+                    label,
+                    requested: len,
+                    limit,
+                });
+            }
+        }
+        match self.resize_for(len) {
+            Some(capacity) => {
+                self.capacity = capacity;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Vertex buffer of instances of `T` that is recreated with a new capacity when the
+/// number of instances of a frame does not fit, see `InstanceCapacity`
+pub struct InstanceBuffer<T: bytemuck::Pod> {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    capacity: InstanceCapacity,
+    len: usize,
+    instance: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> InstanceBuffer<T> {
+    pub fn new(g_ctx: &GraphicsContext, label: &'static str, capacity: InstanceCapacity) -> Self {
+        Self {
+            label,
+            buffer: Self::create_buffer(g_ctx, label, capacity.capacity())
+                .expect("Expected the initial capacity to fit into a buffer"),
+            capacity,
+            len: 0,
+            instance: PhantomData,
+        }
+    }
+
+    fn create_buffer(
+        g_ctx: &GraphicsContext,
+        label: &'static str,
+        capacity: usize,
+    ) -> Result<wgpu::Buffer, RenderError> {
+        Ok(g_ctx.create_buffer(
+            label,
+            buffer_size::<T>(label, capacity)?,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            false,
+        ))
+    }
+
+    /// Writes the instances to the start of the buffer, recreating the buffer first if
+    /// the capacity changes. Fails if there are more instances than the limit or than
+    /// fit into a buffer, the buffer is then kept as it was.
+    pub fn write(&mut self, g_ctx: &GraphicsContext, instances: &[T]) -> Result<(), RenderError> {
+        let previous = self.capacity;
+        if self.capacity.reserve(self.label, instances.len())? {
+            match Self::create_buffer(g_ctx, self.label, self.capacity.capacity()) {
+                Ok(buffer) => self.buffer = buffer,
+                Err(e) => {
+                    self.capacity = previous;
+                    return Err(e);
+                }
+            }
+        }
+        g_ctx
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        self.len = instances.len();
+        Ok(())
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// The number of instances of the last write
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.capacity()
+    }
+}
+
+/// The size in bytes of a buffer of `capacity` instances of `T`, buffers are sized with
+/// a `u32`
+fn buffer_size<T>(label: &'static str, capacity: usize) -> Result<u32, RenderError> {
+    std::mem::size_of::<T>()
+        .checked_mul(capacity)
+        .and_then(|size| u32::try_from(size).ok())
+        .ok_or(RenderError::BufferTooLarge { label, capacity })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buffer_size, InstanceCapacity};
+    use crate::engine::renderer_engine::render_error::RenderError;
+
+    #[test]
+    fn given_more_instances_than_capacity_expect_capacity_to_double() {
+        let mut capacity = InstanceCapacity::new(16);
+
+        assert_eq!(Ok(false), capacity.reserve("test", 16));
+        assert_eq!(Ok(true), capacity.reserve("test", 17));
+        assert_eq!(32, capacity.capacity());
+        assert_eq!(Ok(true), capacity.reserve("test", 100));
+        assert_eq!(100, capacity.capacity());
+    }
+
+    #[test]
+    fn given_limit_expect_growth_capped_and_error_past_limit() {
+        let mut capacity = InstanceCapacity::new(16).limit(Some(20));
+
+        assert_eq!(Ok(true), capacity.reserve("test", 17));
+        assert_eq!(20, capacity.capacity());
+        assert_eq!(
+            Err(RenderError::InstanceLimitExceeded {
+                label: "test",
+                requested: 21,
+                limit: 20
+            }),
+            capacity.reserve("test", 21)
+        );
+        assert_eq!(20, capacity.capacity());
+    }
+
+    #[test]
+    fn given_shrink_expect_capacity_halved_down_to_initial() {
+        let mut capacity = InstanceCapacity::new(8).shrink(true);
+        capacity.reserve("test", 64).unwrap();
+
+        assert_eq!(Ok(false), capacity.reserve("test", 17));
+        assert_eq!(Ok(true), capacity.reserve("test", 16));
+        assert_eq!(32, capacity.capacity());
+        for _ in 0..10 {
+            capacity.reserve("test", 0).unwrap();
+        }
+        assert_eq!(8, capacity.capacity());
+    }
+
+    #[test]
+    fn given_no_shrink_expect_capacity_kept() {
+        let mut capacity = InstanceCapacity::new(8);
+        capacity.reserve("test", 64).unwrap();
+
+        assert_eq!(Ok(false), capacity.reserve("test", 0));
+        assert_eq!(64, capacity.capacity());
+    }
+
+    #[test]
+    fn given_zero_initial_capacity_expect_buffer_of_one_instance() {
+        assert_eq!(1, InstanceCapacity::new(0).capacity());
+    }
+
+    #[test]
+    fn given_capacity_fitting_into_a_buffer_expect_size_in_bytes() {
+        assert_eq!(Ok(64), buffer_size::<[f32; 4]>("test", 4));
+    }
+
+    #[test]
+    fn given_capacity_past_the_largest_buffer_expect_error() {
+        let capacity = u32::MAX as usize / 16 + 1;
+
+        assert_eq!(
+            Err(RenderError::BufferTooLarge {
+                label: "test",
+                capacity
+            }),
+            buffer_size::<[f32; 4]>("test", capacity)
+        );
+        assert_eq!(
+            Err(RenderError::BufferTooLarge {
+                label: "test",
+                capacity: usize::MAX
+            }),
+            buffer_size::<[f32; 4]>("test", usize::MAX)
+        );
+    }
+}
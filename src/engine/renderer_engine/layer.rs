@@ -0,0 +1,246 @@
+use super::{
+    blend_mode::BlendMode,
+    shapes::{circle::CircleInstance, rectangle::RectangleInstance},
+};
+
+/// Named layers from bottom to top. A sprite on a higher layer is always drawn over a
+/// sprite on a lower layer, whatever their z values. The sprites of a layer are blended
+/// with the blend mode of the layer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderLayers {
+    layers: Vec<(String, BlendMode)>,
+}
+
+impl RenderLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer on top of the layers added before, blended with `BlendMode::Alpha`
+    pub fn layer(mut self, name: &str) -> Self {
+        debug_assert!(
+            !self.layers.iter().any(|(n, _)| n == name),
+            "Expected unique layer names, found {name} twice"
+        );
+        self.layers.push((name.to_string(), BlendMode::default()));
+        self
+    }
+
+    /// Sets the blend mode of the layer added last
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        let (_, mode) = self
+            .layers
+            .last_mut()
+            .expect("Expected a layer to set the blend mode of");
+        *mode = blend_mode;
+        self
+    }
+
+    /// Returns the position of the layer counted from the bottom
+    pub fn depth_of(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|(n, _)| n == name)
+    }
+
+    pub fn blend_mode_of(&self, name: &str) -> Option<BlendMode> {
+        self.layers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, mode)| *mode)
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+/// An instance of any shape kind, ordered by the z coordinate of its position
+#[derive(Clone, Copy, Debug)]
+pub enum SpriteInstance {
+    Circle(CircleInstance),
+    Rectangle(RectangleInstance),
+}
+
+impl SpriteInstance {
+    pub fn z(&self) -> f32 {
+        match self {
+            SpriteInstance::Circle(c) => c.position[2],
+            SpriteInstance::Rectangle(r) => r.position[2],
+        }
+    }
+}
+
+impl From<CircleInstance> for SpriteInstance {
+    fn from(instance: CircleInstance) -> Self {
+        SpriteInstance::Circle(instance)
+    }
+}
+
+impl From<RectangleInstance> for SpriteInstance {
+    fn from(instance: RectangleInstance) -> Self {
+        SpriteInstance::Rectangle(instance)
+    }
+}
+
+/// Sprites of all shape kinds drawn in order of layer, then z and then the order they
+/// were added in. Within a layer a sprite with a higher z is drawn over one with a
+/// lower z. Add the batch to a frame with `Frame::sprites`.
+#[derive(Clone, Debug)]
+pub struct SpriteBatch<'l> {
+    layers: &'l RenderLayers,
+    sprites: Vec<(usize, SpriteInstance)>,
+}
+
+impl<'l> SpriteBatch<'l> {
+    pub fn new(layers: &'l RenderLayers) -> Self {
+        Self {
+            layers,
+            sprites: vec![],
+        }
+    }
+
+    pub fn add<S: Into<SpriteInstance>>(&mut self, layer: &str, sprite: S) {
+        let depth = self
+            .layers
+            .depth_of(layer)
+            .unwrap_or_else(|| panic!("Render layer {layer} does not exist"));
+        self.sprites.push((depth, sprite.into()));
+    }
+
+    pub fn extend<S, I>(&mut self, layer: &str, sprites: I)
+    where
+        S: Into<SpriteInstance>,
+        I: IntoIterator<Item = S>,
+    {
+        sprites.into_iter().for_each(|s| self.add(layer, s));
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Returns the sprites from the back to the front with the blend mode of their layer
+    pub fn sorted(&self) -> Vec<(BlendMode, SpriteInstance)> {
+        let mut sprites = self.sprites.clone();
+        // The sort is stable, sprites of equal layer and z keep the order they were added in
+        sprites.sort_by(|(layer_a, a), (layer_b, b)| {
+            layer_a.cmp(layer_b).then(a.z().total_cmp(&b.z()))
+        });
+        sprites
+            .into_iter()
+            .map(|(depth, s)| (self.layers.layers[depth].1, s))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RenderLayers, SpriteBatch, SpriteInstance};
+    use crate::engine::renderer_engine::blend_mode::BlendMode;
+    use crate::engine::renderer_engine::shapes::{
+        circle::CircleInstance, rectangle::RectangleInstance,
+    };
+
+    fn circle(id: f32, z: f32) -> CircleInstance {
+        CircleInstance {
+            position: [0.0, 0.0, z],
+            radius: id,
+            ..Default::default()
+        }
+    }
+
+    fn rectangle(id: f32, z: f32) -> RectangleInstance {
+        RectangleInstance {
+            position: [0.0, 0.0, z],
+            width: id,
+            ..Default::default()
+        }
+    }
+
+    fn ids(sprites: &[(BlendMode, SpriteInstance)]) -> Vec<f32> {
+        sprites
+            .iter()
+            .map(|(_, s)| match s {
+                SpriteInstance::Circle(c) => c.radius,
+                SpriteInstance::Rectangle(r) => r.width,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_layers_expect_depth_from_bottom() {
+        let layers = RenderLayers::new()
+            .layer("ground")
+            .layer("bodies")
+            .layer("ui");
+
+        assert_eq!(Some(0), layers.depth_of("ground"));
+        assert_eq!(Some(2), layers.depth_of("ui"));
+        assert_eq!(None, layers.depth_of("sky"));
+    }
+
+    #[test]
+    fn given_higher_layer_expect_drawn_last_whatever_z() {
+        let layers = RenderLayers::new().layer("ground").layer("bodies");
+        let mut batch = SpriteBatch::new(&layers);
+        batch.add("bodies", circle(1.0, -10.0));
+        batch.add("ground", rectangle(2.0, 10.0));
+
+        assert_eq!(vec![2.0, 1.0], ids(&batch.sorted()));
+    }
+
+    #[test]
+    fn given_circle_with_lower_z_expect_drawn_behind_rectangle() {
+        let layers = RenderLayers::new().layer("bodies");
+        let mut batch = SpriteBatch::new(&layers);
+        batch.add("bodies", rectangle(1.0, 0.5));
+        batch.add("bodies", circle(2.0, -0.5));
+        batch.add("bodies", circle(3.0, 1.5));
+
+        assert_eq!(vec![2.0, 1.0, 3.0], ids(&batch.sorted()));
+    }
+
+    #[test]
+    fn given_equal_layer_and_z_expect_order_added_kept() {
+        let layers = RenderLayers::new().layer("bodies");
+        let mut batch = SpriteBatch::new(&layers);
+        batch.extend("bodies", (1..=5).map(|i| circle(i as f32, 0.0)));
+        batch.add("bodies", rectangle(6.0, 0.0));
+
+        assert_eq!(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], ids(&batch.sorted()));
+    }
+
+    #[test]
+    fn given_layer_blend_modes_expect_sprites_with_mode_of_their_layer() {
+        let layers = RenderLayers::new()
+            .layer("bodies")
+            .layer("lights")
+            .blend_mode(BlendMode::Additive)
+            .layer("ui");
+        let mut batch = SpriteBatch::new(&layers);
+        batch.add("ui", circle(1.0, 0.0));
+        batch.add("lights", circle(2.0, 0.0));
+        batch.add("bodies", circle(3.0, 0.0));
+
+        let modes: Vec<BlendMode> = batch.sorted().iter().map(|(mode, _)| *mode).collect();
+        assert_eq!(
+            vec![BlendMode::Alpha, BlendMode::Additive, BlendMode::Alpha],
+            modes
+        );
+        assert_eq!(Some(BlendMode::Additive), layers.blend_mode_of("lights"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn given_unknown_layer_expect_panic() {
+        let layers = RenderLayers::new().layer("bodies");
+        SpriteBatch::new(&layers).add("ui", circle(1.0, 0.0));
+    }
+}
@@ -1,14 +1,32 @@
+mod animation;
 pub mod asset;
+mod blend_mode;
+mod camera;
+mod frame;
 pub mod graphics_context;
 pub mod instance;
+mod instance_buffer;
+mod layer;
 pub mod post_process;
 mod render_body;
 mod render_engine;
+mod render_error;
 pub mod render_pass;
 pub mod shapes;
+mod texture_registry;
 pub mod util;
 pub mod vertex;
 
+pub use animation::{AnimationClip, AnimationEvent, AnimationFrame, AnimationMode, Animator};
+pub use blend_mode::BlendMode;
+pub use camera::{Camera2D, CameraFollow, CameraUniform};
+pub use frame::Frame;
+pub use instance_buffer::{InstanceBuffer, InstanceCapacity};
+pub use layer::{RenderLayers, SpriteBatch, SpriteInstance};
 pub use render_body::RenderBodyShape;
+pub use render_pass::primitive_pass::PrimitiveSpace;
 pub use render_body::{RenderBody, RenderBodyBuilder};
 pub use render_engine::{RenderEngineControl, RenderEngineControlBuilder};
+pub use render_error::RenderError;
+pub use shapes::primitive::{PrimitivePoint, Primitives};
+pub use texture_registry::{TextureAtlas, TextureHandle, TextureRegion, TextureRegistry, MAX_TEXTURES};
@@ -1,7 +1,7 @@
 pub mod post_process_filter;
 pub mod post_process_pipeline;
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone)]
+#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
 pub enum PostProcessFilterId {
     Gray,
     Tint,
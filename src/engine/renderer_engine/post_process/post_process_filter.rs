@@ -18,39 +18,47 @@ impl PostProcessFilter {
 
         let mut command_encoder = g_ctx.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor { label: self.label.as_deref() });
-        {
-            let target_texture_view = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
-            let mut render_pass = command_encoder.begin_render_pass(
-                &wgpu::RenderPassDescriptor {
-                    label: self.label.as_deref(),
-                    color_attachments: &[
-                        Some(
-                            wgpu::RenderPassColorAttachment {
-                                view: &target_texture_view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Load,
-                                    store: wgpu::StoreOp::Store,
-                                },
-                            }
-                        )],
-                        depth_stencil_attachment: None,
-                        occlusion_query_set: None,
-                        timestamp_writes: None,
-                });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
-            render_pass.set_bind_group(0, input_texture_bind_group, &[]);
-
-            render_pass.draw_indexed(0..6, 0, 0..1);
-        }
+        let target_texture_view = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.record(&mut command_encoder, &target_texture_view, vertex_buffer, index_buffer,
+            index_format, input_texture_bind_group);
 
         g_ctx.queue.submit(Some(command_encoder.finish()));
 
         Ok(())
     }
+
+    /// Records the filter as its own pass of `command_encoder`, the caller submits
+    pub fn record(
+        &self, command_encoder: &mut wgpu::CommandEncoder, target_texture_view: &wgpu::TextureView,
+        vertex_buffer: &wgpu::Buffer, index_buffer: &wgpu::Buffer,
+        index_format: &wgpu::IndexFormat, input_texture_bind_group: &wgpu::BindGroup,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: self.label.as_deref(),
+                color_attachments: &[
+                    Some(
+                        wgpu::RenderPassColorAttachment {
+                            view: target_texture_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                        }
+                    )],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+            });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
+        render_pass.set_bind_group(0, input_texture_bind_group, &[]);
+
+        render_pass.draw_indexed(0..6, 0, 0..1);
+    }
 }
 
 pub struct PostProcessFilterBuilder {
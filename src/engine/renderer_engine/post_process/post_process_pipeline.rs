@@ -8,7 +8,8 @@ use crate::engine::renderer_engine::{
     util::{
         create_sampler,
         create_texture,
-        texture_bind_group_from_texture
+        texture_bind_group_from_texture,
+        texture_bind_group_with_layout,
     },
 };
 
@@ -56,6 +57,36 @@ impl PostProcessPipeline {
         return Ok(pp_ctx.request_other_handle(&texture_handle).unwrap());
     }
 
+    /// Records the filter into `command_encoder` instead of submitting it on its own,
+    /// returns the handle of the texture containing the filtered output
+    pub fn record(
+        &self, command_encoder: &mut wgpu::CommandEncoder, pp_ctx: &PostProcessPipelineContext,
+        filter_id: &PostProcessFilterId, texture_handle: &wgpu::Id<wgpu::Texture>
+    ) -> wgpu::Id<wgpu::Texture> {
+
+        let filter = self.filters.get(filter_id)
+            .expect("Requested post processing using nonexisting filter {filter_id}");
+        let source = pp_ctx.request_bind_group_by_handle(texture_handle)
+            .expect("Target texture handle {texture_handle} does not belong to post process context");
+        let destination = pp_ctx.request_other_texture_by_handle(texture_handle)
+            .expect("Target texture handle {texture_handle} does not belong to post process context")
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        filter.record(command_encoder, &destination, &pp_ctx.vertex_buffer, &pp_ctx.index_buffer,
+            &pp_ctx.index_format, source);
+
+        pp_ctx.request_other_handle(texture_handle).unwrap()
+    }
+
+    /// Records the copy of the texture to the surface into `command_encoder`
+    pub fn record_finalize(
+        &self, command_encoder: &mut wgpu::CommandEncoder, pp_ctx: &PostProcessPipelineContext,
+        texture_handle: &wgpu::Id<wgpu::Texture>, surface_view: &wgpu::TextureView
+    ) {
+        let source = pp_ctx.request_bind_group_by_handle(texture_handle).unwrap();
+        self.identity.record(command_encoder, surface_view, &pp_ctx.vertex_buffer,
+            &pp_ctx.index_buffer, &pp_ctx.index_format, source);
+    }
 
     pub fn finalize(
         &mut self, g_ctx: &GraphicsContext, pp_ctx: &PostProcessPipelineContext,
@@ -119,6 +150,22 @@ impl PostProcessPipelineContext {
             texture_a, texture_b, bind_group_layout }
     }
 
+    /// Recreates both textures with the new size. Handles requested before the resize
+    /// no longer belong to the context, request them again for the next frame.
+    pub fn resize(
+        &mut self, g_ctx: &GraphicsContext, window_size: &winit::dpi::PhysicalSize<u32>
+    ) {
+        // The filters were built with the original layout, the new bind groups use it too
+        let (texture_a, bind_group_a) = Self::create_texture_bind_group_with_layout(
+            g_ctx, window_size, &self.bind_group_layout, "a");
+        let (texture_b, bind_group_b) = Self::create_texture_bind_group_with_layout(
+            g_ctx, window_size, &self.bind_group_layout, "b");
+        self.texture_a = texture_a;
+        self.texture_b = texture_b;
+        self.bind_group_a = bind_group_a;
+        self.bind_group_b = bind_group_b;
+    }
+
     pub fn request_texture_by_handle(
         &self, handle: &wgpu::Id<wgpu::Texture>
     ) -> Option<&wgpu::Texture> {
@@ -193,4 +240,16 @@ impl PostProcessPipelineContext {
         (input_texture, input_texture_bg, input_texture_bg_layout)
     }
 
+    fn create_texture_bind_group_with_layout(
+        ctx: &GraphicsContext, window_size: &winit::dpi::PhysicalSize<u32>,
+        layout: &wgpu::BindGroupLayout, id: &str
+    ) -> (wgpu::Texture, wgpu::BindGroup) {
+        let input_texture = create_texture(
+            ctx, (window_size.width,window_size.height), Some(&format!("{id} texture")));
+        let input_texture_sampler = create_sampler(&ctx.device, Some(&format!("{id} sampler")));
+        let input_texture_bg = texture_bind_group_with_layout(
+            &ctx.device, layout, &input_texture_sampler, &input_texture);
+        (input_texture, input_texture_bg)
+    }
+
 }
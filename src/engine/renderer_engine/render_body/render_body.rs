@@ -1,17 +1,22 @@
 use super::RenderBodyShape;
 use crate::engine::renderer_engine::asset::sprite_sheet::SpriteCoordinate;
-use cgmath::Vector3;
+use crate::engine::renderer_engine::TextureHandle;
+use cgmath::Vector4;
 
 pub struct RenderBody {
     pub shape: RenderBodyShape,
-    pub color: Vector3<f32>,
+    /// Red, green, blue and alpha between 0 and 1
+    pub color: Vector4<f32>,
     pub sprite_coord: SpriteCoordinate,
+    /// The sprite sheet the sprite coordinate is counted in cells of
+    pub texture: TextureHandle,
 }
 
 pub struct RenderBodyBuilder {
     pub shape: Option<RenderBodyShape>,
-    pub color: Option<Vector3<f32>>,
+    pub color: Option<Vector4<f32>>,
     pub sprite_coord: Option<SpriteCoordinate>,
+    pub texture: Option<TextureHandle>,
 }
 
 impl RenderBodyBuilder {
@@ -20,10 +25,11 @@ impl RenderBodyBuilder {
             shape: None,
             color: None,
             sprite_coord: None,
+            texture: None,
         }
     }
 
-    pub fn color(mut self, color: [f32; 3]) -> Self {
+    pub fn color(mut self, color: [f32; 4]) -> Self {
         self.color = Some(color.into());
         self
     }
@@ -33,6 +39,11 @@ impl RenderBodyBuilder {
         self
     }
 
+    pub fn texture(mut self, texture: TextureHandle) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
     pub fn shape(mut self, shape: RenderBodyShape) -> Self {
         self.shape = Some(shape);
         self
@@ -42,7 +53,7 @@ impl RenderBodyBuilder {
         let color = if let Some(c) = self.color {
             c
         } else {
-            [1.0, 0.0, 0.0].into()
+            [1.0, 0.0, 0.0, 1.0].into()
         };
 
         let sprite_coord = if let Some(sc) = self.sprite_coord {
@@ -54,6 +65,7 @@ impl RenderBodyBuilder {
             shape: self.shape.expect("Expected RenderBody to have a shape"),
             color,
             sprite_coord,
+            texture: self.texture.unwrap_or_default(),
         }
     }
 }
@@ -11,18 +11,33 @@ use crate::engine::renderer_engine::post_process::post_process_pipeline::PostPro
 use crate::engine::renderer_engine::post_process::PostProcessFilterId;
 
 use super::{
+    blend_mode::BlendMode,
+    camera::{Camera2D, CameraUniform},
+    frame::{Frame, FrameCommand, FramePass},
     graphics_context::GraphicsContext,
-    render_pass,
+    instance_buffer::{InstanceBuffer, InstanceCapacity},
+    render_error::RenderError,
+    render_pass::{self, primitive_pass::PrimitivePass},
     shapes::{
         circle::{Circle, CircleInstance},
+        primitive::PrimitiveVertex,
         rectangle::{Rectangle, RectangleInstance},
         Shape,
     },
+    texture_registry::{TextureHandle, TextureRegistry},
 };
 
+/// The number of instances the buffers are created with, they grow when needed
+const INITIAL_INSTANCE_CAPACITY: usize = 256;
+
+/// The width and height of the texture atlas in pixels, the largest size every backend
+/// supports
+const DEFAULT_ATLAS_SIZE: u32 = 2048;
+
 pub struct RenderEngineControl<'a> {
     pub g_ctx: GraphicsContext<'a>,
     window_size: PhysicalSize<u32>,
+    camera: Camera2D,
 
     pp_ctx: PostProcessPipelineContext,
 
@@ -31,16 +46,45 @@ pub struct RenderEngineControl<'a> {
     background_render_pass: Option<render_pass::render_pass::RenderPass>,
 
     text_render_pass: Option<render_pass::render_pass::RenderPass>,
-    text_instance_buf: Option<wgpu::Buffer>,
+    text_instance_buf: Option<InstanceBuffer<FontInstance>>,
 
     circle_render_pass: render_pass::render_pass::RenderPass,
-    pub circle_instance_buffer: wgpu::Buffer,
+    pub circle_instance_buffer: InstanceBuffer<CircleInstance>,
 
     rectangle_render_pass: render_pass::render_pass::RenderPass,
-    pub rectangle_instance_buffer: wgpu::Buffer,
+    pub rectangle_instance_buffer: InstanceBuffer<RectangleInstance>,
+
+    primitive_pass: PrimitivePass,
+    primitive_vertex_buffer: InstanceBuffer<PrimitiveVertex>,
+
+    texture_registry: TextureRegistry,
 }
 
 impl<'a> RenderEngineControl<'a> {
+    /// The camera used for circles and rectangles, text is drawn in screen space
+    pub fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    /// Packs the sprite sheet into the texture atlas, circles and rectangles use it
+    /// when their render body has the returned handle. The atlas is a single texture
+    /// of the size set with `atlas_size` on the builder that does not grow, loading
+    /// returns `RenderError::AtlasFull` once no space of the size of the sheet is left.
+    pub fn load_texture(
+        &mut self,
+        sprite_sheet: &SpriteSheet,
+    ) -> Result<TextureHandle, RenderError> {
+        self.texture_registry.load(&self.g_ctx, sprite_sheet)
+    }
+
+    pub fn texture_registry(&self) -> &TextureRegistry {
+        &self.texture_registry
+    }
+
     pub fn render_background(
         &mut self,
         texture_handle: &wgpu::Id<wgpu::Texture>,
@@ -69,20 +113,19 @@ impl<'a> RenderEngineControl<'a> {
     pub fn render_circles(
         &mut self,
         texture_handle: &wgpu::Id<wgpu::Texture>,
-        instances: &Vec<CircleInstance>,
+        instances: &[CircleInstance],
         clear: bool,
-    ) -> Result<(), wgpu::SurfaceError> {
-        let buf = &self.circle_instance_buffer;
+    ) -> Result<(), RenderError> {
+        self.circle_instance_buffer.write(&self.g_ctx, instances)?;
+        let buf = self.circle_instance_buffer.buffer();
         let indices = Circle::compute_indices();
         let pass = &mut self.circle_render_pass;
+        pass.set_camera(&self.g_ctx.queue, &self.camera.uniform());
         let num_instances = instances.len();
         let target_texture = self
             .pp_ctx
             .request_texture_by_handle(&texture_handle)
             .unwrap();
-        self.g_ctx
-            .queue
-            .write_buffer(&buf, 0, bytemuck::cast_slice(&instances));
 
         pass.render(
             &self.g_ctx.device,
@@ -100,20 +143,20 @@ impl<'a> RenderEngineControl<'a> {
     pub fn render_rectangles(
         &mut self,
         texture_handle: &wgpu::Id<wgpu::Texture>,
-        instances: &Vec<RectangleInstance>,
+        instances: &[RectangleInstance],
         clear: bool,
-    ) -> Result<(), wgpu::SurfaceError> {
-        let buf = &self.rectangle_instance_buffer;
+    ) -> Result<(), RenderError> {
+        self.rectangle_instance_buffer
+            .write(&self.g_ctx, instances)?;
+        let buf = self.rectangle_instance_buffer.buffer();
         let indices = Rectangle::compute_indices();
         let pass = &mut self.rectangle_render_pass;
+        pass.set_camera(&self.g_ctx.queue, &self.camera.uniform());
         let num_instances = instances.len();
         let target_texture = self
             .pp_ctx
             .request_texture_by_handle(&texture_handle)
             .unwrap();
-        self.g_ctx
-            .queue
-            .write_buffer(&buf, 0, bytemuck::cast_slice(&instances));
 
         pass.render(
             &self.g_ctx.device,
@@ -133,23 +176,25 @@ impl<'a> RenderEngineControl<'a> {
         texture_handle: &wgpu::Id<wgpu::Texture>,
         text: Vec<FontInstance>,
         clear: bool,
-    ) -> Result<(), wgpu::SurfaceError> {
+    ) -> Result<(), RenderError> {
         let pass = match &mut self.text_render_pass {
             None => panic!("No font is set"),
             Some(p) => p,
         };
 
-        if let Some(buf) = &self.text_instance_buf {
+        if let Some(text_buf) = &mut self.text_instance_buf {
+            text_buf.write(&self.g_ctx, &text)?;
+            let buf = text_buf.buffer();
             let target_texture = self
                 .pp_ctx
                 .request_texture_by_handle(&texture_handle)
                 .unwrap();
             let indices = Rectangle::compute_indices();
             let num_instances = text.len();
-
-            self.g_ctx
-                .queue
-                .write_buffer(&buf, 0, bytemuck::cast_slice(&text));
+            pass.set_camera(
+                &self.g_ctx.queue,
+                &CameraUniform::screen(self.camera.viewport()),
+            );
 
             pass.render(
                 &self.g_ctx.device,
@@ -189,23 +234,157 @@ impl<'a> RenderEngineControl<'a> {
         Ok(())
     }
 
+    /// Renders the frame and presents it with a single submit, see `Frame`
+    pub fn render_frame(&mut self, frame: &Frame) -> Result<(), RenderError> {
+        self.circle_instance_buffer
+            .write(&self.g_ctx, frame.circle_instances())?;
+        self.rectangle_instance_buffer
+            .write(&self.g_ctx, frame.rectangle_instances())?;
+        if let Some(buf) = &mut self.text_instance_buf {
+            buf.write(&self.g_ctx, frame.text_instances())?;
+        }
+        self.primitive_vertex_buffer
+            .write(&self.g_ctx, frame.primitive_vertices())?;
+        let camera = self.camera.uniform();
+        self.circle_render_pass
+            .set_camera(&self.g_ctx.queue, &camera);
+        self.rectangle_render_pass
+            .set_camera(&self.g_ctx.queue, &camera);
+        let screen_camera = CameraUniform::screen(self.camera.viewport());
+        if let Some(pass) = &self.text_render_pass {
+            pass.set_camera(&self.g_ctx.queue, &screen_camera);
+        }
+        self.primitive_pass
+            .set_cameras(&self.g_ctx.queue, &camera, &screen_camera);
+
+        let surface = self.g_ctx.surface.get_current_texture()?;
+        let surface_view = surface
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut command_encoder =
+            self.g_ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Frame Render Encoder"),
+                });
+
+        let mut texture_handle = self.pp_ctx.request_texture_handle();
+        for frame_pass in frame.passes() {
+            match frame_pass {
+                FramePass::Filter(filter_id) => {
+                    texture_handle = self.post_process_pipeline.record(
+                        &mut command_encoder,
+                        &self.pp_ctx,
+                        &filter_id,
+                        &texture_handle,
+                    );
+                }
+                FramePass::Draw { clear, draws } => {
+                    let target_view = self
+                        .pp_ctx
+                        .request_texture_by_handle(&texture_handle)
+                        .unwrap()
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    let mut render_pass = render_pass::render_pass::begin_render_pass(
+                        &mut command_encoder,
+                        &target_view,
+                        clear,
+                        Some("Frame Render Pass"),
+                    );
+                    for draw in draws {
+                        self.record_draw(&mut render_pass, draw);
+                    }
+                }
+            }
+        }
+
+        self.post_process_pipeline.record_finalize(
+            &mut command_encoder,
+            &self.pp_ctx,
+            &texture_handle,
+            &surface_view,
+        );
+        self.g_ctx.queue.submit(Some(command_encoder.finish()));
+        surface.present();
+        Ok(())
+    }
+
+    fn record_draw<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>, draw: &FrameCommand) {
+        match draw {
+            FrameCommand::Background => match &self.background_render_pass {
+                Some(pass) => pass.record(render_pass, None, 6, 0..1, BlendMode::Alpha),
+                None => panic!("Background not set"),
+            },
+            FrameCommand::Rectangles(instances, blend_mode) => self.rectangle_render_pass.record(
+                render_pass,
+                Some(self.rectangle_instance_buffer.buffer()),
+                Rectangle::compute_indices().len() as u32,
+                instances.clone(),
+                *blend_mode,
+            ),
+            FrameCommand::Circles(instances, blend_mode) => self.circle_render_pass.record(
+                render_pass,
+                Some(self.circle_instance_buffer.buffer()),
+                Circle::compute_indices().len() as u32,
+                instances.clone(),
+                *blend_mode,
+            ),
+            FrameCommand::Text(instances) => {
+                match (&self.text_render_pass, &self.text_instance_buf) {
+                    (Some(pass), Some(buf)) => pass.record(
+                        render_pass,
+                        Some(buf.buffer()),
+                        Rectangle::compute_indices().len() as u32,
+                        instances.clone(),
+                        BlendMode::Alpha,
+                    ),
+                    _ => panic!("No font is set"),
+                }
+            }
+            FrameCommand::Primitives(vertices, blend_mode, space) => self.primitive_pass.record(
+                render_pass,
+                self.primitive_vertex_buffer.buffer(),
+                vertices.clone(),
+                *blend_mode,
+                *space,
+            ),
+            FrameCommand::Clear | FrameCommand::Filter(_) => {
+                unreachable!("Expected only draws within a frame pass")
+            }
+        }
+    }
+
     pub fn request_texture_handle(&mut self) -> wgpu::Id<wgpu::Texture> {
         self.pp_ctx.request_texture_handle()
     }
 
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    /// Resizes the surface, the post process textures and the viewport of the camera.
+    /// A window with zero width or height, e.g. when minimized, is ignored since the
+    /// surface cannot be configured with it.
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 || new_size == self.window_size {
+            return;
+        }
         self.window_size = new_size;
+        self.camera.set_viewport((new_size.width, new_size.height));
         self.g_ctx.config.width = new_size.width;
         self.g_ctx.config.height = new_size.height;
         self.g_ctx
             .surface
             .configure(&self.g_ctx.device, &self.g_ctx.config);
+        self.pp_ctx.resize(&self.g_ctx, &new_size);
     }
 }
 
 pub struct RenderEngineControlBuilder {
-    max_num_circle_instances: u32,
-    max_num_rectangle_instances: u32,
+    max_num_circle_instances: Option<u32>,
+    max_num_rectangle_instances: Option<u32>,
+    shrink_instance_buffers: bool,
+    atlas_size: u32,
     sprite_sheet: Option<SpriteSheet>,
     background: Option<Background>,
     font: Option<Font>,
@@ -215,8 +394,10 @@ pub struct RenderEngineControlBuilder {
 impl<'a> RenderEngineControlBuilder {
     pub fn new() -> Self {
         Self {
-            max_num_circle_instances: 0,
-            max_num_rectangle_instances: 0,
+            max_num_circle_instances: None,
+            max_num_rectangle_instances: None,
+            shrink_instance_buffers: false,
+            atlas_size: DEFAULT_ATLAS_SIZE,
             sprite_sheet: None,
             background: None,
             font: None,
@@ -224,16 +405,35 @@ impl<'a> RenderEngineControlBuilder {
         }
     }
 
+    /// Limits the number of circles of a frame, all circles of a `Frame` or of one
+    /// `render_circles` call. Drawing more returns `RenderError::InstanceLimitExceeded`.
+    /// Without a limit the buffer grows as needed.
     pub fn max_num_circle_instances(mut self, len: u32) -> Self {
-        self.max_num_circle_instances = len;
+        self.max_num_circle_instances = Some(len);
         self
     }
 
+    /// Limits the number of rectangles of a frame, see `max_num_circle_instances`
     pub fn max_num_rectangle_instances(mut self, len: u32) -> Self {
-        self.max_num_rectangle_instances = len;
+        self.max_num_rectangle_instances = Some(len);
+        self
+    }
+
+    /// Halves the instance buffers when a frame uses at most a quarter of them
+    pub fn shrink_instance_buffers(mut self, shrink: bool) -> Self {
+        self.shrink_instance_buffers = shrink;
         self
     }
 
+    /// The width and height of the texture atlas all sprite sheets are packed into. The
+    /// atlas is grown to fit the sprite sheet given to the builder if it is larger.
+    pub fn atlas_size(mut self, size: u32) -> Self {
+        self.atlas_size = size;
+        self
+    }
+
+    /// The sprite sheet of `TextureHandle::default()`, more can be added at runtime with
+    /// `RenderEngineControl::load_texture`
     pub fn sprite_sheet(mut self, tex: SpriteSheet) -> Self {
         self.sprite_sheet = Some(tex);
         self
@@ -271,11 +471,11 @@ impl<'a> RenderEngineControlBuilder {
                     .build(&g_ctx, &window_size),
             );
 
-            let buf = Some(g_ctx.create_buffer(
+            let buf = Some(InstanceBuffer::new(
+                &g_ctx,
                 "Text instance buffer",
-                1024,
-                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                false,
+                InstanceCapacity::new(INITIAL_INSTANCE_CAPACITY)
+                    .shrink(self.shrink_instance_buffers),
             ));
 
             (pass, buf)
@@ -293,32 +493,42 @@ impl<'a> RenderEngineControlBuilder {
             None
         };
 
-        let default_circle = CircleInstance::default();
-        let raw_circle_instance = bytemuck::bytes_of(&default_circle);
-        let circle_instance_buffer_len =
-            (raw_circle_instance.len() as u32) * self.max_num_circle_instances;
+        let (sheet_width, sheet_height) = sprite_sheet.sprite_buf.dimensions();
+        let atlas_size = self.atlas_size.max(sheet_width).max(sheet_height);
+        let mut texture_registry = TextureRegistry::new(&g_ctx, atlas_size);
+        texture_registry
+            .load(&g_ctx, &sprite_sheet)
+            .expect("Expected the sprite sheet to fit into an atlas sized after it");
+
+        let (bind_group, layout) = texture_registry.create_bind_group(&g_ctx.device);
         let circle_render_pass = render_pass::render_pass::RenderPassBuilder::circle()
-            .texture_data(Box::new(sprite_sheet.clone()))
+            .texture_bind_group(bind_group, layout)
             .build(&g_ctx, &window_size);
-        let circle_instance_buffer = g_ctx.create_buffer(
+        let circle_instance_buffer = InstanceBuffer::new(
+            &g_ctx,
             "Circle instance buffer",
-            circle_instance_buffer_len,
-            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            false,
+            InstanceCapacity::new(INITIAL_INSTANCE_CAPACITY)
+                .limit(self.max_num_circle_instances.map(|len| len as usize))
+                .shrink(self.shrink_instance_buffers),
         );
 
-        let default_rect = RectangleInstance::default();
-        let raw_rect_instance = bytemuck::bytes_of(&default_rect);
-        let rect_instance_buffer_len =
-            (raw_rect_instance.len() as u32) * self.max_num_rectangle_instances;
+        let (bind_group, layout) = texture_registry.create_bind_group(&g_ctx.device);
         let rectangle_render_pass = render_pass::render_pass::RenderPassBuilder::rectangle()
-            .texture_data(Box::new(sprite_sheet.clone()))
+            .texture_bind_group(bind_group, layout)
             .build(&g_ctx, &window_size);
-        let rectangle_instance_buffer = g_ctx.create_buffer(
+        let rectangle_instance_buffer = InstanceBuffer::new(
+            &g_ctx,
             "Rectangle instance buffer",
-            rect_instance_buffer_len,
-            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            false,
+            InstanceCapacity::new(INITIAL_INSTANCE_CAPACITY)
+                .limit(self.max_num_rectangle_instances.map(|len| len as usize))
+                .shrink(self.shrink_instance_buffers),
+        );
+
+        let primitive_pass = PrimitivePass::new(&g_ctx, &window_size);
+        let primitive_vertex_buffer = InstanceBuffer::new(
+            &g_ctx,
+            "Primitive vertex buffer",
+            InstanceCapacity::new(INITIAL_INSTANCE_CAPACITY).shrink(self.shrink_instance_buffers),
         );
 
         let pp_ctx = PostProcessPipelineContext::new(&g_ctx, &window_size);
@@ -336,6 +546,7 @@ impl<'a> RenderEngineControlBuilder {
             g_ctx,
             pp_ctx,
             window_size,
+            camera: Camera2D::new((window_size.width, window_size.height)),
             background_render_pass,
             circle_render_pass,
             circle_instance_buffer,
@@ -343,6 +554,9 @@ impl<'a> RenderEngineControlBuilder {
             rectangle_instance_buffer,
             text_render_pass,
             text_instance_buf,
+            primitive_pass,
+            primitive_vertex_buffer,
+            texture_registry,
             post_process_pipeline,
         }
     }
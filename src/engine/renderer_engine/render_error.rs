@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// Errors returned when drawing with `RenderEngineControl`
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderError {
+    Surface(wgpu::SurfaceError),
+    /// More instances were drawn in one frame, or in one immediate `render_*` call,
+    /// than the limit set on the builder
+    InstanceLimitExceeded {
+        label: &'static str,
+        requested: usize,
+        limit: usize,
+    },
+    /// The instance buffer would have to be larger than a buffer can be
+    BufferTooLarge {
+        label: &'static str,
+        capacity: usize,
+    },
+    /// The texture registry holds as many textures as the shaders can look up
+    TextureLimitExceeded {
+        limit: usize,
+    },
+    /// No free space of the size of the image is left in the texture atlas
+    AtlasFull {
+        width: u32,
+        height: u32,
+        atlas_width: u32,
+        atlas_height: u32,
+    },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Surface(e) => write!(f, "{e}"),
+            RenderError::InstanceLimitExceeded {
+                label,
+                requested,
+                limit,
+            } => write!(
+                f,
+                "{label} requested {requested} instances but the limit is {limit}"
+            ),
+            RenderError::BufferTooLarge { label, capacity } => {
+                write!(f, "{label} can not hold {capacity} instances in one buffer")
+            }
+            RenderError::TextureLimitExceeded { limit } => {
+                write!(f, "Can not load more than {limit} textures")
+            }
+            RenderError::AtlasFull {
+                width,
+                height,
+                atlas_width,
+                atlas_height,
+            } => write!(
+                f,
+                "No space left for a {width}x{height} image in the {atlas_width}x{atlas_height} texture atlas"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<wgpu::SurfaceError> for RenderError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        RenderError::Surface(e)
+    }
+}
@@ -1 +1,2 @@
+pub mod primitive_pass;
 pub mod render_pass;
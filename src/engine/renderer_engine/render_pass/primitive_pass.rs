@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use crate::engine::renderer_engine::blend_mode::BlendMode;
+use crate::engine::renderer_engine::camera::CameraUniform;
+use crate::engine::renderer_engine::graphics_context::GraphicsContext;
+use crate::engine::renderer_engine::shapes::primitive::PrimitiveVertex;
+use crate::engine::renderer_engine::util::create_shader_module;
+
+use super::render_pass::RenderPassBuilder;
+
+/// The camera the primitives are drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimitiveSpace {
+    /// World coordinates as seen through the camera
+    World,
+    /// Pixels around the center of the window, y pointing up
+    Screen,
+}
+
+/// Draws triangles from a vertex buffer of `PrimitiveVertex`, without instances or
+/// textures. Both cameras have their own uniform, such that world and screen
+/// primitives can be recorded into the same submit.
+pub struct PrimitivePass {
+    world_camera_buffer: wgpu::Buffer,
+    world_camera_bind_group: wgpu::BindGroup,
+    screen_camera_buffer: wgpu::Buffer,
+    screen_camera_bind_group: wgpu::BindGroup,
+    render_pipelines: Vec<(BlendMode, wgpu::RenderPipeline)>,
+}
+
+impl PrimitivePass {
+    pub fn new(ctx: &GraphicsContext, window_size: &winit::dpi::PhysicalSize<u32>) -> Self {
+        let camera = CameraUniform::screen((window_size.width, window_size.height));
+        let (world_camera_buffer, world_camera_bind_group, camera_bind_group_layout) =
+            RenderPassBuilder::create_uniform_buffer_init(&ctx.device, &camera);
+        let (screen_camera_buffer, screen_camera_bind_group, _) =
+            RenderPassBuilder::create_uniform_buffer_init(&ctx.device, &camera);
+
+        let shader_module = create_shader_module(
+            &ctx.device,
+            include_str!("./shaders/primitive.wgsl").to_string(),
+        );
+        let render_pipeline_layout =
+            ctx.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Primitive Pipeline Layout"),
+                    bind_group_layouts: &[&camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let render_pipelines = BlendMode::ALL
+            .iter()
+            .map(|mode| {
+                let render_targets = [Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    blend: Some(mode.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })];
+                let render_pipeline =
+                    ctx.device
+                        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                            label: Some("Primitive Pipeline"),
+                            layout: Some(&render_pipeline_layout),
+                            vertex: wgpu::VertexState {
+                                module: &shader_module,
+                                entry_point: "vs_main",
+                                buffers: &[PrimitiveVertex::desc()],
+                            },
+                            // Lines and polygons may be wound either way
+                            primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: None,
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                unclipped_depth: false,
+                                conservative: false,
+                            },
+                            fragment: Some(wgpu::FragmentState {
+                                module: &shader_module,
+                                entry_point: "fs_main",
+                                targets: &render_targets,
+                            }),
+                            depth_stencil: None,
+                            multisample: wgpu::MultisampleState {
+                                count: 1,
+                                mask: !0,
+                                alpha_to_coverage_enabled: false,
+                            },
+                            multiview: None,
+                        });
+                (*mode, render_pipeline)
+            })
+            .collect();
+
+        Self {
+            world_camera_buffer,
+            world_camera_bind_group,
+            screen_camera_buffer,
+            screen_camera_bind_group,
+            render_pipelines,
+        }
+    }
+
+    pub fn set_cameras(&self, queue: &wgpu::Queue, world: &CameraUniform, screen: &CameraUniform) {
+        queue.write_buffer(&self.world_camera_buffer, 0, bytemuck::bytes_of(world));
+        queue.write_buffer(&self.screen_camera_buffer, 0, bytemuck::bytes_of(screen));
+    }
+
+    /// Draws the vertices in `vertices` of the buffer into a render pass begun by the
+    /// caller
+    pub fn record<'p>(
+        &'p self,
+        render_pass: &mut wgpu::RenderPass<'p>,
+        vertex_buffer: &'p wgpu::Buffer,
+        vertices: Range<u32>,
+        blend_mode: BlendMode,
+        space: PrimitiveSpace,
+    ) {
+        let (_, render_pipeline) = self
+            .render_pipelines
+            .iter()
+            .find(|(mode, _)| *mode == blend_mode)
+            .expect("Expected a pipeline for every blend mode");
+        let camera_bind_group = match space {
+            PrimitiveSpace::World => &self.world_camera_bind_group,
+            PrimitiveSpace::Screen => &self.screen_camera_bind_group,
+        };
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(vertices, 0..1);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::engine::renderer_engine::asset::font::Font;
 use crate::engine::renderer_engine::asset::Asset;
+use crate::engine::renderer_engine::blend_mode::BlendMode;
+use crate::engine::renderer_engine::camera::CameraUniform;
 use crate::engine::renderer_engine::graphics_context::GraphicsContext;
 use crate::engine::renderer_engine::shapes::rectangle::Rectangle;
 use crate::engine::renderer_engine::shapes::Shape;
@@ -7,18 +9,25 @@ use crate::engine::renderer_engine::util::{
     create_sampler, create_shader_module, create_texture, write_texture,
 };
 use crate::engine::renderer_engine::{shapes::circle::Circle, vertex::Vertex};
+use std::ops::Range;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
 pub struct RenderPass {
     id: String,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
-    render_pipeline: wgpu::RenderPipeline,
+    render_pipelines: Vec<(BlendMode, wgpu::RenderPipeline)>,
+    uniform_buffer: wgpu::Buffer,
     uniform_buf_bind_group: wgpu::BindGroup,
     texture_bind_group: wgpu::BindGroup,
 }
 
 impl RenderPass {
+    /// Sets the view-projection matrix used by the following calls to `render`
+    pub fn set_camera(&self, queue: &wgpu::Queue, camera: &CameraUniform) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(camera));
+    }
+
     pub fn render(
         &mut self,
         device: &wgpu::Device,
@@ -36,58 +45,99 @@ impl RenderPass {
         };
 
         let mut command_encoder = device.create_command_encoder(&command_encoder_descriptor);
-
-        let color_attachment = wgpu::RenderPassColorAttachment {
-            view: &target_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-            resolve_target: None,
-            ops: wgpu::Operations {
-                load: match clear_texture {
-                    true => wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.1,
-                        b: 0.1,
-                        a: 1.0,
-                    }),
-                    false => wgpu::LoadOp::Load,
-                },
-                store: wgpu::StoreOp::Store,
-            },
-        };
+        let target_view = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         {
             let rp_label = format!("{id} Render Pass");
-            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some(rp_label.as_str()),
-                color_attachments: &[Some(color_attachment)],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            // TODO: I wish to somehow set the bind_groups in a loop and make it possible
-            // to have a render pass with and without buffer without any effort
-            render_pass.set_bind_group(0, &self.uniform_buf_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            let mut render_pass = begin_render_pass(
+                &mut command_encoder,
+                &target_view,
+                clear_texture,
+                Some(rp_label.as_str()),
+            );
+            self.record(
+                &mut render_pass,
+                instance_buffer,
+                num_indices,
+                0..num_instances,
+                BlendMode::Alpha,
+            );
+        }
 
-            if let Some(buf) = instance_buffer {
-                render_pass.set_vertex_buffer(1, buf.slice(..));
-            }
+        queue.submit(Some(command_encoder.finish()));
 
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.set_pipeline(&self.render_pipeline);
+        Ok(())
+    }
 
-            // TODO: There is most likely a way I can merge the two render passes (circle,
-            // rect) into one vertex (and index) by using the base_vertex
-            render_pass.draw_indexed(0..num_indices, 0, 0..num_instances);
+    /// Draws the instances in `instances` of the buffer into a render pass begun by the
+    /// caller, such that several draws can share one pass and one submit
+    pub fn record<'p>(
+        &'p self,
+        render_pass: &mut wgpu::RenderPass<'p>,
+        instance_buffer: Option<&'p wgpu::Buffer>,
+        num_indices: u32,
+        instances: Range<u32>,
+        blend_mode: BlendMode,
+    ) {
+        let render_pipeline = self
+            .render_pipelines
+            .iter()
+            .find(|(mode, _)| *mode == blend_mode)
+            .map(|(_, pipeline)| pipeline)
+            .unwrap_or_else(|| panic!("{} pass does not support {blend_mode:?}", self.id));
+
+        // TODO: I wish to somehow set the bind_groups in a loop and make it possible
+        // to have a render pass with and without buffer without any effort
+        render_pass.set_bind_group(0, &self.uniform_buf_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        if let Some(buf) = instance_buffer {
+            render_pass.set_vertex_buffer(1, buf.slice(..));
         }
 
-        queue.submit(Some(command_encoder.finish()));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_pipeline(render_pipeline);
 
-        Ok(())
+        // TODO: There is most likely a way I can merge the two render passes (circle,
+        // rect) into one vertex (and index) by using the base_vertex
+        render_pass.draw_indexed(0..num_indices, 0, instances);
     }
 }
 
+/// Begins a pass drawing into `target`, cleared first if `clear_texture` is set
+pub fn begin_render_pass<'e>(
+    command_encoder: &'e mut wgpu::CommandEncoder,
+    target: &'e wgpu::TextureView,
+    clear_texture: bool,
+    label: Option<&str>,
+) -> wgpu::RenderPass<'e> {
+    let color_attachment = wgpu::RenderPassColorAttachment {
+        view: target,
+        resolve_target: None,
+        ops: wgpu::Operations {
+            load: match clear_texture {
+                true => wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.1,
+                    g: 0.1,
+                    b: 0.1,
+                    a: 1.0,
+                }),
+                false => wgpu::LoadOp::Load,
+            },
+            store: wgpu::StoreOp::Store,
+        },
+    };
+
+    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label,
+        color_attachments: &[Some(color_attachment)],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
 pub struct RenderPassBuilder {
     id: String,
     shader_path: String,
@@ -96,6 +146,8 @@ pub struct RenderPassBuilder {
     indices: Vec<u16>,
     instance_buffer_layout: Option<wgpu::VertexBufferLayout<'static>>,
     texture_data: Option<Box<dyn Asset>>,
+    texture_bind_group: Option<(wgpu::BindGroup, wgpu::BindGroupLayout)>,
+    blend_modes: Vec<BlendMode>,
 }
 
 impl RenderPassBuilder {
@@ -107,6 +159,7 @@ impl RenderPassBuilder {
         let indices = Circle::compute_indices();
         let instance_buffer_layout = Some(Circle::instance_buffer_desc());
         let texture_data = None;
+        let blend_modes = BlendMode::ALL.to_vec();
         Self {
             id,
             shader_path,
@@ -115,6 +168,8 @@ impl RenderPassBuilder {
             indices,
             instance_buffer_layout,
             texture_data,
+            texture_bind_group: None,
+            blend_modes,
         }
     }
 
@@ -126,6 +181,7 @@ impl RenderPassBuilder {
         let indices = Rectangle::compute_indices();
         let instance_buffer_layout = Some(Rectangle::instance_buffer_desc());
         let texture_data = None;
+        let blend_modes = BlendMode::ALL.to_vec();
         Self {
             id,
             shader_path,
@@ -134,6 +190,8 @@ impl RenderPassBuilder {
             indices,
             instance_buffer_layout,
             texture_data,
+            texture_bind_group: None,
+            blend_modes,
         }
     }
 
@@ -158,6 +216,7 @@ impl RenderPassBuilder {
         let indices = vec![0, 1, 2, 1, 3, 2];
         let instance_buffer_layout = None;
         let texture_data = None;
+        let blend_modes = vec![BlendMode::Alpha];
         Self {
             id,
             shader_path,
@@ -166,6 +225,8 @@ impl RenderPassBuilder {
             indices,
             instance_buffer_layout,
             texture_data,
+            texture_bind_group: None,
+            blend_modes,
         }
     }
 
@@ -177,6 +238,7 @@ impl RenderPassBuilder {
         let indices = Rectangle::compute_indices();
         let instance_buffer_layout = Some(Font::instance_buffer_desc());
         let texture_data = None;
+        let blend_modes = vec![BlendMode::Alpha];
         Self {
             id,
             shader_path,
@@ -185,16 +247,18 @@ impl RenderPassBuilder {
             indices,
             instance_buffer_layout,
             texture_data,
+            texture_bind_group: None,
+            blend_modes,
         }
     }
 
-    fn create_uniform_buffer_init(
+    pub(crate) fn create_uniform_buffer_init(
         device: &wgpu::Device,
-        data: &[f32],
+        camera: &CameraUniform,
     ) -> (wgpu::Buffer, wgpu::BindGroup, wgpu::BindGroupLayout) {
         let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Global render information"),
-            contents: bytemuck::cast_slice(data),
+            contents: bytemuck::bytes_of(camera),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -300,7 +364,19 @@ impl RenderPassBuilder {
         self
     }
 
+    /// Binds a texture created elsewhere instead of `texture_data`, such as the atlas of
+    /// the `TextureRegistry`
+    pub fn texture_bind_group(
+        mut self,
+        bind_group: wgpu::BindGroup,
+        layout: wgpu::BindGroupLayout,
+    ) -> Self {
+        self.texture_bind_group = Some((bind_group, layout));
+        self
+    }
+
     // TODO: Should this also return the instance buffer?
+    /// The pass starts out with the default camera of the window, see `set_camera`
     pub fn build(
         self,
         ctx: &GraphicsContext,
@@ -322,39 +398,38 @@ impl RenderPassBuilder {
                 usage: wgpu::BufferUsages::INDEX,
             });
 
-        let (texture_bind_group, texture_bind_group_layout) = match self.texture_data {
-            Some(data) => {
-                let texture = create_texture(
-                    &ctx,
-                    data.buffer().dimensions(),
-                    Some(format!("{} Sprite Sheet", id.clone()).as_str()),
-                );
-                write_texture(&ctx, &texture, data.buffer());
-                let sampler = create_sampler(
-                    &ctx.device,
-                    Some(format!("{} Sprite Sheet Sampler", id.clone()).as_str()),
-                );
-                Self::create_texture_bind_group_from_sprite_sheet(
-                    &ctx.device,
-                    texture,
-                    sampler,
-                    &data,
-                )
-            }
-            _ => todo!(),
-        };
+        let (texture_bind_group, texture_bind_group_layout) =
+            match (self.texture_bind_group, self.texture_data) {
+                (Some(bind_group), _) => bind_group,
+                (None, Some(data)) => {
+                    let texture = create_texture(
+                        &ctx,
+                        data.buffer().dimensions(),
+                        Some(format!("{} Sprite Sheet", id.clone()).as_str()),
+                    );
+                    write_texture(&ctx, &texture, data.buffer());
+                    let sampler = create_sampler(
+                        &ctx.device,
+                        Some(format!("{} Sprite Sheet Sampler", id.clone()).as_str()),
+                    );
+                    Self::create_texture_bind_group_from_sprite_sheet(
+                        &ctx.device,
+                        texture,
+                        sampler,
+                        &data,
+                    )
+                }
+                (None, None) => panic!(
+                    "{} pass needs a texture, set either texture_bind_group or texture_data",
+                    id
+                ),
+            };
 
         let shader_module = create_shader_module(&ctx.device, self.shader_path);
 
-        let render_targets = [Some(wgpu::ColorTargetState {
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-            write_mask: wgpu::ColorWrites::ALL,
-        })];
-
-        let size = [window_size.width as f32, window_size.height as f32];
-        let (_buffer, uniform_buf_bind_group, buffer_bind_group_layout) =
-            Self::create_uniform_buffer_init(&ctx.device, &size);
+        let camera = CameraUniform::screen((window_size.width, window_size.height));
+        let (uniform_buffer, uniform_buf_bind_group, buffer_bind_group_layout) =
+            Self::create_uniform_buffer_init(&ctx.device, &camera);
         let render_pipeline_layout =
             &ctx.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -369,48 +444,62 @@ impl RenderPassBuilder {
             vec![Vertex::desc()]
         };
 
-        let render_pipeline = ctx
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&render_pipeline_layout),
-
-                vertex: wgpu::VertexState {
-                    module: &shader_module,
-                    entry_point: "vs_main",
-                    buffers: &vertex_buffer_layouts,
-                },
-
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_module,
-                    entry_point: "fs_main",
-                    targets: &render_targets,
-                }),
-
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            });
+        // The shaders write premultiplied colors, one pipeline per blend state
+        let render_pipelines = self
+            .blend_modes
+            .iter()
+            .map(|mode| {
+                let render_targets = [Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    blend: Some(mode.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })];
+                let render_pipeline =
+                    ctx.device
+                        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                            label: Some("Render Pipeline"),
+                            layout: Some(&render_pipeline_layout),
+
+                            vertex: wgpu::VertexState {
+                                module: &shader_module,
+                                entry_point: "vs_main",
+                                buffers: &vertex_buffer_layouts,
+                            },
+
+                            primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                unclipped_depth: false,
+                                conservative: false,
+                            },
+
+                            fragment: Some(wgpu::FragmentState {
+                                module: &shader_module,
+                                entry_point: "fs_main",
+                                targets: &render_targets,
+                            }),
+
+                            depth_stencil: None,
+                            multisample: wgpu::MultisampleState {
+                                count: 1,
+                                mask: !0,
+                                alpha_to_coverage_enabled: false,
+                            },
+                            multiview: None,
+                        });
+                (*mode, render_pipeline)
+            })
+            .collect();
 
         RenderPass {
             id,
             vertex_buffer,
             index_buffer,
-            render_pipeline,
+            render_pipelines,
+            uniform_buffer,
             uniform_buf_bind_group,
             texture_bind_group,
         }
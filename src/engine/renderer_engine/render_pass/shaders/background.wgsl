@@ -4,7 +4,11 @@ struct VertexOutput {
     @location(0) tex_coord: vec2<f32>,
 };
 
-@group(0) @binding(0) var<uniform> window_size: vec2<f32>;
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
@@ -32,6 +36,7 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let texel = textureSample(texture, texture_sampler, in.tex_coord);
-    return texel.bgra;
+    // The output is premultiplied by alpha, see BlendMode
+    let texel = textureSample(texture, texture_sampler, in.tex_coord).bgra;
+    return vec4<f32>(texel.rgb * texel.a, texel.a);
 }
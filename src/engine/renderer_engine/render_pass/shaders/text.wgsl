@@ -14,7 +14,11 @@ struct VertexOutput {
     @location(0) tex_coord: vec2<f32>,
 };
 
-@group(0) @binding(0) var<uniform> window_size: vec2<f32>;
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
@@ -47,19 +51,17 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     
-    let scaled_object_position = instance.position / vec3<f32>(window_size, 1.0);
-
-    let scaled_object_width = instance.size/ window_size[0];
-    let scaled_object_height = instance.size / window_size[1];
-    let scaled_vertex_position = vertex.position * vec3<f32>(scaled_object_width/2.0, scaled_object_height/2.0, 1.0);
+    // Text is placed in units of two pixels around the center of the window
+    let screen_position = (instance.position.xy + vertex.position.xy * instance.size / 2.0) / 2.0;
 
-    out.clip_position = vec4<f32>(scaled_vertex_position, 1.0) + vec4<f32>(scaled_object_position, 0.0);
+    out.clip_position = camera.view_projection * vec4<f32>(screen_position, instance.position.z + vertex.position.z, 1.0);
     out.tex_coord = compute_font_coordinate(vertex.position, font_info, instance.tex_coords);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let texel = textureSample(texture, texture_sampler, in.tex_coord);
-    return texel.bgra;
+    // The output is premultiplied by alpha, see BlendMode
+    let texel = textureSample(texture, texture_sampler, in.tex_coord).bgra;
+    return vec4<f32>(texel.rgb * texel.a, texel.a);
 }
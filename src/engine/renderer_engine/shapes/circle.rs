@@ -1,4 +1,5 @@
 
+use crate::engine::renderer_engine::texture_registry::TextureHandle;
 use crate::engine::renderer_engine::vertex::Vertex;
 use std::mem;
 use super::Shape;
@@ -6,19 +7,21 @@ use super::Shape;
 pub struct Circle {}
 
 #[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
 pub struct CircleInstance {
     pub position: [f32; 3],
-    pub color: [f32; 3], 
+    pub color: [f32; 4],
     pub radius: f32,
     pub rotation: f32,
     pub sprite_coord: [f32; 4],
+    /// The image the sprite coordinate is counted in cells of
+    pub texture: TextureHandle,
 }
 
 impl Default for CircleInstance {
     fn default() -> Self {
-        CircleInstance { position: [0.0,0.0,0.0], color: [0.0,255.0,0.0], radius: 0.,
-            sprite_coord: [0.0,0.0,1.0,1.0], rotation: 0.0
+        CircleInstance { position: [0.0,0.0,0.0], color: [0.0,1.0,0.0,1.0], radius: 0.,
+            sprite_coord: [0.0,0.0,1.0,1.0], rotation: 0.0, texture: TextureHandle::default(),
         }
     }
 }
@@ -69,22 +72,27 @@ impl Shape for Circle {
                 wgpu::VertexAttribute {
                     offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x4,
                 },
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32,
                 },
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32,
                 },
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
                     shader_location: 6,
                     format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Uint32,
                 }
             ],
         }
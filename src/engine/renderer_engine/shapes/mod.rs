@@ -3,6 +3,7 @@ use super::vertex::Vertex;
 pub mod triangle;
 pub mod circle;
 pub mod rectangle;
+pub mod primitive;
 
 pub trait Shape {
     fn id() -> String;
@@ -0,0 +1,400 @@
+use cgmath::{InnerSpace, Vector2};
+
+/// Vertex of the triangles the primitives are built from
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PrimitiveVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl PrimitiveVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PrimitiveVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A point of a primitive with its own color and, for lines, thickness. The colors and
+/// thicknesses are interpolated between the points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrimitivePoint {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    pub thickness: f32,
+}
+
+impl PrimitivePoint {
+    pub fn new(position: [f32; 2], color: [f32; 4]) -> Self {
+        Self {
+            position,
+            color,
+            thickness: 1.0,
+        }
+    }
+
+    pub fn thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+}
+
+/// Miter joins longer than this many times half the thickness are cut short, such that
+/// sharp corners do not spike out
+const MITER_LIMIT: f32 = 4.0;
+
+/// Lines and shapes built into triangles on the cpu each frame, drawn in order with
+/// `Frame::primitives`. Used for debug drawing and user interfaces where instancing
+/// does not pay off.
+#[derive(Clone, Debug, Default)]
+pub struct Primitives {
+    vertices: Vec<PrimitiveVertex>,
+}
+
+impl Primitives {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertices(&self) -> &[PrimitiveVertex] {
+        &self.vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    fn push(&mut self, position: Vector2<f32>, color: [f32; 4]) {
+        self.vertices.push(PrimitiveVertex {
+            position: [position.x, position.y, 0.0],
+            color,
+        });
+    }
+
+    /// A line of one color and thickness
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, color: [f32; 4]) {
+        self.polyline(
+            &[
+                PrimitivePoint::new(from, color).thickness(thickness),
+                PrimitivePoint::new(to, color).thickness(thickness),
+            ],
+            false,
+        );
+    }
+
+    /// Lines through the points with mitered corners, `closed` connects the last point
+    /// back to the first. A closed polyline may repeat the first point at the end.
+    pub fn polyline(&mut self, points: &[PrimitivePoint], closed: bool) {
+        let mut points: Vec<&PrimitivePoint> = points
+            .iter()
+            .enumerate()
+            .filter(|(i, p)| *i == 0 || points[i - 1].position != p.position)
+            .map(|(_, p)| p)
+            .collect();
+        if closed && points.len() > 1 && points[0].position == points[points.len() - 1].position {
+            points.pop();
+        }
+        if points.len() < 2 {
+            return;
+        }
+        let n = points.len();
+        let position = |i: usize| Vector2::from(points[i % n].position);
+        let normal = |from: Vector2<f32>, to: Vector2<f32>| {
+            let direction = (to - from).normalize();
+            Vector2::new(-direction.y, direction.x)
+        };
+
+        // The left and right edge of the line at each point
+        let edges: Vec<(Vector2<f32>, Vector2<f32>)> = (0..n)
+            .map(|i| {
+                let half = points[i].thickness / 2.0;
+                let before = (i > 0 || closed).then(|| normal(position(i + n - 1), position(i)));
+                let after = (i + 1 < n || closed).then(|| normal(position(i), position(i + 1)));
+                let offset = match (before, after) {
+                    // The line turns back on itself, there is no corner to miter
+                    (Some(a), Some(b)) if (a + b).magnitude2() < f32::EPSILON => b * half,
+                    (Some(a), Some(b)) => {
+                        let miter = (a + b).normalize();
+                        let length = half / miter.dot(b).max(f32::EPSILON);
+                        miter * length.min(half * MITER_LIMIT)
+                    }
+                    (Some(a), None) => a * half,
+                    (None, Some(b)) => b * half,
+                    (None, None) => unreachable!(),
+                };
+                (position(i) + offset, position(i) - offset)
+            })
+            .collect();
+
+        let segments = if closed { n } else { n - 1 };
+        for i in 0..segments {
+            let j = (i + 1) % n;
+            let (left_i, right_i) = edges[i];
+            let (left_j, right_j) = edges[j];
+            let (color_i, color_j) = (points[i].color, points[j].color);
+            self.push(left_i, color_i);
+            self.push(right_i, color_i);
+            self.push(left_j, color_j);
+            self.push(right_i, color_i);
+            self.push(right_j, color_j);
+            self.push(left_j, color_j);
+        }
+    }
+
+    /// A filled triangle, the colors of the corners are blended across it
+    pub fn triangle(&mut self, corners: [PrimitivePoint; 3]) {
+        for corner in corners {
+            self.push(corner.position.into(), corner.color);
+        }
+    }
+
+    /// A filled convex polygon. The points are connected as a fan from the first point,
+    /// a concave polygon is drawn with overlapping triangles.
+    pub fn convex_polygon(&mut self, points: &[PrimitivePoint]) {
+        debug_assert!(is_convex(points), "Expected the points of a convex polygon");
+        for i in 1..points.len().saturating_sub(1) {
+            self.triangle([points[0], points[i], points[i + 1]]);
+        }
+    }
+
+    /// The outline of a circle approximated by `segments` lines
+    pub fn circle_outline(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        segments: usize,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        let points: Vec<PrimitivePoint> = (0..segments.max(3))
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::TAU / segments.max(3) as f32;
+                let position = [
+                    center[0] + radius * angle.cos(),
+                    center[1] + radius * angle.sin(),
+                ];
+                PrimitivePoint::new(position, color).thickness(thickness)
+            })
+            .collect();
+        self.polyline(&points, true);
+    }
+}
+
+fn is_convex(points: &[PrimitivePoint]) -> bool {
+    let n = points.len();
+    let mut sign = 0.0;
+    for i in 0..n {
+        let a = Vector2::from(points[i].position);
+        let b = Vector2::from(points[(i + 1) % n].position);
+        let c = Vector2::from(points[(i + 2) % n].position);
+        let cross = (b - a).perp_dot(c - b);
+        if cross != 0.0 {
+            if sign * cross < 0.0 {
+                return false;
+            }
+            sign = cross;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrimitivePoint, Primitives};
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+    fn positions(primitives: &Primitives) -> Vec<[f32; 2]> {
+        primitives
+            .vertices()
+            .iter()
+            .map(|v| [v.position[0], v.position[1]])
+            .collect()
+    }
+
+    fn assert_close(expected: [f32; 2], output: [f32; 2]) {
+        assert!(
+            (expected[0] - output[0]).abs() < 1e-4 && (expected[1] - output[1]).abs() < 1e-4,
+            "Expected {expected:?} but found {output:?}"
+        );
+    }
+
+    #[test]
+    fn given_horizontal_line_expect_quad_of_thickness_around_it() {
+        let mut primitives = Primitives::new();
+        primitives.line([0.0, 0.0], [10.0, 0.0], 2.0, WHITE);
+
+        assert_eq!(
+            vec![
+                [0.0, 1.0],
+                [0.0, -1.0],
+                [10.0, 1.0],
+                [0.0, -1.0],
+                [10.0, -1.0],
+                [10.0, 1.0]
+            ],
+            positions(&primitives)
+        );
+    }
+
+    #[test]
+    fn given_thickness_and_color_per_point_expect_them_at_the_ends() {
+        let mut primitives = Primitives::new();
+        primitives.polyline(
+            &[
+                PrimitivePoint::new([0.0, 0.0], WHITE).thickness(2.0),
+                PrimitivePoint::new([0.0, 10.0], RED).thickness(6.0),
+            ],
+            false,
+        );
+        let vertices = primitives.vertices();
+
+        assert_close(
+            [-1.0, 0.0],
+            [vertices[0].position[0], vertices[0].position[1]],
+        );
+        assert_close(
+            [-3.0, 10.0],
+            [vertices[2].position[0], vertices[2].position[1]],
+        );
+        assert_eq!(WHITE, vertices[0].color);
+        assert_eq!(RED, vertices[2].color);
+    }
+
+    #[test]
+    fn given_right_angle_expect_mitered_corner() {
+        let mut primitives = Primitives::new();
+        let points = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]]
+            .map(|p| PrimitivePoint::new(p, WHITE).thickness(2.0));
+        primitives.polyline(&points, false);
+        let positions = positions(&primitives);
+
+        // The outer corner of the first segment is shared with the second segment
+        assert_eq!(12, positions.len());
+        assert_close([9.0, 1.0], positions[2]);
+        assert_close([11.0, -1.0], positions[4]);
+        assert_close([9.0, 1.0], positions[6]);
+        assert_close([11.0, -1.0], positions[7]);
+    }
+
+    #[test]
+    fn given_sharp_corner_expect_miter_cut_at_limit() {
+        let mut primitives = Primitives::new();
+        let points = [[0.0, 0.0], [10.0, 0.0], [0.0, 0.1]]
+            .map(|p| PrimitivePoint::new(p, WHITE).thickness(2.0));
+        primitives.polyline(&points, false);
+
+        for [x, y] in positions(&primitives) {
+            assert!(((x - 10.0).powi(2) + y.powi(2)).sqrt() <= 4.0 + 1e-4 || x < 9.0);
+        }
+    }
+
+    #[test]
+    fn given_line_turning_back_on_itself_expect_finite_vertices() {
+        let mut primitives = Primitives::new();
+        let points = [[0.0, 0.0], [10.0, 0.0], [0.0, 0.0]]
+            .map(|p| PrimitivePoint::new(p, WHITE).thickness(2.0));
+        primitives.polyline(&points, false);
+        let positions = positions(&primitives);
+
+        assert_eq!(12, positions.len());
+        assert!(positions.iter().flatten().all(|c| c.is_finite()));
+        assert_close([10.0, -1.0], positions[2]);
+        assert_close([10.0, 1.0], positions[4]);
+    }
+
+    #[test]
+    fn given_closed_polyline_ending_at_first_point_expect_it_dropped() {
+        let mut primitives = Primitives::new();
+        let points = [
+            [0.0, 0.0],
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [0.0, 10.0],
+            [0.0, 0.0],
+        ]
+        .map(|p| PrimitivePoint::new(p, WHITE));
+        primitives.polyline(&points, true);
+        let mut expected = Primitives::new();
+        expected.polyline(&points[..4], true);
+
+        assert_eq!(4 * 6, primitives.vertices().len());
+        assert_eq!(positions(&expected), positions(&primitives));
+    }
+
+    #[test]
+    fn given_closed_polyline_expect_segment_back_to_start() {
+        let mut primitives = Primitives::new();
+        let points = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]
+            .map(|p| PrimitivePoint::new(p, WHITE));
+        primitives.polyline(&points, true);
+
+        assert_eq!(4 * 6, primitives.vertices().len());
+    }
+
+    #[test]
+    fn given_repeated_points_expect_them_skipped() {
+        let mut primitives = Primitives::new();
+        let points = [[0.0, 0.0], [0.0, 0.0], [10.0, 0.0]].map(|p| PrimitivePoint::new(p, WHITE));
+        primitives.polyline(&points, false);
+        primitives.polyline(&points[..2], false);
+
+        assert_eq!(6, primitives.vertices().len());
+        assert!(primitives
+            .vertices()
+            .iter()
+            .all(|v| v.position.iter().all(|c| c.is_finite())));
+    }
+
+    #[test]
+    fn given_convex_polygon_expect_fan_of_triangles() {
+        let mut primitives = Primitives::new();
+        let points = [
+            [0.0, 0.0],
+            [10.0, 0.0],
+            [12.0, 5.0],
+            [5.0, 10.0],
+            [-2.0, 5.0],
+        ]
+        .map(|p| PrimitivePoint::new(p, RED));
+        primitives.convex_polygon(&points);
+        let positions = positions(&primitives);
+
+        assert_eq!(9, positions.len());
+        assert_eq!([0.0, 0.0], positions[3]);
+        assert_eq!([12.0, 5.0], positions[4]);
+        assert_eq!([5.0, 10.0], positions[5]);
+    }
+
+    #[test]
+    fn given_triangle_expect_color_per_corner() {
+        let mut primitives = Primitives::new();
+        primitives.triangle([
+            PrimitivePoint::new([0.0, 0.0], RED),
+            PrimitivePoint::new([1.0, 0.0], WHITE),
+            PrimitivePoint::new([0.0, 1.0], RED),
+        ]);
+
+        let colors: Vec<[f32; 4]> = primitives.vertices().iter().map(|v| v.color).collect();
+        assert_eq!(vec![RED, WHITE, RED], colors);
+    }
+}
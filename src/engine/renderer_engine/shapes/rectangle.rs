@@ -1,4 +1,5 @@
 use std::mem;
+use crate::engine::renderer_engine::texture_registry::TextureHandle;
 use crate::engine::renderer_engine::vertex::Vertex;
 use super::Shape;
 
@@ -7,19 +8,21 @@ pub struct Rectangle {}
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
 pub struct RectangleInstance {
-    pub color: [f32; 3],
+    pub color: [f32; 4],
     pub position: [f32; 3], // TODO: rename to center (also for circle)
     pub rotation: f32,
     pub width: f32,
     pub height: f32,
     pub sprite_coord: [f32; 4],
+    /// The image the sprite coordinate is counted in cells of
+    pub texture: TextureHandle,
 }
 
 impl Default for RectangleInstance {
     fn default() -> Self {
         RectangleInstance {
-            color: [255.0,0.0,0.0], position: [0.0,0.0,0.0], width: 10.0, height: 10.0,
-            sprite_coord: [0.0,0.0,1.0,1.0], rotation: 0.0,
+            color: [1.0,0.0,0.0,1.0], position: [0.0,0.0,0.0], width: 10.0, height: 10.0,
+            sprite_coord: [0.0,0.0,1.0,1.0], rotation: 0.0, texture: TextureHandle::default(),
         }
     }
 }
@@ -54,37 +57,43 @@ impl Shape for Rectangle {
                 wgpu::VertexAttribute {
                     offset: 0,
                     shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x4,
                 },
                 // Position
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
                     shader_location: 3,
                     format: wgpu::VertexFormat::Float32x3,
                 },
                 // Rotation
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32,
                 },
                 // Width
                  wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32,
                 },
                 // Height
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
                     shader_location: 6,
                     format: wgpu::VertexFormat::Float32,
                 },
                 // Sprite Coord
                 wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    offset: mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
                     shader_location: 7,
                     format: wgpu::VertexFormat::Float32x4,
+                },
+                // Texture
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 14]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Uint32,
                 }
 
             ],
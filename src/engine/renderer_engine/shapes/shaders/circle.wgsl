@@ -4,22 +4,33 @@ struct VertexInput {
 
 struct InstanceInput {
     @location(2) position: vec3<f32>,
-    @location(3) color: vec3<f32>,
+    @location(3) color: vec4<f32>,
     @location(4) radius: f32,
     @location(5) rotation: f32,
     @location(6) sprite_coords: vec4<f32>,
+    @location(7) texture: u32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
     @location(1) tex_coord: vec2<f32>,
 };
 
-@group(0) @binding(0) var<uniform> window_size: vec2<f32>;
+struct TextureRegion {
+    origin: vec2<f32>,
+    cell_size: vec2<f32>,
+};
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
-@group(1) @binding(2) var<uniform> sprite_info: vec4<f32>;
+// One region of the atlas per TextureHandle, see MAX_TEXTURES
+@group(1) @binding(2) var<uniform> texture_regions: array<TextureRegion, 256>;
 
 
 fn scale_one_sprite_coordinate(
@@ -32,13 +43,13 @@ fn scale_one_sprite_coordinate(
 }
 
 fn compute_sprite_coordinate(
-    vertex_position: vec3<f32>, sprite_data: vec4<f32>, sprite_bbox: vec4<f32>
+    vertex_position: vec3<f32>, region: TextureRegion, sprite_bbox: vec4<f32>
 ) -> vec2<f32> {
-    let norm_cell_dim = sprite_data.zw / sprite_data.xy;
     let sprite_coordinate_upside_down = scale_one_sprite_coordinate(vertex_position.xy, sprite_bbox.xy, sprite_bbox.zw);
-    let sprite_coordinate = abs(sprite_coordinate_upside_down - vec2<f32>(0.0,1.0));
-    let norm_sprite_coordinate = sprite_coordinate*norm_cell_dim;
-    return norm_sprite_coordinate;
+    // Images start at the top, mirror y within the cells of the sprite
+    let sprite_coordinate = vec2<f32>(
+        sprite_coordinate_upside_down.x, sprite_bbox.y + sprite_bbox.w - sprite_coordinate_upside_down.y);
+    return region.origin + sprite_coordinate * region.cell_size;
 }
 
 @vertex
@@ -50,25 +61,24 @@ fn vs_main(
     var out: VertexOutput;
     out.color = instance.color;
 
-    let scaled_object_position = instance.position / vec3<f32>(window_size/2.0, 1.0);
-
     // Circle vertices are defined with radius 1.0 using vertices
-    let scaled_object_radius = vec2<f32>(instance.radius, instance.radius) / (window_size/2.0);
-    let scaled_vertex_position = vertex.position * vec3<f32>(scaled_object_radius, 1.0);
+    let scaled_vertex_position = vertex.position.xy * instance.radius;
 
     let rotation_matrix = mat2x2<f32>(
             vec2<f32>(cos(-instance.rotation), -sin(-instance.rotation)),
             vec2<f32>(sin(-instance.rotation),  cos(-instance.rotation)));
-    let rotated_vertex_position = rotation_matrix * scaled_vertex_position.xy;
+    let rotated_vertex_position = rotation_matrix * scaled_vertex_position;
 
-    out.clip_position = vec4<f32>(rotated_vertex_position, 0.0, 1.0) + vec4<f32>(scaled_object_position, 0.0);
+    // The z coordinate only orders the instances on the cpu, see SpriteBatch. Passing
+    // it on would clip instances outside of the depth range
+    let world_position = vec4<f32>(rotated_vertex_position + instance.position.xy, 0.0, 1.0);
+    out.clip_position = camera.view_projection * world_position;
 
     let none = vec4<f32>(-1.0,0.0,0.0,0.0);
     if (instance.sprite_coords.x == none.x) {
         out.tex_coord = vec2<f32>(-1.0,-1.0);
     } else {
-        let norm_cell_dim = sprite_info.zw / sprite_info.xy;
-        out.tex_coord = compute_sprite_coordinate(vertex.position, sprite_info, instance.sprite_coords); 
+        out.tex_coord = compute_sprite_coordinate(vertex.position, texture_regions[instance.texture], instance.sprite_coords);
     } 
 
     return out;
@@ -76,9 +86,11 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The output is premultiplied by alpha, see BlendMode
     if (in.tex_coord.x == -1.0 && in.tex_coord.y == -1.0) {
-        return vec4<f32>(in.color, 1.0);
+        return vec4<f32>(in.color.rgb * in.color.a, in.color.a);
     }
-    let texel = textureSample(texture, texture_sampler, in.tex_coord);
-    return texel.bgra;
+    let texel = textureSample(texture, texture_sampler, in.tex_coord).bgra;
+    let alpha = texel.a * in.color.a;
+    return vec4<f32>(texel.rgb * alpha, alpha);
 }
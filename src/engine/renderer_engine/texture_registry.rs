@@ -0,0 +1,326 @@
+use super::{
+    asset::sprite_sheet::SpriteSheet,
+    graphics_context::GraphicsContext,
+    render_error::RenderError,
+    util::{create_sampler, create_texture, write_texture_region},
+};
+
+/// The number of textures the circle and rectangle shaders can look up, the length of
+/// the region array in the shaders
+pub const MAX_TEXTURES: usize = 256;
+
+/// Pixels left free around every image, such that sampling at the edge of a cell does
+/// not bleed into the neighbouring image
+const ATLAS_PADDING: u32 = 1;
+
+/// Refers to an image in the texture registry. The default handle is the sprite sheet
+/// given to the engine builder.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextureHandle(u32);
+
+impl TextureHandle {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Where an image is placed in the atlas, normalized to the size of the atlas. The
+/// sprite coordinates of an instance are counted in cells of its image.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextureRegion {
+    pub origin: [f32; 2],
+    pub cell_size: [f32; 2],
+}
+
+/// Places images into one atlas on shelves, rows as high as the highest image placed on
+/// them. Images are never removed, the atlas fills up in the order they are loaded.
+#[derive(Clone, Debug)]
+pub struct TextureAtlas {
+    width: u32,
+    height: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+    cursor_x: u32,
+    regions: Vec<TextureRegion>,
+}
+
+impl TextureAtlas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            shelf_y: 0,
+            shelf_height: 0,
+            cursor_x: 0,
+            regions: vec![],
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Finds space for an image of `image_size` pixels divided into cells of
+    /// `cell_size` pixels. Returns its handle and the pixel it starts at.
+    pub fn insert(
+        &mut self,
+        image_size: (u32, u32),
+        cell_size: (u32, u32),
+    ) -> Result<(TextureHandle, [u32; 2]), RenderError> {
+        if self.regions.len() >= MAX_TEXTURES {
+            return Err(RenderError::TextureLimitExceeded {
+                limit: MAX_TEXTURES,
+            });
+        }
+        let (width, height) = image_size;
+        let atlas_full = RenderError::AtlasFull {
+            width,
+            height,
+            atlas_width: self.width,
+            atlas_height: self.height,
+        };
+        if width > self.width {
+            return Err(atlas_full);
+        }
+
+        // Start a new shelf if the image does not fit next to the last one
+        let (x, y, shelf_height) = match self.cursor_x + width <= self.width {
+            true => (self.cursor_x, self.shelf_y, self.shelf_height.max(height)),
+            false => (0, self.shelf_y + self.shelf_height + ATLAS_PADDING, height),
+        };
+        if y + shelf_height > self.height {
+            return Err(atlas_full);
+        }
+        self.cursor_x = x + width + ATLAS_PADDING;
+        self.shelf_y = y;
+        self.shelf_height = shelf_height;
+
+        let (atlas_width, atlas_height) = (self.width as f32, self.height as f32);
+        self.regions.push(TextureRegion {
+            origin: [x as f32 / atlas_width, y as f32 / atlas_height],
+            cell_size: [
+                cell_size.0 as f32 / atlas_width,
+                cell_size.1 as f32 / atlas_height,
+            ],
+        });
+        Ok((TextureHandle(self.regions.len() as u32 - 1), [x, y]))
+    }
+
+    pub fn region(&self, handle: TextureHandle) -> Option<&TextureRegion> {
+        self.regions.get(handle.index())
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+/// Images loaded at runtime and packed into one atlas texture, such that circles and
+/// rectangles using different images are drawn in one call. Instances pick their
+/// image with the `TextureHandle` of their render body.
+pub struct TextureRegistry {
+    atlas: TextureAtlas,
+    texture: wgpu::Texture,
+    sampler: wgpu::Sampler,
+    region_buffer: wgpu::Buffer,
+}
+
+impl TextureRegistry {
+    /// Creates an empty atlas of `size` by `size` pixels
+    pub fn new(ctx: &GraphicsContext, size: u32) -> Self {
+        let texture = create_texture(ctx, (size, size), Some("Texture Atlas"));
+        let sampler = create_sampler(&ctx.device, Some("Texture Atlas Sampler"));
+        let region_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture Atlas Regions"),
+            size: (MAX_TEXTURES * std::mem::size_of::<TextureRegion>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            atlas: TextureAtlas::new(size, size),
+            texture,
+            sampler,
+            region_buffer,
+        }
+    }
+
+    pub fn atlas(&self) -> &TextureAtlas {
+        &self.atlas
+    }
+
+    /// Copies the sprite sheet into the atlas. The handle stays valid for the lifetime
+    /// of the registry.
+    pub fn load(
+        &mut self,
+        ctx: &GraphicsContext,
+        sprite_sheet: &SpriteSheet,
+    ) -> Result<TextureHandle, RenderError> {
+        let image_size = sprite_sheet.sprite_buf.dimensions();
+        let cell_size = (
+            sprite_sheet.sprite_data[2] as u32,
+            sprite_sheet.sprite_data[3] as u32,
+        );
+        let (handle, origin) = self.atlas.insert(image_size, cell_size)?;
+        write_texture_region(ctx, &self.texture, origin, &sprite_sheet.sprite_buf);
+
+        let region = self
+            .atlas
+            .region(handle)
+            .expect("Expected a region for the inserted image");
+        let offset = handle.index() * std::mem::size_of::<TextureRegion>();
+        ctx.queue.write_buffer(
+            &self.region_buffer,
+            offset as wgpu::BufferAddress,
+            bytemuck::bytes_of(region),
+        );
+        Ok(handle)
+    }
+
+    /// Creates a bind group of the atlas, its sampler and regions for a render pass
+    pub(crate) fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Atlas Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let texture_view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Atlas Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.region_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        (bind_group, layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TextureAtlas, TextureHandle, TextureRegion, MAX_TEXTURES};
+    use crate::engine::renderer_engine::render_error::RenderError;
+
+    #[test]
+    fn given_images_fitting_next_to_each_other_expect_same_shelf() {
+        let mut atlas = TextureAtlas::new(64, 64);
+
+        assert_eq!(
+            Ok((TextureHandle(0), [0, 0])),
+            atlas.insert((16, 16), (8, 8))
+        );
+        assert_eq!(
+            Ok((TextureHandle(1), [17, 0])),
+            atlas.insert((30, 8), (10, 8))
+        );
+    }
+
+    #[test]
+    fn given_image_wider_than_space_left_expect_new_shelf_below_highest_image() {
+        let mut atlas = TextureAtlas::new(64, 64);
+        atlas.insert((40, 10), (10, 10)).unwrap();
+        atlas.insert((10, 20), (10, 10)).unwrap();
+
+        assert_eq!(
+            Ok((TextureHandle(2), [0, 21])),
+            atlas.insert((30, 5), (5, 5))
+        );
+    }
+
+    #[test]
+    fn given_image_placed_expect_region_normalized_to_atlas() {
+        let mut atlas = TextureAtlas::new(64, 32);
+        atlas.insert((16, 16), (8, 8)).unwrap();
+        let (handle, _) = atlas.insert((32, 16), (16, 8)).unwrap();
+
+        assert_eq!(
+            Some(&TextureRegion {
+                origin: [17.0 / 64.0, 0.0],
+                cell_size: [0.25, 0.25],
+            }),
+            atlas.region(handle)
+        );
+    }
+
+    #[test]
+    fn given_full_atlas_expect_error_and_atlas_unchanged() {
+        let mut atlas = TextureAtlas::new(32, 32);
+        atlas.insert((32, 20), (16, 16)).unwrap();
+
+        assert_eq!(
+            Err(RenderError::AtlasFull {
+                width: 16,
+                height: 16,
+                atlas_width: 32,
+                atlas_height: 32
+            }),
+            atlas.insert((16, 16), (16, 16))
+        );
+        assert_eq!(
+            Ok((TextureHandle(1), [0, 21])),
+            atlas.insert((16, 11), (16, 11))
+        );
+    }
+
+    #[test]
+    fn given_more_textures_than_the_shaders_hold_expect_error() {
+        let mut atlas = TextureAtlas::new(4096, 4096);
+        for _ in 0..MAX_TEXTURES {
+            atlas.insert((1, 1), (1, 1)).unwrap();
+        }
+
+        assert_eq!(
+            Err(RenderError::TextureLimitExceeded {
+                limit: MAX_TEXTURES
+            }),
+            atlas.insert((1, 1), (1, 1))
+        );
+    }
+}
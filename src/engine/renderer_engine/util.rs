@@ -33,10 +33,20 @@ pub fn texture_bind_group_from_texture(
                         count: None }
             ] }
     );
-    let bind_group = device.create_bind_group(
+    let bind_group = texture_bind_group_with_layout(device, &layout, sampler, texture);
+    (bind_group, layout)
+}
+
+/// Creates a bind group for the texture with an existing layout from
+/// `texture_bind_group_from_texture`, such that pipelines built with that layout accept it
+pub fn texture_bind_group_with_layout(
+    device: &wgpu::Device, layout: &wgpu::BindGroupLayout, sampler: &wgpu::Sampler,
+    texture: &wgpu::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(
         &wgpu::BindGroupDescriptor {
             label: Some("Gray Bind Group"),
-            layout: &layout,
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -48,8 +58,7 @@ pub fn texture_bind_group_from_texture(
                         &texture.create_view(&wgpu::TextureViewDescriptor::default())) 
                 }
             ] }
-    );
-    (bind_group, layout)
+    )
 }
 
 pub (crate) fn create_texture(
@@ -77,6 +86,14 @@ pub (crate) fn create_texture(
 pub (crate) fn write_texture(
     ctx: &GraphicsContext, texture: &wgpu::Texture, 
     data: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+) {
+    write_texture_region(ctx, texture, [0, 0], data);
+}
+
+/// Writes the image into the texture with its top left corner at `origin`
+pub (crate) fn write_texture_region(
+    ctx: &GraphicsContext, texture: &wgpu::Texture, origin: [u32; 2],
+    data: &ImageBuffer<Rgba<u8>, Vec<u8>>,
 ) {
     let dimensions = data.dimensions();
     let texture_size = wgpu::Extent3d {
@@ -84,12 +101,12 @@ pub (crate) fn write_texture(
     };    
     ctx.queue.write_texture(
         wgpu::ImageCopyTexture {
-            texture: &texture,
+            texture,
             mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
+            origin: wgpu::Origin3d { x: origin[0], y: origin[1], z: 0 },
             aspect: wgpu::TextureAspect::All,
         }, 
-        &data, 
+        data, 
         wgpu::ImageDataLayout {
             offset: 0, 
             bytes_per_row: Some(4*dimensions.0),
@@ -1,14 +1,19 @@
-pub fn red() -> [f32; 3] {
-    [255.0, 0.0, 0.0]
+pub fn red() -> [f32; 4] {
+    [1.0, 0.0, 0.0, 1.0]
 }
 
-pub fn green() -> [f32; 3] {
-    [0.0, 255.0, 0.0]
+pub fn green() -> [f32; 4] {
+    [0.0, 1.0, 0.0, 1.0]
 }
 
-pub fn blue() -> [f32; 3] {
-    [0.0, 0.0, 255.0]
+pub fn blue() -> [f32; 4] {
+    [0.0, 0.0, 1.0, 1.0]
 }
-pub fn yellow() -> [f32; 3] {
-    [255., 255., 0.0]
+pub fn yellow() -> [f32; 4] {
+    [1., 1., 0.0, 1.0]
+}
+
+/// Returns the color with its alpha set to `alpha`, e.g. to fade out an entity
+pub fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], alpha]
 }
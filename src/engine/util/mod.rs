@@ -7,6 +7,7 @@ use super::{
 pub mod color;
 pub mod fixed_float;
 pub mod log_performance;
+pub mod physics_debug_draw;
 
 pub fn zero() -> [f32; 3] {
     [0., 0., 0.]
@@ -22,6 +23,7 @@ pub fn get_circle_instances(entities: &[EntityHandle]) -> Vec<CircleInstance> {
                 rotation: entity.rigid_body.unwrap().rotation,
                 radius,
                 sprite_coord: entity.render_body.unwrap().sprite_coord.coordinate,
+                texture: entity.render_body.unwrap().texture,
             }),
             _ => None,
         })
@@ -39,6 +41,7 @@ pub fn get_rectangle_instances(entities: &[EntityHandle]) -> Vec<RectangleInstan
                 width,
                 height,
                 sprite_coord: entity.render_body.unwrap().sprite_coord.coordinate,
+                texture: entity.render_body.unwrap().texture,
             }),
             _ => None,
         })
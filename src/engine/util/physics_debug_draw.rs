@@ -0,0 +1,371 @@
+use crate::engine::physics_engine::collision::{CollisionGraph, RigidBody, RigidBodyType};
+use crate::engine::physics_engine::constraint::Constraint;
+use crate::engine::physics_engine::query::QueryGrid;
+use crate::engine::renderer_engine::{PrimitivePoint, Primitives};
+
+use super::color::{blue, green, red, with_alpha, yellow};
+
+/// Number of lines the outline of a circle is drawn with
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Draws the state of the physics engine as lines on top of a frame, for tuning
+/// collisions. The overlay is off until toggled on, and every part of it can be left
+/// out on its own.
+///
+/// Clear the overlay at the start of a frame, draw the state into it and add
+/// `primitives()` to the frame with `Frame::primitives`.
+#[derive(Clone, Debug)]
+pub struct PhysicsDebugDraw {
+    enabled: bool,
+    outlines: bool,
+    aabbs: bool,
+    cells: bool,
+    contacts: bool,
+    velocities: bool,
+    constraints: bool,
+    thickness: f32,
+    velocity_scale: f32,
+    normal_length: f32,
+    primitives: Primitives,
+}
+
+impl Default for PhysicsDebugDraw {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            outlines: true,
+            aabbs: true,
+            cells: true,
+            contacts: true,
+            velocities: true,
+            constraints: true,
+            thickness: 2.0,
+            velocity_scale: 10.0,
+            normal_length: 20.0,
+            primitives: Primitives::new(),
+        }
+    }
+}
+
+impl PhysicsDebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn outlines(mut self, outlines: bool) -> Self {
+        self.outlines = outlines;
+        self
+    }
+
+    pub fn aabbs(mut self, aabbs: bool) -> Self {
+        self.aabbs = aabbs;
+        self
+    }
+
+    /// Draws the occupied cells of the broadphase
+    pub fn cells(mut self, cells: bool) -> Self {
+        self.cells = cells;
+        self
+    }
+
+    /// Draws the contact points with their normal and penetration depth
+    pub fn contacts(mut self, contacts: bool) -> Self {
+        self.contacts = contacts;
+        self
+    }
+
+    pub fn velocities(mut self, velocities: bool) -> Self {
+        self.velocities = velocities;
+        self
+    }
+
+    pub fn constraints(mut self, constraints: bool) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Thickness of the lines in world units
+    pub fn thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Velocities are drawn as the distance moved in this many steps
+    pub fn velocity_scale(mut self, velocity_scale: f32) -> Self {
+        self.velocity_scale = velocity_scale;
+        self
+    }
+
+    /// Length the normals are drawn with, the penetration depth is drawn over it
+    pub fn normal_length(mut self, normal_length: f32) -> Self {
+        self.normal_length = normal_length;
+        self
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The lines drawn since the last `clear`, empty while the overlay is off
+    pub fn primitives(&self) -> &Primitives {
+        &self.primitives
+    }
+
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+    }
+
+    /// Draws the outline, bounding box and velocity of every body
+    pub fn bodies<'a, I>(&mut self, bodies: I)
+    where
+        I: Iterator<Item = &'a RigidBody>,
+    {
+        if !self.enabled {
+            return;
+        }
+        for body in bodies.filter(|b| b.body_type != RigidBodyType::Unknown) {
+            let center = [body.position.x, body.position.y];
+            if self.aabbs {
+                let aabb = body.aabb();
+                self.rectangle(
+                    [aabb.min.x, aabb.min.y],
+                    [aabb.max.x, aabb.max.y],
+                    with_alpha(blue(), 0.5),
+                );
+            }
+            if self.outlines {
+                self.outline(body);
+            }
+            if self.velocities {
+                let end = [
+                    center[0] + body.velocity.x * self.velocity_scale,
+                    center[1] + body.velocity.y * self.velocity_scale,
+                ];
+                self.primitives.line(center, end, self.thickness, yellow());
+            }
+        }
+    }
+
+    /// Draws the contact points of every collision, the normal in red and the
+    /// penetration depth along it in yellow
+    pub fn collision_graph(&mut self, graph: &CollisionGraph) {
+        if !self.enabled || !self.contacts {
+            return;
+        }
+        for node in graph.collisions.iter() {
+            let normal = node.info.normal;
+            for contact in node.info.contact_points.points() {
+                let point = [contact.point[0], contact.point[1]];
+                let along =
+                    |length: f32| [point[0] + normal[0] * length, point[1] + normal[1] * length];
+                self.primitives
+                    .line(point, along(self.normal_length), self.thickness, red());
+                self.primitives
+                    .line(point, along(contact.depth), self.thickness * 2.0, yellow());
+
+                let size = self.thickness * 2.0;
+                let corners = [[-size, -size], [size, -size], [size, size], [-size, size]]
+                    .map(|[x, y]| PrimitivePoint::new([point[0] + x, point[1] + y], red()));
+                self.primitives.convex_polygon(&corners);
+            }
+        }
+    }
+
+    /// Draws the cells of the grid holding at least one body, get the grid of a
+    /// broadphase from `SpatialQuery::query_grid`. The grid has the cells of the
+    /// broadphase, such as the non-square cells of `SpatialSubdivision`.
+    pub fn query_grid(&mut self, grid: &QueryGrid) {
+        if !self.enabled || !self.cells {
+            return;
+        }
+        for cell in grid.occupied_cells() {
+            self.rectangle(
+                [cell.min.x, cell.min.y],
+                [cell.max.x, cell.max.y],
+                with_alpha(green(), 0.3),
+            );
+        }
+    }
+
+    /// Draws the walls the constraint keeps the bodies within
+    pub fn constraint(&mut self, constraint: &dyn Constraint) {
+        if !self.enabled || !self.constraints {
+            return;
+        }
+        for segment in constraint.bounds() {
+            self.primitives.line(
+                [segment.start[0], segment.start[1]],
+                [segment.end[0], segment.end[1]],
+                self.thickness,
+                red(),
+            );
+        }
+    }
+
+    fn outline(&mut self, body: &RigidBody) {
+        let color = green();
+        match body.body_type {
+            RigidBodyType::Rectangle { .. } => {
+                let corners: Vec<PrimitivePoint> = body
+                    .corners()
+                    .iter()
+                    .map(|c| PrimitivePoint::new([c[0], c[1]], color).thickness(self.thickness))
+                    .collect();
+                self.primitives.polyline(&corners, true);
+            }
+            RigidBodyType::Circle { radius } | RigidBodyType::Custom { radius, .. } => {
+                let center = [body.position.x, body.position.y];
+                self.primitives.circle_outline(
+                    center,
+                    radius,
+                    CIRCLE_SEGMENTS,
+                    self.thickness,
+                    color,
+                );
+                // The radius towards the rotation shows how the circle spins
+                let edge = [
+                    center[0] + radius * body.rotation.cos(),
+                    center[1] + radius * body.rotation.sin(),
+                ];
+                self.primitives.line(center, edge, self.thickness, color);
+            }
+            RigidBodyType::Unknown => (),
+        }
+    }
+
+    fn rectangle(&mut self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        let corners = [min, [max[0], min[1]], max, [min[0], max[1]]]
+            .map(|p| PrimitivePoint::new(p, color).thickness(self.thickness));
+        self.primitives.polyline(&corners, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhysicsDebugDraw;
+    use crate::engine::physics_engine::broadphase::{BroadPhase, SpatialSubdivision};
+    use crate::engine::physics_engine::collision::{
+        Aabb, CollisionGraph, CollisionGraphNode, CollisionInformation, ContactManifold, RigidBody,
+        RigidBodyBuilder, RigidBodyType,
+    };
+    use crate::engine::physics_engine::query::{QueryGrid, SpatialQuery};
+
+    fn circle() -> RigidBody {
+        RigidBodyBuilder::default()
+            .id(0)
+            .position([0.0, 0.0, 0.0])
+            .velocity([1.0, 0.0, 0.0])
+            .body_type(RigidBodyType::Circle { radius: 10.0 })
+            .build()
+    }
+
+    #[test]
+    fn given_disabled_overlay_expect_nothing_drawn() {
+        let mut debug_draw = PhysicsDebugDraw::new();
+        debug_draw.bodies([circle()].iter());
+
+        assert!(debug_draw.primitives().is_empty());
+    }
+
+    #[test]
+    fn given_toggled_overlay_expect_drawn_until_toggled_again() {
+        let mut debug_draw = PhysicsDebugDraw::new();
+        debug_draw.toggle();
+        debug_draw.bodies([circle()].iter());
+        assert!(!debug_draw.primitives().is_empty());
+
+        debug_draw.toggle();
+        debug_draw.clear();
+        debug_draw.bodies([circle()].iter());
+        assert!(debug_draw.primitives().is_empty());
+    }
+
+    #[test]
+    fn given_only_velocities_expect_one_line_per_body() {
+        let mut debug_draw = PhysicsDebugDraw::new()
+            .enabled(true)
+            .outlines(false)
+            .aabbs(false)
+            .velocity_scale(5.0);
+        debug_draw.bodies([circle(), circle()].iter());
+
+        let vertices = debug_draw.primitives().vertices();
+        assert_eq!(2 * 6, vertices.len());
+        assert_eq!(5.0, vertices[2].position[0]);
+    }
+
+    #[test]
+    fn given_collision_expect_normal_depth_and_marker_per_contact_point() {
+        let mut debug_draw = PhysicsDebugDraw::new().enabled(true);
+        let graph = CollisionGraph {
+            collisions: vec![CollisionGraphNode {
+                body_i_idx: 0,
+                body_j_idx: 1,
+                info: CollisionInformation::new(
+                    [0.0, 1.0, 0.0],
+                    ContactManifold::single([0.0, 0.0, 0.0], 3.0),
+                ),
+            }],
+        };
+        debug_draw.collision_graph(&graph);
+
+        // Two lines and a square of two triangles
+        let vertices = debug_draw.primitives().vertices();
+        assert_eq!(6 + 6 + 6, vertices.len());
+        assert_eq!(20.0, vertices[2].position[1]);
+        assert_eq!(3.0, vertices[8].position[1]);
+    }
+
+    #[test]
+    fn given_query_grid_expect_outline_per_occupied_cell() {
+        let mut debug_draw = PhysicsDebugDraw::new().enabled(true);
+        let mut grid = QueryGrid::new([0.0, 0.0, 0.0], 10.0);
+        grid.insert_aabb(0, &Aabb::from_center([10.0, 10.0, 0.0], 5.0, 5.0));
+        debug_draw.query_grid(&grid);
+
+        // The box overlaps four cells, each drawn as four lines
+        assert_eq!(4 * 4 * 6, debug_draw.primitives().vertices().len());
+    }
+
+    #[test]
+    fn given_grid_of_spatial_subdivision_expect_cells_with_its_width_and_height() {
+        let mut debug_draw = PhysicsDebugDraw::new().enabled(true).thickness(0.0);
+        let mut spatial_subdivision = SpatialSubdivision::new();
+        let plank = RigidBodyBuilder::default()
+            .id(0)
+            .position([100.0, 2.0, 0.0])
+            .body_type(RigidBodyType::Rectangle {
+                width: 200.0,
+                height: 4.0,
+            })
+            .build();
+        spatial_subdivision.collision_detection([plank].iter());
+        debug_draw.query_grid(spatial_subdivision.query_grid());
+
+        let statistics = spatial_subdivision.statistics();
+        let (min_x, max_x, min_y, max_y) = debug_draw.primitives().vertices().iter().fold(
+            (f32::MAX, f32::MIN, f32::MAX, f32::MIN),
+            |(min_x, max_x, min_y, max_y), v| {
+                (
+                    min_x.min(v.position[0]),
+                    max_x.max(v.position[0]),
+                    min_y.min(v.position[1]),
+                    max_y.max(v.position[1]),
+                )
+            },
+        );
+        assert_eq!(statistics.origin[0], min_x);
+        assert_eq!(statistics.origin[1], min_y);
+        assert_eq!(statistics.cell_width, max_x - min_x);
+        assert_eq!(statistics.cell_height, max_y - min_y);
+    }
+}